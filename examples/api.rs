@@ -0,0 +1,59 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonprooftoken::{
+    api,
+    encoding::SerializationType,
+    jpa::algs::{PresentationProofAlgorithm, ProofAlgorithm},
+    jpt::claims::JptClaims,
+    jwk::{key::Jwk, types::KeyPairSubtype},
+    jwp::header::{IssuerProtectedHeader, PresentationProtectedHeader},
+};
+
+fn main() {
+    let issuer_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issuer_public_jwk = issuer_jwk.to_public().unwrap();
+
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    jpt_claims.set_claim(Some("age"), 42, false);
+
+    let mut presentation_header =
+        PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF);
+    presentation_header.set_nonce(Some("wrmBRkKtXjQ".to_owned()));
+
+    let issued = api::issue(
+        IssuerProtectedHeader::new(ProofAlgorithm::BBS),
+        jpt_claims,
+        &issuer_jwk,
+        SerializationType::COMPACT,
+    )
+    .unwrap();
+    let presented = api::present(
+        &issued,
+        presentation_header,
+        &["iss", "name"],
+        &issuer_public_jwk,
+        SerializationType::COMPACT,
+    )
+    .unwrap();
+    let disclosed =
+        api::verify_presented(&presented, &issuer_public_jwk, SerializationType::COMPACT).unwrap();
+
+    println!(
+        "Disclosed claims: {}",
+        serde_json::to_string(&disclosed).unwrap()
+    );
+}
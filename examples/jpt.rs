@@ -50,6 +50,7 @@ fn main() {
     );
 
     let issued_jwp = JwpIssuedBuilder::new(issued_header, jpt_claims)
+        .unwrap()
         .build(&bbs_jwk)
         .unwrap();
 
@@ -64,12 +65,7 @@ fn main() {
 
     assert_eq!(issued_jwp, decoded_issued_jwp);
 
-    let mut presentation_header = PresentationProtectedHeader::new(
-        decoded_issued_jwp
-            .get_issuer_protected_header()
-            .alg()
-            .into(),
-    );
+    let mut presentation_header = PresentationProtectedHeader::from_issued(&decoded_issued_jwp);
     presentation_header.set_aud(Some("https://recipient.example.com".to_owned()));
     presentation_header.set_nonce(Some("wrmBRkKtXjQ".to_owned()));
 
@@ -0,0 +1,103 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verify a compact JWP read from stdin with the issuer public key, printing the disclosed claims.
+//!
+//! ```text
+//! cargo run --example verify -- issuer_jwk.json < token.jpt
+//! ```
+//!
+//! The key file holds either a JWK or a JWK Set. From a set, the key is selected with the `kid`
+//! of the issuer protected header, or taken as it is if it is the only one.
+
+use std::{
+    env, fs,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use jsonprooftoken::{
+    api,
+    encoding::SerializationType,
+    jwk::{key::Jwk, set::JwkSet},
+    jwp::{issued::JwpIssuedDecoder, presented::JwpPresentedDecoder},
+};
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(claims) => {
+            println!("{claims}");
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("error: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<String, String> {
+    let path = env::args()
+        .nth(1)
+        .ok_or("usage: verify <issuer jwk file> < token")?;
+    let keys = fs::read_to_string(&path).map_err(|e| format!("cannot read {path}: {e}"))?;
+    let mut token = String::new();
+    io::stdin()
+        .read_to_string(&mut token)
+        .map_err(|e| format!("cannot read the token from stdin: {e}"))?;
+    let token = token.trim();
+
+    let claims = match token.split('.').count() {
+        3 => {
+            let decoded = JwpIssuedDecoder::decode(token, SerializationType::COMPACT)
+                .map_err(|e| format!("cannot decode the issued JWP: {e}"))?;
+            let jwk = select_key(&keys, decoded.get_header().kid())?;
+            eprintln!("issued JWP");
+            api::verify_issued(token, &jwk, SerializationType::COMPACT)
+        }
+        4 => {
+            let decoded = JwpPresentedDecoder::decode(token, SerializationType::COMPACT)
+                .map_err(|e| format!("cannot decode the presented JWP: {e}"))?;
+            let jwk = select_key(&keys, decoded.get_issuer_header().kid())?;
+            eprintln!("presented JWP");
+            api::verify_presented(token, &jwk, SerializationType::COMPACT)
+        }
+        segments => {
+            return Err(format!(
+                "expected 3 (issued) or 4 (presented) segments, found {segments}"
+            ))
+        }
+    }
+    .map_err(|e| format!("verification failed: {e}"))?;
+
+    serde_json::to_string_pretty(&claims).map_err(|e| e.to_string())
+}
+
+fn select_key(keys: &str, kid: Option<&String>) -> Result<Jwk, String> {
+    if let Ok(jwk) = serde_json::from_str::<Jwk>(keys) {
+        return Ok(jwk);
+    }
+    let (set, skipped) = JwkSet::from_json_lenient(keys);
+    for entry in skipped {
+        eprintln!("skipped {entry}");
+    }
+    match (kid, set.keys.as_slice()) {
+        (Some(kid), _) => set
+            .find(kid)
+            .cloned()
+            .ok_or_else(|| format!("no key with kid {kid}")),
+        (None, [jwk]) => Ok(jwk.clone()),
+        (None, _) => Err("the token has no kid to select the key from the set".to_owned()),
+    }
+}
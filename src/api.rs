@@ -0,0 +1,122 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! One-call issuance, presentation and verification.
+//!
+//! These functions wrap the builders and decoders in [`crate::jwp`] for the common case:
+//!
+//! ```
+//! use jsonprooftoken::{
+//!     api, encoding::SerializationType, jpa::algs::ProofAlgorithm, jpt::claims::JptClaims,
+//!     jwk::{key::Jwk, types::KeyPairSubtype}, jwp::header::IssuerProtectedHeader,
+//! };
+//!
+//! let jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+//! let mut claims = JptClaims::new();
+//! claims.set_iss("https://issuer.example".to_owned());
+//!
+//! let header = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+//! let token = api::issue(header, claims.clone(), &jwk, SerializationType::COMPACT).unwrap();
+//! let verified = api::verify_issued(&token, &jwk.to_public().unwrap(), SerializationType::COMPACT).unwrap();
+//! assert_eq!(verified, claims);
+//! ```
+
+use alloc::string::String;
+use core::iter::zip;
+
+use crate::{
+    encoding::SerializationType,
+    errors::CustomError,
+    jpt::{
+        claims::{Claims, JptClaims},
+        payloads::{PayloadType, Payloads},
+    },
+    jwk::key::Jwk,
+    jwp::{
+        header::{IssuerProtectedHeader, PresentationProtectedHeader},
+        issued::{JwpIssuedBuilder, JwpIssuedDecoder},
+        presented::{JwpPresentedBuilder, JwpPresentedDecoder},
+    },
+};
+
+/// Issue a JWP with the issuer private key and return it encoded with `serialization`
+pub fn issue(
+    header: IssuerProtectedHeader,
+    claims: JptClaims,
+    jwk: &Jwk,
+    serialization: SerializationType,
+) -> Result<String, CustomError> {
+    JwpIssuedBuilder::new(header, claims)?
+        .build(jwk)?
+        .encode(serialization)
+}
+
+/// Decode and verify an issued JWP with the issuer public key, returning its claims
+pub fn verify_issued(
+    token: &str,
+    jwk: &Jwk,
+    serialization: SerializationType,
+) -> Result<JptClaims, CustomError> {
+    let issued_jwp = JwpIssuedDecoder::decode(token, serialization)?.verify(jwk)?;
+    disclosed_claims(
+        issued_jwp.get_issuer_protected_header(),
+        issued_jwp.get_payloads(),
+    )
+}
+
+/// Verify an issued JWP with the issuer public key and present it, disclosing only the claims in `disclosed`
+/// (every other claim is undisclosed, and unknown claims are a [`CustomError::SelectiveDisclosureError`]). Returns the presentation encoded with `serialization`
+pub fn present(
+    issued_token: &str,
+    presentation_header: PresentationProtectedHeader,
+    disclosed: &[&str],
+    jwk: &Jwk,
+    serialization: SerializationType,
+) -> Result<String, CustomError> {
+    let issued_jwp = JwpIssuedDecoder::decode(issued_token, serialization)?.verify(jwk)?;
+    JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(presentation_header)
+        .disclose_only(disclosed)?
+        .build(jwk)?
+        .encode(serialization)
+}
+
+/// Decode and verify a presented JWP with the issuer public key, returning the disclosed claims
+pub fn verify_presented(
+    token: &str,
+    jwk: &Jwk,
+    serialization: SerializationType,
+) -> Result<JptClaims, CustomError> {
+    let presented_jwp = JwpPresentedDecoder::decode(token, serialization)?.verify(jwk)?;
+    disclosed_claims(
+        presented_jwp.get_issuer_protected_header(),
+        presented_jwp.get_payloads(),
+    )
+}
+
+/// Claims with a disclosed payload (or proven equal to a constant), without salts
+fn disclosed_claims(
+    header: &IssuerProtectedHeader,
+    payloads: &Payloads,
+) -> Result<JptClaims, CustomError> {
+    let (names, values) = zip(header.claims().into_iter().flatten(), payloads)
+        .filter(|(_, (_, payload_type))| *payload_type != PayloadType::Undisclosed)
+        .map(|(name, payload)| (name.clone(), payload.clone()))
+        .unzip();
+    let mut payloads = Payloads(values);
+    if header.salted() {
+        payloads = payloads.unsalted()?;
+    }
+    JptClaims::from_claims_and_payloads(&Claims(names), &payloads)
+}
@@ -12,20 +12,98 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use data_encoding::BASE64URL_NOPAD;
-use serde::Serialize;
+use alloc::{
+    borrow::ToOwned,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use data_encoding::{BASE64URL, BASE64URL_NOPAD};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use serde_json::Value;
 
+use crate::errors::CustomError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SerializationType {
     COMPACT,
     JSON,
 }
 
+/// Compression of a serialized JWP for transport (e.g. QR codes).
+///
+/// The compressed token starts with a byte identifying the scheme. Only the serialized token is compressed,
+/// the bytes covered by the proof are unchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// DEFLATE (RFC 1951)
+    Deflate,
+}
+
+impl Compression {
+    /// Largest token accepted when decompressing
+    pub const MAX_DECOMPRESSED_LEN: usize = 1 << 20;
+
+    fn marker(self) -> u8 {
+        match self {
+            Compression::Deflate => 0x01,
+        }
+    }
+
+    fn from_marker(marker: u8) -> Option<Self> {
+        match marker {
+            0x01 => Some(Compression::Deflate),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn compress(bytes: &[u8], compression: Compression) -> Vec<u8> {
+    let mut compressed = alloc::vec![compression.marker()];
+    match compression {
+        Compression::Deflate => compressed.extend(miniz_oxide::deflate::compress_to_vec(bytes, 10)),
+    }
+    compressed
+}
+
+pub(crate) fn decompress(bytes: &[u8]) -> Result<Vec<u8>, CustomError> {
+    let (marker, compressed) = bytes
+        .split_first()
+        .ok_or_else(|| CustomError::DecompressionError("empty token".to_owned()))?;
+    match Compression::from_marker(*marker) {
+        Some(Compression::Deflate) => miniz_oxide::inflate::decompress_to_vec_with_limit(
+            compressed,
+            Compression::MAX_DECOMPRESSED_LEN,
+        )
+        .map_err(|e| CustomError::DecompressionError(e.to_string())),
+        None => Err(CustomError::DecompressionError(format!(
+            "unknown compression {marker:#04x}"
+        ))),
+    }
+}
+
 pub fn base64url_encode<T: AsRef<[u8]>>(bytes: T) -> String {
     BASE64URL_NOPAD.encode(bytes.as_ref())
 }
 
-pub fn base64url_decode<T: AsRef<[u8]>>(bytes: T) -> Vec<u8> {
-    BASE64URL_NOPAD.decode(bytes.as_ref()).unwrap()
+/// Length of the base64url (no padding) encoding of `len` bytes
+pub fn base64url_encoded_len(len: usize) -> usize {
+    BASE64URL_NOPAD.encode_len(len)
+}
+
+pub fn base64url_decode<T: AsRef<[u8]>>(bytes: T) -> Result<Vec<u8>, CustomError> {
+    Ok(BASE64URL_NOPAD.decode(bytes.as_ref())?)
+}
+
+/// Same as [`base64url_decode`], also accepting input padded with `=` as emitted by some non-conformant producers.
+/// The padding, when present, must be complete. Used to decode the segments of a JWP; encoding never pads.
+pub fn base64url_decode_lenient<T: AsRef<[u8]>>(bytes: T) -> Result<Vec<u8>, CustomError> {
+    let bytes = bytes.as_ref();
+    if bytes.last() == Some(&b'=') {
+        Ok(BASE64URL.decode(bytes)?)
+    } else {
+        base64url_decode(bytes)
+    }
 }
 
 // Encodes a struct in base64url
@@ -34,10 +112,47 @@ pub fn base64url_encode_serializable<T: Serialize>(value: T) -> String {
     base64url_encode(bytes)
 }
 
+/// Sort the keys of the objects in `value`, recursively
+pub(crate) fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<(String, Value)> = object.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+/// Serializes a JSON value with the keys of its objects sorted, as [`sort_keys`] but without cloning the value
+pub(crate) struct SortedKeys<'a>(pub(crate) &'a Value);
+
+impl Serialize for SortedKeys<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Object(object) => {
+                let mut entries: Vec<(&String, &Value)> = object.iter().collect();
+                entries.sort_by_key(|(key, _)| *key);
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, &SortedKeys(value))?;
+                }
+                map.end()
+            }
+            Value::Array(values) => serializer.collect_seq(values.iter().map(SortedKeys)),
+            value => value.serialize(serializer),
+        }
+    }
+}
+
 pub struct EncondingKey {
-    //TODO: family attribute implement something like this
-    //             ProofAlgorithm::EdDSA => AlgorithmFamily::Ed,
-    //             ProofAlgorithm::BBS_X (or BBS_BLS12381_SHA256, BBS_BLS12381_SHAKE256) => AlgorithmFamily::Bls12381
+    //TODO: family attribute, see Jwk::algorithm_family
 }
 
 //TODO: implement From<Jwk> trait that transform a Jwk into and EncodingKey
@@ -12,12 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::string::{String, ToString};
 use thiserror::Error;
 
+use crate::jwk::key::KeyOps;
+
 #[derive(Error, Debug)]
 pub enum CustomError {
-    #[error("Error during generation of a proof")]
-    ProofGenerationError(String),
+    #[error("Error during generation of a {alg} proof over {payload_count} payloads: {message}")]
+    ProofGenerationError {
+        alg: String,
+        message: String,
+        payload_count: usize,
+    },
 
     #[error("Error during verification of a proof")]
     ProofVerificationError(String),
@@ -40,8 +47,8 @@ pub enum CustomError {
     #[error("Index out of bounds!")]
     IndexOutOfBounds,
 
-    #[error("Incomplete Jwp build")]
-    IncompleteJwpBuild(IncompleteJwpBuild),
+    #[error("Incomplete Jwp build: {0}")]
+    IncompleteJwpBuild(#[source] IncompleteJwpBuild),
 
     #[error("Error during JSON flattening process")]
     FlatteningError,
@@ -49,14 +56,134 @@ pub enum CustomError {
     #[error("Error during selective disclosure of an attribute")]
     SelectiveDisclosureError,
 
-    #[error("Serialization failed")]
-    SerializationError,
+    #[error("Serialization failed: {0}")]
+    SerializationError(String),
 
     #[error("Invalid JWK")]
     InvalidJwk,
 
     #[error("Curve is not supported")]
     CurveNotSupported,
+
+    #[error("Key with curve {crv} is not compatible with algorithm {alg}")]
+    IncompatibleKeyAlgorithm { alg: String, crv: String },
+
+    #[error("Duplicate claim: {0}")]
+    DuplicateClaim(String),
+
+    #[error("Invalid proof length: expected {expected} bytes, got {got}")]
+    InvalidProofLength { expected: usize, got: usize },
+
+    #[error("Holder binding verification failed")]
+    HolderBindingError,
+
+    #[error("Invalid JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Invalid PEM")]
+    InvalidPem,
+
+    #[error("Duplicate header parameter: {0}")]
+    DuplicateHeaderParameter(String),
+
+    #[error("Predicate not satisfied by claim: {0}")]
+    PredicateNotSatisfied(String),
+
+    #[error("Every claim is disclosed")]
+    OverDisclosure,
+
+    #[error("No proof_key in the issuer protected header")]
+    MissingProofKey,
+
+    #[error("Presentation algorithm {presentation} cannot present a JWP issued with {issuer}")]
+    AlgorithmMismatch {
+        issuer: String,
+        presentation: String,
+    },
+
+    #[error("The JPT is expired")]
+    Expired,
+
+    #[error("The JPT is not valid yet")]
+    NotYetValid,
+
+    #[error("Invalid base64 encoding: {0}")]
+    Base64Error(String),
+
+    #[error("No claims in the issuer protected header and cid {0} could not be resolved")]
+    UnresolvedClaims(String),
+
+    #[error("Unsupported algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("JSON serialization is not supported yet")]
+    UnsupportedSerialization,
+
+    #[error("Expected typ {expected}, found {found:?}")]
+    TypeMismatch {
+        expected: String,
+        found: Option<String>,
+    },
+
+    #[error("Disclosed value does not match the digest of claim: {0}")]
+    DigestMismatch(String),
+
+    #[error("The key_ops or use of the JWK do not permit {0:?}")]
+    KeyOpsNotPermitted(KeyOps),
+
+    #[error("Issuer {header} in the header does not match the iss claim {claim}")]
+    IssuerMismatch { header: String, claim: String },
+
+    #[error("Cannot decompress the JWP: {0}")]
+    DecompressionError(String),
+
+    #[error("Invalid x5c certificate: {0}")]
+    InvalidCertificate(String),
+
+    #[error("The x5c leaf certificate does not certify the key of the JWK")]
+    X5cKeyMismatch,
+
+    #[error("api_id {api_id} is not the one of the ciphersuite of {alg}")]
+    CiphersuiteMismatch { alg: String, api_id: String },
+
+    #[error("Expected {expected} keys, one per presentation, got {got}")]
+    KeyCountMismatch { expected: usize, got: usize },
+
+    #[error("Header member {0} does not survive the re-serialization of the decoded header")]
+    HeaderReserializationMismatch(String),
+
+    #[error("The {0} claim is after exp")]
+    InconsistentTemporalClaims(String),
+
+    #[error("Expected a proof of {expected}, got one of {got}")]
+    ProofAlgorithmMismatch { expected: String, got: String },
+
+    #[error("The presented JWP was not derived from the issued JWP")]
+    UnrelatedPresentation,
+
+    #[error("Unknown header parameter: {0}")]
+    UnknownHeaderParameter(String),
+
+    #[error("The claim order must list each claim exactly once")]
+    ClaimOrderMismatch,
+
+    #[error("Invalid UUID: {0}")]
+    InvalidUuid(String),
+
+    #[error("Claims {0} and {1} do not have the same value")]
+    EqualityNotSatisfied(String, String),
+
+    #[error("Holder signature generation failed: {0}")]
+    HolderSignatureError(String),
+
+    #[error("I/O error: {0}")]
+    IoError(String),
+}
+
+impl From<data_encoding::DecodeError> for CustomError {
+    fn from(error: data_encoding::DecodeError) -> Self {
+        CustomError::Base64Error(error.to_string())
+    }
 }
 
 #[derive(Error, Debug)]
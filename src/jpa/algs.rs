@@ -12,10 +12,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fmt, str::FromStr};
+use core::{fmt, str::FromStr};
 
 use serde::{Deserialize, Serialize};
 
+use crate::errors::CustomError;
+
+/// Size of a BBS signature (A and e)
+pub(crate) const BBS_SIGNATURE_BYTES: usize = 48 + 32;
+// Layout of a BBS proof of knowledge, as encoded by zkryptium 0.2 (`BBSplusPoKSignature::to_bytes`): the G1 points
+// Abar, Bbar and D (48 bytes each), then the scalars e^, r1^, r3^, one m^ per undisclosed message in index order
+// and the challenge (32 bytes each). `BBSplusAlgorithm::check_equal_responses` reads the m^ at these offsets
+// and the `bbs-prover` feature writes proofs in this layout, so both must follow any change of zkryptium.
+/// Size of a BBS proof of knowledge without undisclosed messages (Abar, Bbar, D, e^, r1^, r3^ and the challenge)
+pub(crate) const BBS_POK_BASE_BYTES: usize = 3 * 48 + 4 * 32;
+/// Size added to a BBS proof of knowledge by each undisclosed message
+pub(crate) const BBS_POK_SCALAR_BYTES: usize = 32;
+
+/// Size of a BBS proof of knowledge with `undisclosed` undisclosed messages
+pub(crate) const fn bbs_pok_len(undisclosed: usize) -> usize {
+    BBS_POK_BASE_BYTES + BBS_POK_SCALAR_BYTES * undisclosed
+}
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 #[allow(non_camel_case_types)]
 pub enum ProofAlgorithm {
@@ -41,13 +59,46 @@ pub enum ProofAlgorithm {
     MAC_H256K,
 }
 
-impl Into<PresentationProofAlgorithm> for ProofAlgorithm {
-    fn into(self) -> PresentationProofAlgorithm {
+impl ProofAlgorithm {
+    /// Algorithms implemented by this build of the crate (none without the `std` feature)
+    pub fn supported() -> &'static [ProofAlgorithm] {
+        #[cfg(feature = "std")]
+        {
+            &[
+                ProofAlgorithm::BBS,
+                ProofAlgorithm::BBS_SHAKE256,
+                ProofAlgorithm::SU_ES256,
+            ]
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            &[]
+        }
+    }
+
+    /// `api_id` of the BBS ciphersuite of the algorithm, `None` for the non-BBS algorithms
+    pub fn bbs_api_id(&self) -> Option<&'static str> {
         match self {
+            ProofAlgorithm::BBS => Some("BBS_BLS12381G1_XMD:SHA-256_SSWU_RO_H2G_HM2S_"),
+            ProofAlgorithm::BBS_SHAKE256 => Some("BBS_BLS12381G1_XOF:SHAKE-256_SSWU_RO_H2G_HM2S_"),
+            _ => None,
+        }
+    }
+
+    /// Length of the proof of an Issued JWP, `None` for the algorithms without a known fixed length
+    pub fn proof_len(&self) -> Option<usize> {
+        match self {
+            ProofAlgorithm::BBS | ProofAlgorithm::BBS_SHAKE256 => Some(BBS_SIGNATURE_BYTES),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProofAlgorithm> for PresentationProofAlgorithm {
+    fn from(val: ProofAlgorithm) -> Self {
+        match val {
             ProofAlgorithm::BBS => PresentationProofAlgorithm::BBS_PROOF,
-            ProofAlgorithm::BBS_SHAKE256 => {
-                PresentationProofAlgorithm::BBS_SHAKE256_PROOF
-            }
+            ProofAlgorithm::BBS_SHAKE256 => PresentationProofAlgorithm::BBS_SHAKE256_PROOF,
             ProofAlgorithm::SU_ES256 => PresentationProofAlgorithm::SU_ES256,
             ProofAlgorithm::MAC_H256 => PresentationProofAlgorithm::MAC_H256,
             ProofAlgorithm::MAC_H384 => PresentationProofAlgorithm::MAC_H384,
@@ -120,6 +171,65 @@ pub enum PresentationProofAlgorithm {
     MAC_H256K,
 }
 
+impl PresentationProofAlgorithm {
+    /// Algorithms implemented by this build of the crate (none without the `std` feature)
+    pub fn supported() -> &'static [PresentationProofAlgorithm] {
+        #[cfg(feature = "std")]
+        {
+            &[
+                PresentationProofAlgorithm::BBS_PROOF,
+                PresentationProofAlgorithm::BBS_SHAKE256_PROOF,
+                PresentationProofAlgorithm::SU_ES256,
+            ]
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            &[]
+        }
+    }
+
+    /// Whether this algorithm can present a JWP issued with `alg`
+    pub fn is_compatible_with(&self, alg: ProofAlgorithm) -> bool {
+        *self == PresentationProofAlgorithm::from(alg)
+    }
+
+    /// Check that a proof of `len` bytes fits a Presented JWP with `undisclosed` undisclosed payloads.
+    /// A BBS proof committing to another number of undisclosed messages errors with
+    /// [`CustomError::InvalidPresentedJwp`], any other length with [`CustomError::InvalidProofLength`].
+    pub(crate) fn check_proof_len(
+        &self,
+        len: usize,
+        undisclosed: usize,
+    ) -> Result<(), CustomError> {
+        let expected = match self.proof_len(undisclosed) {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        match len
+            .checked_sub(BBS_POK_BASE_BYTES)
+            .filter(|scalars_len| scalars_len % BBS_POK_SCALAR_BYTES == 0)
+        {
+            // The proof is well formed, but commits to a different number of undisclosed messages
+            // than the empty payloads of the JWP
+            Some(scalars_len) if scalars_len / BBS_POK_SCALAR_BYTES != undisclosed => {
+                Err(CustomError::InvalidPresentedJwp)
+            }
+            Some(_) => Ok(()),
+            None => Err(CustomError::InvalidProofLength { expected, got: len }),
+        }
+    }
+
+    /// Length of the proof of a Presented JWP with `undisclosed` undisclosed payloads,
+    /// `None` for the algorithms without a known length
+    pub fn proof_len(&self, undisclosed: usize) -> Option<usize> {
+        match self {
+            PresentationProofAlgorithm::BBS_PROOF
+            | PresentationProofAlgorithm::BBS_SHAKE256_PROOF => Some(bbs_pok_len(undisclosed)),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for PresentationProofAlgorithm {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let variant_str = match self {
@@ -143,9 +253,7 @@ impl FromStr for PresentationProofAlgorithm {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "BBS-PROOF" => Ok(PresentationProofAlgorithm::BBS_PROOF),
-            "BBS-SHAKE256-PROOF" => {
-                Ok(PresentationProofAlgorithm::BBS_SHAKE256_PROOF)
-            }
+            "BBS-SHAKE256-PROOF" => Ok(PresentationProofAlgorithm::BBS_SHAKE256_PROOF),
             "SU-ES256" => Ok(PresentationProofAlgorithm::SU_ES256),
             "MAC-H256" => Ok(PresentationProofAlgorithm::MAC_H256),
             "MAC-H384" => Ok(PresentationProofAlgorithm::MAC_H384),
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use core::fmt;
 use serde::{Deserialize, Serialize};
 use zkryptium::{
     bbsplus::{
@@ -30,51 +31,78 @@ use crate::{
     errors::CustomError,
     jpt::payloads::Payloads,
     jwk::{
-        alg_parameters::{Algorithm, JwkAlgorithmParameters},
-        key::Jwk,
+        alg_parameters::{Algorithm, JwkAlgorithmParameters, JwkEllipticCurveKeyParameters},
+        key::{Jwk, KeyOps},
         utils::{check_alg_curve_compatibility, check_presentation_alg_curve_compatibility},
     },
 };
 
-use super::algs::{PresentationProofAlgorithm, ProofAlgorithm};
+use super::algs::{
+    bbs_pok_len, PresentationProofAlgorithm, ProofAlgorithm, BBS_POK_BASE_BYTES,
+    BBS_POK_SCALAR_BYTES, BBS_SIGNATURE_BYTES,
+};
+
+#[cfg(feature = "bbs-prover")]
+use bls12_381_plus::{ff::Field, Scalar};
+#[cfg(feature = "bbs-prover")]
+use rand_core::{CryptoRng, OsRng, RngCore};
+#[cfg(feature = "bbs-prover")]
+use zkryptium::{
+    bbsplus::{
+        ciphersuites::{BbsCiphersuite, Bls12381Sha256, Bls12381Shake256},
+        generators::Generators,
+    },
+    utils::{
+        message::bbsplus_message::BBSplusMessage,
+        util::bbsplus_utils::{hash_to_scalar, i2osp},
+    },
+};
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct BBSplusAlgorithm {}
 
 impl BBSplusAlgorithm {
+    /// Length of a BBS secret key, the `d` of a JWK
+    pub const SECRET_KEY_LEN: usize = 32;
+    /// Length of a compressed BBS public key (a BLS12-381 G2 point), the `x` of a JWK in the OKP form
+    pub const PUBLIC_KEY_LEN: usize = 96;
+    /// Length of each coordinate of a BBS public key, the `x` and `y` of a JWK in the EC form
+    pub const PUBLIC_KEY_COORDINATE_LEN: usize = 96;
+    /// Length of a BBS signature, the proof of an Issued JWP
+    pub const SIGNATURE_LEN: usize = BBS_SIGNATURE_BYTES;
+
+    /// Length of a BBS proof of knowledge, the proof of a Presented JWP, with `undisclosed` undisclosed payloads
+    pub const fn proof_len(undisclosed: usize) -> usize {
+        bbs_pok_len(undisclosed)
+    }
+
     pub fn generate_issuer_proof(
         alg: ProofAlgorithm,
         payloads: &Payloads,
         key: &Jwk,
         issuer_header: &[u8],
     ) -> Result<Vec<u8>, CustomError> {
+        key.check_key_ops(KeyOps::ProofGeneration)?;
         let key_params = match &key.key_params {
             JwkAlgorithmParameters::EllipticCurve(params) => {
-                if params.is_private() == false {
-                    return Err(CustomError::ProofGenerationError(
-                        "key is not compatible".to_string(),
-                    ));
+                if !params.is_private() {
+                    return Err(generation_error(alg, payloads, "key is not compatible"));
                 }
                 params
             }
-            _ => {
-                return Err(CustomError::ProofGenerationError(
-                    "key is not compatible".to_string(),
-                ))
-            }
+            _ => return Err(generation_error(alg, payloads, "key is not compatible")),
         };
 
-        if check_alg_curve_compatibility(Algorithm::Proof(alg.clone()), key_params.crv.clone())
-            == false
-        {
-            Err(CustomError::ProofGenerationError(
-                "key is not compatible".to_string(),
-            ))
+        if !check_alg_curve_compatibility(Algorithm::Proof(alg), key_params.crv.clone()) {
+            Err(CustomError::IncompatibleKeyAlgorithm {
+                alg: alg.to_string(),
+                crv: key_params.crv.to_string(),
+            })
         } else {
-            let x: [u8; 96] = base64url_decode(&key_params.x)
+            let x: [u8; Self::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&key_params.x)?
                 .try_into()
                 .map_err(|_| CustomError::InvalidJwk)?;
-            let y: [u8; 96] = base64url_decode(&key_params.y)
+            let y: [u8; Self::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&key_params.y)?
                 .try_into()
                 .map_err(|_| CustomError::InvalidJwk)?;
 
@@ -83,8 +111,10 @@ impl BBSplusAlgorithm {
 
             let sk = BBSplusSecretKey::from_bytes(&base64url_decode(
                 key_params.d.as_ref().ok_or(CustomError::InvalidJwk)?,
-            ))
-            .map_err(|_| CustomError::SerializationError)?;
+            )?)
+            .map_err(|e| {
+                CustomError::SerializationError(format!("d is not a BBS secret key: {e}"))
+            })?;
 
             let proof = match alg {
                 ProofAlgorithm::BBS => Signature::<BbsBls12381Sha256>::sign(
@@ -93,7 +123,7 @@ impl BBSplusAlgorithm {
                     &pk,
                     Some(issuer_header),
                 )
-                .map_err(|e| CustomError::ProofGenerationError(e.to_string()))?
+                .map_err(|e| generation_error(alg, payloads, e))?
                 .to_bytes(),
                 ProofAlgorithm::BBS_SHAKE256 => Signature::<BbsBls12381Shake256>::sign(
                     Some(&payloads.to_bytes()?),
@@ -101,7 +131,7 @@ impl BBSplusAlgorithm {
                     &pk,
                     Some(issuer_header),
                 )
-                .map_err(|e| CustomError::ProofGenerationError(e.to_string()))?
+                .map_err(|e| generation_error(alg, payloads, e))?
                 .to_bytes(),
                 _ => unreachable!(),
             };
@@ -117,42 +147,32 @@ impl BBSplusAlgorithm {
         issuer_header: &[u8],
         payloads: &Payloads,
     ) -> Result<(), CustomError> {
-        let key_params = match &key.key_params {
-            JwkAlgorithmParameters::EllipticCurve(params) => {
-                if params.is_public() == false {
-                    return Err(CustomError::ProofGenerationError(
-                        "key is not compatible".to_string(),
-                    ));
-                }
-                params
-            }
-            _ => {
-                return Err(CustomError::ProofGenerationError(
-                    "key is not compatible".to_string(),
-                ))
-            }
-        };
+        let key_params = Self::public_key_params(key)?;
 
-        if check_alg_curve_compatibility(Algorithm::Proof(alg.clone()), key_params.crv.clone())
-            == false
-        {
-            Err(CustomError::ProofGenerationError(
-                "key is not compatible".to_string(),
-            ))
+        if !check_alg_curve_compatibility(Algorithm::Proof(alg), key_params.crv.clone()) {
+            Err(CustomError::IncompatibleKeyAlgorithm {
+                alg: alg.to_string(),
+                crv: key_params.crv.to_string(),
+            })
         } else {
-            let x: [u8; 96] = base64url_decode(&key_params.x)
+            let x: [u8; Self::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&key_params.x)?
                 .try_into()
                 .map_err(|_| CustomError::InvalidJwk)?;
-            let y: [u8; 96] = base64url_decode(&key_params.y)
+            let y: [u8; Self::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&key_params.y)?
                 .try_into()
                 .map_err(|_| CustomError::InvalidJwk)?;
 
             let pk =
                 BBSplusPublicKey::from_coordinates(&x, &y).map_err(|_| CustomError::InvalidJwk)?;
-            let proof = BBSplusSignature::from_bytes(proof.try_into().map_err(|_| {
-                CustomError::ProofVerificationError("Proof is not valid".to_owned())
-            })?)
-            .map_err(|_| CustomError::SerializationError)?;
+            let proof: &[u8; Self::SIGNATURE_LEN] =
+                proof
+                    .try_into()
+                    .map_err(|_| CustomError::InvalidProofLength {
+                        expected: Self::SIGNATURE_LEN,
+                        got: proof.len(),
+                    })?;
+            let proof =
+                BBSplusSignature::from_bytes(proof).map_err(|_| CustomError::InvalidIssuedJwp)?;
             let check = match alg {
                 ProofAlgorithm::BBS => {
                     let proof = Signature::<BbsBls12381Sha256>::BBSplus(proof);
@@ -165,7 +185,8 @@ impl BBSplusAlgorithm {
                 _ => unreachable!(),
             };
 
-            check.map_err(|e| CustomError::ProofVerificationError(e.to_string()))
+            // Every structural check passed: a wrong key and a modified token are indistinguishable from here
+            check.map_err(|_| CustomError::InvalidIssuedProof)
         }
     }
 
@@ -177,67 +198,199 @@ impl BBSplusAlgorithm {
         issuer_header: &[u8],
         presentation_header: &[u8],
     ) -> Result<Vec<u8>, CustomError> {
-        let key_params = match &key.key_params {
-            JwkAlgorithmParameters::EllipticCurve(params) => {
-                if params.is_public() == false {
-                    return Err(CustomError::ProofGenerationError(
-                        "key is not compatible".to_string(),
-                    ));
-                }
-                params
-            }
-            _ => {
-                return Err(CustomError::ProofGenerationError(
-                    "key is not compatible".to_string(),
-                ))
+        let pk = Self::presentation_public_key(alg, payloads, key)?;
+        let revealed_message_indexes = payloads.get_disclosed_indexes();
+        let proof = match alg {
+            PresentationProofAlgorithm::BBS_PROOF => PoKSignature::<BbsBls12381Sha256>::proof_gen(
+                &pk,
+                signature,
+                Some(issuer_header),
+                Some(presentation_header),
+                Some(&payloads.to_bytes()?),
+                Some(&revealed_message_indexes),
+            )
+            .map_err(|e| generation_error(alg, payloads, e))?
+            .to_bytes(),
+            PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
+                PoKSignature::<BbsBls12381Shake256>::proof_gen(
+                    &pk,
+                    signature,
+                    Some(issuer_header),
+                    Some(presentation_header),
+                    Some(&payloads.to_bytes()?),
+                    Some(&revealed_message_indexes),
+                )
+                .map_err(|e| generation_error(alg, payloads, e))?
+                .to_bytes()
             }
+            _ => unreachable!(),
         };
 
-        if check_presentation_alg_curve_compatibility(alg, key_params.crv.clone()) == false {
-            Err(CustomError::ProofGenerationError(
-                "key is not compatible".to_string(),
-            ))
-        } else {
-            let x: [u8; 96] = base64url_decode(&key_params.x)
-                .try_into()
-                .map_err(|_| CustomError::InvalidJwk)?;
-            let y: [u8; 96] = base64url_decode(&key_params.y)
-                .try_into()
-                .map_err(|_| CustomError::InvalidJwk)?;
+        Ok(proof.to_vec())
+    }
 
-            let pk =
-                BBSplusPublicKey::from_coordinates(&x, &y).map_err(|_| CustomError::InvalidJwk)?;
-            let revealed_message_indexes = payloads.get_disclosed_indexes();
-            let proof = match alg {
-                PresentationProofAlgorithm::BBS_PROOF => {
-                    PoKSignature::<BbsBls12381Sha256>::proof_gen(
-                        &pk,
-                        &signature,
-                        Some(issuer_header),
-                        Some(presentation_header),
-                        Some(&payloads.to_bytes()?),
-                        Some(&revealed_message_indexes),
-                    )
-                    .map_err(|e| CustomError::ProofGenerationError(e.to_string()))?
-                    .to_bytes()
-                }
-                PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
-                    PoKSignature::<BbsBls12381Shake256>::proof_gen(
-                        &pk,
-                        &signature,
-                        Some(issuer_header),
-                        Some(presentation_header),
-                        Some(&payloads.to_bytes()?),
-                        Some(&revealed_message_indexes),
-                    )
-                    .map_err(|e| CustomError::ProofGenerationError(e.to_string()))?
-                    .to_bytes()
-                }
-                _ => unreachable!(),
-            };
+    /// Same as [`BBSplusAlgorithm::generate_presentation_proof`], also proving that the undisclosed payloads
+    /// of each pair of `equal_indexes` have the same value, without revealing it.
+    ///
+    /// Both messages of a pair get the same blinding, so the proof has the same response for them:
+    /// the verifier checks it with [`BBSplusAlgorithm::check_equal_responses`]. zkryptium does not let the caller
+    /// choose the blindings, so these proofs come from the crate's own ProofGen (`bbs-prover` feature).
+    #[cfg(feature = "bbs-prover")]
+    pub fn generate_presentation_proof_with_equalities(
+        alg: PresentationProofAlgorithm,
+        signature: &[u8],
+        payloads: &Payloads,
+        key: &Jwk,
+        issuer_header: &[u8],
+        presentation_header: &[u8],
+        equal_indexes: &[(usize, usize)],
+    ) -> Result<Vec<u8>, CustomError> {
+        if equal_indexes.is_empty() {
+            return Self::generate_presentation_proof(
+                alg,
+                signature,
+                payloads,
+                key,
+                issuer_header,
+                presentation_header,
+            );
+        }
+        Self::generate_presentation_proof_from_rng(
+            alg,
+            signature,
+            payloads,
+            key,
+            issuer_header,
+            presentation_header,
+            equal_indexes,
+            &mut OsRng,
+        )
+    }
 
-            Ok(proof.to_vec())
+    /// Same as [`BBSplusAlgorithm::generate_presentation_proof_with_equalities`], drawing the random scalars of the
+    /// proof from `rng` instead of the OS RNG, so the same seed always gives the same proof.
+    ///
+    /// The blindings hide the undisclosed payloads: `rng` must be a cryptographically secure generator,
+    /// and a seed must never be reused outside of tests. Also without `equal_indexes` the proof comes from the
+    /// crate's own ProofGen, as zkryptium does not accept an RNG.
+    #[cfg(feature = "rng")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_presentation_proof_with_rng<R: RngCore + CryptoRng>(
+        alg: PresentationProofAlgorithm,
+        signature: &[u8],
+        payloads: &Payloads,
+        key: &Jwk,
+        issuer_header: &[u8],
+        presentation_header: &[u8],
+        equal_indexes: &[(usize, usize)],
+        rng: &mut R,
+    ) -> Result<Vec<u8>, CustomError> {
+        Self::generate_presentation_proof_from_rng(
+            alg,
+            signature,
+            payloads,
+            key,
+            issuer_header,
+            presentation_header,
+            equal_indexes,
+            rng,
+        )
+    }
+
+    #[cfg(feature = "bbs-prover")]
+    #[allow(clippy::too_many_arguments)]
+    fn generate_presentation_proof_from_rng(
+        alg: PresentationProofAlgorithm,
+        signature: &[u8],
+        payloads: &Payloads,
+        key: &Jwk,
+        issuer_header: &[u8],
+        presentation_header: &[u8],
+        equal_indexes: &[(usize, usize)],
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<Vec<u8>, CustomError> {
+        let pk = Self::presentation_public_key(alg, payloads, key)?;
+        let messages = payloads.to_bytes()?;
+        let disclosed_indexes = payloads.get_disclosed_indexes();
+        match alg {
+            PresentationProofAlgorithm::BBS_PROOF => proof_gen_with_rng::<Bls12381Sha256>(
+                &pk,
+                signature,
+                issuer_header,
+                presentation_header,
+                &messages,
+                &disclosed_indexes,
+                equal_indexes,
+                rng,
+            ),
+            PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
+                proof_gen_with_rng::<Bls12381Shake256>(
+                    &pk,
+                    signature,
+                    issuer_header,
+                    presentation_header,
+                    &messages,
+                    &disclosed_indexes,
+                    equal_indexes,
+                    rng,
+                )
+            }
+            _ => return Err(CustomError::UnsupportedAlgorithm(alg.to_string())),
+        }
+        .map_err(|e| generation_error(alg, payloads, e))
+    }
+
+    /// Check that the BBS proof has the same response for the undisclosed payloads of each pair of `equal_indexes`,
+    /// which, once the proof verifies, proves that they have the same value.
+    /// Errors with [`CustomError::InvalidPresentedJwp`] if the proof is not in the encoding of zkryptium
+    /// or an index is not of an undisclosed payload, and with [`CustomError::InvalidPresentedProof`]
+    /// if the responses differ.
+    pub fn check_equal_responses(
+        proof: &[u8],
+        payloads: &Payloads,
+        equal_indexes: &[(usize, usize)],
+    ) -> Result<(), CustomError> {
+        if equal_indexes.is_empty() {
+            return Ok(());
+        }
+        let undisclosed_indexes = payloads.get_undisclosed_indexes();
+        if proof.len() != bbs_pok_len(undisclosed_indexes.len()) {
+            return Err(CustomError::InvalidPresentedJwp);
+        }
+        // The responses are read at the offsets of the layout in `algs`: only trust them if zkryptium
+        // re-encodes the proof to the same bytes
+        match BBSplusPoKSignature::from_bytes(proof) {
+            Ok(parsed) if parsed.to_bytes() == proof => {}
+            _ => return Err(CustomError::InvalidPresentedJwp),
         }
+        let response = |index: usize| {
+            let position = undisclosed_indexes.iter().position(|&i| i == index)?;
+            let start = BBS_POK_BASE_BYTES - BBS_POK_SCALAR_BYTES + position * BBS_POK_SCALAR_BYTES;
+            proof.get(start..start + BBS_POK_SCALAR_BYTES)
+        };
+        for &(a, b) in equal_indexes {
+            match (response(a), response(b)) {
+                (Some(response_a), Some(response_b)) if response_a == response_b => {}
+                (Some(_), Some(_)) => return Err(CustomError::InvalidPresentedProof),
+                _ => return Err(CustomError::InvalidPresentedJwp),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the key used by the holder to present, the public key of the issuer, and decodes it
+    fn presentation_public_key(
+        alg: PresentationProofAlgorithm,
+        payloads: &Payloads,
+        key: &Jwk,
+    ) -> Result<BBSplusPublicKey, CustomError> {
+        key.check_key_ops(KeyOps::ProofVerification)?;
+        let key_params = match &key.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) if params.is_public() => params,
+            _ => return Err(generation_error(alg, payloads, "key is not compatible")),
+        };
+        Self::check_presentation_compatibility(alg, key_params)?;
+        Self::public_key(key_params)
     }
 
     pub fn verify_presentation_proof(
@@ -248,66 +401,342 @@ impl BBSplusAlgorithm {
         issuer_header: &[u8],
         payloads: &Payloads,
     ) -> Result<(), CustomError> {
-        let key_params = match &key.key_params {
-            JwkAlgorithmParameters::EllipticCurve(params) => {
-                if params.is_public() == false {
-                    return Err(CustomError::ProofGenerationError(
-                        "key is not compatible".to_string(),
-                    ));
-                }
-                params
+        let key_params = Self::public_key_params(key)?;
+        Self::check_presentation_compatibility(alg, key_params)?;
+        let pk = Self::public_key(key_params)?;
+
+        Self::verify_presentation_proof_with_public_key(
+            alg,
+            &pk,
+            proof,
+            presentation_header,
+            issuer_header,
+            payloads,
+        )
+    }
+
+    /// Same as [`BBSplusAlgorithm::verify_presentation_proof`] but with an already decoded public key,
+    /// useful to verify many presentations without decoding the same key every time.
+    /// The caller is responsible for checking that the key is compatible with `alg`.
+    pub(crate) fn verify_presentation_proof_with_public_key(
+        alg: PresentationProofAlgorithm,
+        pk: &BBSplusPublicKey,
+        proof: &[u8],
+        presentation_header: &[u8],
+        issuer_header: &[u8],
+        payloads: &Payloads,
+    ) -> Result<(), CustomError> {
+        alg.check_proof_len(proof.len(), payloads.get_undisclosed_indexes().len())?;
+
+        let disclosed_indexes = payloads.get_disclosed_indexes();
+        let proof =
+            BBSplusPoKSignature::from_bytes(proof).map_err(|_| CustomError::InvalidPresentedJwp)?;
+        let check = match alg {
+            PresentationProofAlgorithm::BBS_PROOF => {
+                let proof = PoKSignature::<BbsBls12381Sha256>::BBSplus(proof);
+                proof.proof_verify(
+                    pk,
+                    Some(&payloads.disclosed_to_bytes()?),
+                    Some(&disclosed_indexes),
+                    Some(issuer_header),
+                    Some(presentation_header),
+                )
+            }
+            PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
+                let proof = PoKSignature::<BbsBls12381Shake256>::BBSplus(proof);
+                proof.proof_verify(
+                    pk,
+                    Some(&payloads.disclosed_to_bytes()?),
+                    Some(&disclosed_indexes),
+                    Some(issuer_header),
+                    Some(presentation_header),
+                )
             }
+            _ => unreachable!(),
+        };
+
+        // Every structural check passed: a wrong key and a modified token are indistinguishable from here
+        check.map_err(|_| CustomError::InvalidPresentedProof)
+    }
+
+    /// Sign `message`, the holder signing input of a presentation protected header, with the holder key:
+    /// a BBS signature of `alg` over no messages, with `message` as BBS header.
+    /// The key must be a private key of the curve of `alg` that permits [`KeyOps::Sign`].
+    /// Errors with [`CustomError::HolderSignatureError`] if the signature cannot be produced.
+    pub fn sign_holder_binding(
+        alg: ProofAlgorithm,
+        key: &Jwk,
+        message: &[u8],
+    ) -> Result<Vec<u8>, CustomError> {
+        key.check_key_ops(KeyOps::Sign)?;
+        let key_params = match &key.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) if params.is_private() => params,
             _ => {
-                return Err(CustomError::ProofGenerationError(
-                    "key is not compatible".to_string(),
+                return Err(CustomError::HolderSignatureError(
+                    "the holder key is not a private key".to_string(),
                 ))
             }
         };
+        Self::check_holder_key(alg, key_params)?;
+        let pk = Self::public_key(key_params)?;
+        let sk = BBSplusSecretKey::from_bytes(&base64url_decode(
+            key_params.d.as_ref().ok_or(CustomError::InvalidJwk)?,
+        )?)
+        .map_err(|e| {
+            CustomError::HolderSignatureError(format!("d is not a BBS secret key: {e}"))
+        })?;
+
+        let signature = match alg {
+            ProofAlgorithm::BBS => {
+                Signature::<BbsBls12381Sha256>::sign(Some(&[]), &sk, &pk, Some(message))
+                    .map(|signature| signature.to_bytes())
+            }
+            ProofAlgorithm::BBS_SHAKE256 => {
+                Signature::<BbsBls12381Shake256>::sign(Some(&[]), &sk, &pk, Some(message))
+                    .map(|signature| signature.to_bytes())
+            }
+            _ => return Err(CustomError::UnsupportedAlgorithm(alg.to_string())),
+        }
+        .map_err(|e| CustomError::HolderSignatureError(e.to_string()))?;
+        Ok(signature.to_vec())
+    }
+
+    /// Verify a signature of [`BBSplusAlgorithm::sign_holder_binding`] with the public holder key, which must
+    /// permit [`KeyOps::Verify`]. Errors with [`CustomError::HolderBindingError`] if the signature does not verify.
+    pub fn verify_holder_binding(
+        alg: ProofAlgorithm,
+        key: &Jwk,
+        signature: &[u8],
+        message: &[u8],
+    ) -> Result<(), CustomError> {
+        key.check_key_ops(KeyOps::Verify)?;
+        let key_params = match &key.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) if params.is_public() => params,
+            _ => return Err(CustomError::InvalidJwk),
+        };
+        Self::check_holder_key(alg, key_params)?;
+        let pk = Self::public_key(key_params)?;
+        let signature: &[u8; Self::SIGNATURE_LEN] = signature
+            .try_into()
+            .map_err(|_| CustomError::HolderBindingError)?;
+        let signature =
+            BBSplusSignature::from_bytes(signature).map_err(|_| CustomError::HolderBindingError)?;
+        match alg {
+            ProofAlgorithm::BBS => Signature::<BbsBls12381Sha256>::BBSplus(signature).verify(
+                &pk,
+                Some(&[]),
+                Some(message),
+            ),
+            ProofAlgorithm::BBS_SHAKE256 => Signature::<BbsBls12381Shake256>::BBSplus(signature)
+                .verify(&pk, Some(&[]), Some(message)),
+            _ => return Err(CustomError::UnsupportedAlgorithm(alg.to_string())),
+        }
+        .map_err(|_| CustomError::HolderBindingError)
+    }
 
-        if check_presentation_alg_curve_compatibility(alg, key_params.crv.clone()) == false {
-            Err(CustomError::ProofGenerationError(
+    /// Errors with [`CustomError::IncompatibleKeyAlgorithm`] if the holder key is not of the curve of `alg`
+    fn check_holder_key(
+        alg: ProofAlgorithm,
+        key_params: &JwkEllipticCurveKeyParameters,
+    ) -> Result<(), CustomError> {
+        if !check_alg_curve_compatibility(Algorithm::Proof(alg), key_params.crv.clone()) {
+            return Err(CustomError::IncompatibleKeyAlgorithm {
+                alg: alg.to_string(),
+                crv: key_params.crv.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the Elliptic Curve parameters of a public key
+    pub(crate) fn public_key_params(
+        key: &Jwk,
+    ) -> Result<&JwkEllipticCurveKeyParameters, CustomError> {
+        key.check_key_ops(KeyOps::ProofVerification)?;
+        match &key.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) if params.is_public() => Ok(params),
+            _ => Err(CustomError::ProofVerificationError(
                 "key is not compatible".to_string(),
-            ))
-        } else {
-            let x: [u8; 96] = base64url_decode(&key_params.x)
-                .try_into()
-                .map_err(|_| CustomError::InvalidJwk)?;
-            let y: [u8; 96] = base64url_decode(&key_params.y)
-                .try_into()
-                .map_err(|_| CustomError::InvalidJwk)?;
+            )),
+        }
+    }
 
-            let pk =
-                BBSplusPublicKey::from_coordinates(&x, &y).map_err(|_| CustomError::InvalidJwk)?;
-            let disclosed_indexes = payloads.get_disclosed_indexes();
-            let proof = BBSplusPoKSignature::from_bytes(proof.try_into().map_err(|_| {
-                CustomError::ProofVerificationError("Proof is not valid".to_owned())
-            })?)
+    pub(crate) fn check_presentation_compatibility(
+        alg: PresentationProofAlgorithm,
+        key_params: &JwkEllipticCurveKeyParameters,
+    ) -> Result<(), CustomError> {
+        if !check_presentation_alg_curve_compatibility(alg, key_params.crv.clone()) {
+            return Err(CustomError::IncompatibleKeyAlgorithm {
+                alg: alg.to_string(),
+                crv: key_params.crv.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Decodes the BBS public key from its coordinates
+    pub(crate) fn public_key(
+        key_params: &JwkEllipticCurveKeyParameters,
+    ) -> Result<BBSplusPublicKey, CustomError> {
+        let x: [u8; Self::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&key_params.x)?
+            .try_into()
             .map_err(|_| CustomError::InvalidJwk)?;
-            let check = match alg {
-                PresentationProofAlgorithm::BBS_PROOF => {
-                    let proof = PoKSignature::<BbsBls12381Sha256>::BBSplus(proof);
-                    proof.proof_verify(
-                        &pk,
-                        Some(&payloads.get_disclosed_payloads().to_bytes()?),
-                        Some(&disclosed_indexes),
-                        Some(issuer_header),
-                        Some(presentation_header),
-                    )
-                }
-                PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
-                    let proof = PoKSignature::<BbsBls12381Shake256>::BBSplus(proof);
-                    proof.proof_verify(
-                        &pk,
-                        Some(&payloads.get_disclosed_payloads().to_bytes()?),
-                        Some(&disclosed_indexes),
-                        Some(issuer_header),
-                        Some(presentation_header),
-                    )
-                }
-                _ => unreachable!(),
-            };
+        let y: [u8; Self::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&key_params.y)?
+            .try_into()
+            .map_err(|_| CustomError::InvalidJwk)?;
+
+        BBSplusPublicKey::from_coordinates(&x, &y).map_err(|_| CustomError::InvalidJwk)
+    }
+}
+
+fn generation_error(
+    alg: impl fmt::Display,
+    payloads: &Payloads,
+    message: impl fmt::Display,
+) -> CustomError {
+    CustomError::ProofGenerationError {
+        alg: alg.to_string(),
+        message: message.to_string(),
+        payload_count: payloads.len(),
+    }
+}
+
+/// BBS ProofGen (https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-bbs-signatures-05#name-proof-generation-proofgen)
+/// with the random scalars drawn from `rng`. Same steps and encoding as zkryptium 0.2 (`core_proof_gen`), which draws
+/// them from the OS RNG and does not accept one.
+///
+/// The undisclosed messages of each pair of `equal_indexes` share their blinding (m~), so that their responses
+/// (m^) are equal exactly when the messages are.
+///
+/// This is a copy of the library's prover, not audited: it is only built with the `bbs-prover` feature and
+/// `tests/bbs_prover.rs` checks that its proofs verify with zkryptium. Check it again on every zkryptium upgrade.
+#[cfg(feature = "bbs-prover")]
+#[allow(clippy::too_many_arguments)]
+fn proof_gen_with_rng<CS: BbsCiphersuite>(
+    pk: &BBSplusPublicKey,
+    signature: &[u8],
+    header: &[u8],
+    ph: &[u8],
+    messages: &[Vec<u8>],
+    disclosed_indexes: &[usize],
+    equal_indexes: &[(usize, usize)],
+    rng: &mut (impl RngCore + CryptoRng),
+) -> Result<Vec<u8>, zkryptium::errors::Error> {
+    use zkryptium::errors::Error;
+
+    let signature =
+        BBSplusSignature::from_bytes(signature.try_into().map_err(|_| Error::InvalidSignature)?)?;
+    let message_scalars = BBSplusMessage::messages_to_scalar::<CS>(messages, CS::API_ID)?;
+    let generators = Generators::create::<CS>(messages.len() + 1, Some(CS::API_ID));
+    if let Some(index) = disclosed_indexes.iter().find(|&&i| i >= messages.len()) {
+        return Err(Error::ProofGenError(format!(
+            "Invalid disclosed index: {index}"
+        )));
+    }
+    let undisclosed_indexes: Vec<usize> = (0..messages.len())
+        .filter(|i| !disclosed_indexes.contains(i))
+        .collect();
+    if let Some(&(a, b)) = equal_indexes
+        .iter()
+        .find(|(a, b)| !undisclosed_indexes.contains(a) || !undisclosed_indexes.contains(b))
+    {
+        return Err(Error::ProofGenError(format!(
+            "Equal indexes {a} and {b} are not both undisclosed"
+        )));
+    }
+
+    let q1 = generators.values[0];
+    let h_points = &generators.values[1..];
+    let dst = [CS::API_ID, CS::H2S].concat();
 
-            check.map_err(|e| CustomError::ProofVerificationError(e.to_string()))
+    // Domain
+    let mut dom_input = pk.to_bytes().to_vec();
+    dom_input.extend(i2osp(h_points.len(), 8));
+    dom_input.extend(q1.to_compressed());
+    h_points
+        .iter()
+        .for_each(|h| dom_input.extend(h.to_compressed()));
+    dom_input.extend(CS::API_ID);
+    dom_input.extend(i2osp(header.len(), 8));
+    dom_input.extend(header);
+    let domain = hash_to_scalar::<CS>(&dom_input, &dst)?;
+
+    // Initialization
+    let [r1, r2, e_tilde, r1_tilde, r3_tilde] = [(); 5].map(|_| Scalar::random(&mut *rng));
+    let mut m_tilde: Vec<Scalar> = undisclosed_indexes
+        .iter()
+        .map(|_| Scalar::random(&mut *rng))
+        .collect();
+    // Every message of a group of equal ones takes the blinding of the first of the group:
+    // relax the pairs until each index points to the lowest index it is equal to
+    let mut group: Vec<usize> = undisclosed_indexes.clone();
+    let position = |index: usize| undisclosed_indexes.iter().position(|&i| i == index);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &(a, b) in equal_indexes {
+            if let (Some(pa), Some(pb)) = (position(a), position(b)) {
+                let lowest = group[pa].min(group[pb]);
+                if group[pa] != lowest || group[pb] != lowest {
+                    group[pa] = lowest;
+                    group[pb] = lowest;
+                    changed = true;
+                }
+            }
         }
     }
+    for (k, &first) in group.iter().enumerate() {
+        if let Some(pf) = position(first) {
+            m_tilde[k] = m_tilde[pf];
+        }
+    }
+
+    let b = message_scalars
+        .iter()
+        .zip(h_points)
+        .fold(generators.g1_base_point + q1 * domain, |b, (m, h)| {
+            b + h * m.value
+        });
+    let d = b * r2;
+    let a_bar = signature.A * (r1 * r2);
+    let b_bar = d * r1 - a_bar * signature.e;
+    let t1 = a_bar * e_tilde + d * r1_tilde;
+    let t2 = undisclosed_indexes
+        .iter()
+        .zip(&m_tilde)
+        .fold(d * r3_tilde, |t2, (&i, m)| t2 + h_points[i] * m);
+
+    // Challenge
+    let mut c_arr = Vec::new();
+    [a_bar, b_bar, d, t1, t2]
+        .iter()
+        .for_each(|p| c_arr.extend(p.to_compressed()));
+    c_arr.extend(i2osp(disclosed_indexes.len(), 8));
+    disclosed_indexes
+        .iter()
+        .for_each(|&i| c_arr.extend(i2osp(i, 8)));
+    disclosed_indexes
+        .iter()
+        .for_each(|&i| c_arr.extend(message_scalars[i].value.to_be_bytes()));
+    c_arr.extend(domain.to_be_bytes());
+    c_arr.extend(i2osp(ph.len(), 8));
+    c_arr.extend(ph);
+    let challenge = hash_to_scalar::<CS>(&c_arr, &dst)?;
+
+    // Finalization
+    let r3 = Option::<Scalar>::from(r2.invert())
+        .ok_or(Error::ProofGenError("Invert scalar failed".to_owned()))?;
+    let mut proof = Vec::with_capacity(bbs_pok_len(undisclosed_indexes.len()));
+    [a_bar, b_bar, d]
+        .iter()
+        .for_each(|p| proof.extend(p.to_compressed()));
+    proof.extend((e_tilde + signature.e * challenge).to_be_bytes());
+    proof.extend((r1_tilde - r1 * challenge).to_be_bytes());
+    proof.extend((r3_tilde - r3 * challenge).to_be_bytes());
+    undisclosed_indexes
+        .iter()
+        .zip(&m_tilde)
+        .for_each(|(&i, m)| proof.extend((m + message_scalars[i].value * challenge).to_be_bytes()));
+    proof.extend(challenge.to_be_bytes());
+    Ok(proof)
 }
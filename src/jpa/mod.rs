@@ -13,4 +13,8 @@
 // limitations under the License.
 
 pub mod algs;
+#[cfg(feature = "std")]
 pub mod bbs_plus;
+pub mod proof;
+#[cfg(feature = "std")]
+pub mod single_use;
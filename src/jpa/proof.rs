@@ -0,0 +1,119 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{string::ToString, vec::Vec};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::errors::CustomError;
+
+use super::algs::{
+    PresentationProofAlgorithm, ProofAlgorithm, BBS_POK_BASE_BYTES, BBS_POK_SCALAR_BYTES,
+};
+
+/// Proof bytes together with the algorithm that produced them, so that a proof cannot be used where one of
+/// another algorithm is expected. The constructors check the length of the bytes against the algorithm
+/// (see [`ProofAlgorithm::proof_len`] and [`PresentationProofAlgorithm::proof_len`]); proofs of algorithms
+/// without a known length are not checked. Deserialization goes through the same checks.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Proof<A> {
+    alg: A,
+    bytes: Vec<u8>,
+}
+
+/// Serialized form of a [`Proof`], not yet checked
+#[derive(Deserialize)]
+struct RawProof<A> {
+    alg: A,
+    bytes: Vec<u8>,
+}
+
+/// Proof of an Issued JWP
+pub type IssuerProof = Proof<ProofAlgorithm>;
+/// Proof of a Presented JWP
+pub type PresentationProof = Proof<PresentationProofAlgorithm>;
+
+impl Proof<ProofAlgorithm> {
+    /// Errors with [`CustomError::InvalidProofLength`] if `bytes` cannot be a proof of `alg`
+    pub fn new(alg: ProofAlgorithm, bytes: Vec<u8>) -> Result<Self, CustomError> {
+        check_len(alg.proof_len(), &bytes)?;
+        Ok(Self { alg, bytes })
+    }
+}
+
+impl Proof<PresentationProofAlgorithm> {
+    /// Errors with [`CustomError::InvalidProofLength`] if `bytes` cannot be a proof of `alg` with `undisclosed`
+    /// undisclosed payloads
+    pub fn new(
+        alg: PresentationProofAlgorithm,
+        bytes: Vec<u8>,
+        undisclosed: usize,
+    ) -> Result<Self, CustomError> {
+        check_len(alg.proof_len(undisclosed), &bytes)?;
+        Ok(Self { alg, bytes })
+    }
+}
+
+impl<'de> Deserialize<'de> for Proof<ProofAlgorithm> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawProof::deserialize(deserializer)?;
+        Self::new(raw.alg, raw.bytes).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Proof<PresentationProofAlgorithm> {
+    /// The number of undisclosed payloads is not serialized: the bytes must be a proof for the number
+    /// their length commits to, which the JWP is checked against on verification
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawProof::deserialize(deserializer)?;
+        let undisclosed = raw.bytes.len().saturating_sub(BBS_POK_BASE_BYTES) / BBS_POK_SCALAR_BYTES;
+        Self::new(raw.alg, raw.bytes, undisclosed).map_err(de::Error::custom)
+    }
+}
+
+impl<A: Copy + PartialEq + ToString> Proof<A> {
+    // Getter for alg
+    pub fn alg(&self) -> A {
+        self.alg
+    }
+
+    // Getter for bytes
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Errors with [`CustomError::ProofAlgorithmMismatch`] if the proof was not produced by `expected`
+    pub(crate) fn check_alg(&self, expected: A) -> Result<(), CustomError> {
+        if self.alg != expected {
+            return Err(CustomError::ProofAlgorithmMismatch {
+                expected: expected.to_string(),
+                got: self.alg.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn check_len(expected: Option<usize>, bytes: &[u8]) -> Result<(), CustomError> {
+    match expected {
+        Some(expected) if expected != bytes.len() => Err(CustomError::InvalidProofLength {
+            expected,
+            got: bytes.len(),
+        }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,319 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Single Use algorithms: every payload is signed with its own ephemeral key, and the issuer signs the issuer
+//! protected header together with the ephemeral public keys.
+//!
+//! For `SU-ES256` the keys are P-256 keys and the signatures are ES256 signatures (64 bytes, `r || s`).
+//! The issuer proof of a JWP with `n` payloads is
+//! ```text
+//! issuer_signature || ephemeral_key_1 || payload_signature_1 || ... || ephemeral_key_n || payload_signature_n
+//! ```
+//! and the presentation proof, with the payloads at indexes `j_1 < ... < j_d` disclosed, is
+//! ```text
+//! issuer_signature || ephemeral_key_1 || ... || ephemeral_key_n || payload_signature_j_1 || ... || payload_signature_j_d
+//! ```
+//! where:
+//! - `issuer_signature` is the signature with the issuer key over the issuer protected header octets followed by
+//!   `ephemeral_key_1 || ... || ephemeral_key_n`;
+//! - `ephemeral_key_i` is the SEC1 compressed public key (33 bytes) of the ephemeral key of payload `i`, whose
+//!   secret key is discarded after signing;
+//! - `payload_signature_i` is the signature with the ephemeral key `i` over the octets of payload `i`
+//!   (see [`Payloads::to_bytes`]).
+//!
+//! The holder only drops the signatures of the undisclosed payloads: the ephemeral keys stay, since the issuer
+//! signature covers all of them. Payloads proven by a predicate are presented as disclosed, with the constant of the
+//! predicate in place of the payload. Nothing in the proof covers the presentation protected header, and the same
+//! ephemeral keys appear in every presentation of a JWP, so each issued JWP should be presented only once.
+
+use core::fmt;
+use p256::{
+    ecdsa::{
+        signature::{Signer, Verifier},
+        Signature, SigningKey, VerifyingKey,
+    },
+    EncodedPoint,
+};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    encoding::base64url_decode,
+    errors::CustomError,
+    jpt::payloads::Payloads,
+    jwk::{
+        alg_parameters::{Algorithm, JwkAlgorithmParameters, JwkEllipticCurveKeyParameters},
+        key::{Jwk, KeyOps},
+        utils::{check_alg_curve_compatibility, check_presentation_alg_curve_compatibility},
+    },
+};
+
+use super::algs::{PresentationProofAlgorithm, ProofAlgorithm};
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct SingleUseAlgorithm {}
+
+impl SingleUseAlgorithm {
+    /// Length of a compressed ephemeral public key
+    pub const EPHEMERAL_KEY_LEN: usize = 33;
+    /// Length of an ES256 signature
+    pub const SIGNATURE_LEN: usize = 64;
+    /// Length of each coordinate of a P-256 public key, the `x` and `y` of a JWK
+    pub const PUBLIC_KEY_COORDINATE_LEN: usize = 32;
+
+    /// Length of the proof of an Issued JWP with `payloads` payloads
+    pub const fn issuer_proof_len(payloads: usize) -> usize {
+        Self::SIGNATURE_LEN + (Self::EPHEMERAL_KEY_LEN + Self::SIGNATURE_LEN) * payloads
+    }
+
+    /// Length of the proof of a Presented JWP with `payloads` payloads, of which `disclosed` are disclosed
+    pub const fn presentation_proof_len(payloads: usize, disclosed: usize) -> usize {
+        Self::SIGNATURE_LEN + Self::EPHEMERAL_KEY_LEN * payloads + Self::SIGNATURE_LEN * disclosed
+    }
+
+    pub fn generate_issuer_proof(
+        alg: ProofAlgorithm,
+        payloads: &Payloads,
+        key: &Jwk,
+        issuer_header: &[u8],
+    ) -> Result<Vec<u8>, CustomError> {
+        key.check_key_ops(KeyOps::ProofGeneration)?;
+        let key_params = match &key.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) if params.is_private() => params,
+            _ => return Err(generation_error(alg, payloads, "key is not compatible")),
+        };
+        if !check_alg_curve_compatibility(Algorithm::Proof(alg), key_params.crv.clone()) {
+            return Err(CustomError::IncompatibleKeyAlgorithm {
+                alg: alg.to_string(),
+                crv: key_params.crv.to_string(),
+            });
+        }
+        let issuer_key = SigningKey::from_slice(&base64url_decode(
+            key_params.d.as_ref().ok_or(CustomError::InvalidJwk)?,
+        )?)
+        .map_err(|_| CustomError::InvalidJwk)?;
+        if *issuer_key.verifying_key() != Self::verifying_key(key_params)? {
+            return Err(CustomError::InvalidJwk);
+        }
+
+        let mut ephemeral_keys = Vec::with_capacity(Self::EPHEMERAL_KEY_LEN * payloads.len());
+        let mut payload_proofs = Vec::with_capacity(payloads.len());
+        for payload in payloads.to_bytes()? {
+            let ephemeral_key = SigningKey::random(&mut OsRng);
+            let ephemeral_public_key = ephemeral_key.verifying_key().to_encoded_point(true);
+            let signature: Signature = ephemeral_key.sign(&payload);
+            ephemeral_keys.extend_from_slice(ephemeral_public_key.as_bytes());
+            payload_proofs.push((ephemeral_public_key, signature));
+        }
+
+        let issuer_signature: Signature =
+            issuer_key.sign(&[issuer_header, &ephemeral_keys].concat());
+        let mut proof = Vec::with_capacity(Self::issuer_proof_len(payloads.len()));
+        proof.extend_from_slice(&issuer_signature.to_bytes());
+        for (ephemeral_public_key, signature) in payload_proofs {
+            proof.extend_from_slice(ephemeral_public_key.as_bytes());
+            proof.extend_from_slice(&signature.to_bytes());
+        }
+        Ok(proof)
+    }
+
+    pub fn verify_issuer_proof(
+        alg: ProofAlgorithm,
+        key: &Jwk,
+        proof: &[u8],
+        issuer_header: &[u8],
+        payloads: &Payloads,
+    ) -> Result<(), CustomError> {
+        let key_params = Self::public_key_params(key)?;
+        if !check_alg_curve_compatibility(Algorithm::Proof(alg), key_params.crv.clone()) {
+            return Err(CustomError::IncompatibleKeyAlgorithm {
+                alg: alg.to_string(),
+                crv: key_params.crv.to_string(),
+            });
+        }
+        let issuer_key = Self::verifying_key(key_params)?;
+        let expected = Self::issuer_proof_len(payloads.len());
+        if proof.len() != expected {
+            return Err(CustomError::InvalidProofLength {
+                expected,
+                got: proof.len(),
+            });
+        }
+
+        let (issuer_signature, payload_proofs) = proof.split_at(Self::SIGNATURE_LEN);
+        let payload_proofs: Vec<(&[u8], &[u8])> = payload_proofs
+            .chunks(Self::EPHEMERAL_KEY_LEN + Self::SIGNATURE_LEN)
+            .map(|payload_proof| payload_proof.split_at(Self::EPHEMERAL_KEY_LEN))
+            .collect();
+        let ephemeral_keys: Vec<u8> = payload_proofs
+            .iter()
+            .flat_map(|(ephemeral_key, _)| ephemeral_key.iter().copied())
+            .collect();
+
+        verify(
+            &issuer_key,
+            &[issuer_header, &ephemeral_keys].concat(),
+            issuer_signature,
+        )
+        .and_then(|_| {
+            payload_proofs
+                .iter()
+                .zip(payloads.to_bytes()?)
+                .try_for_each(|((ephemeral_key, signature), payload)| {
+                    verify(
+                        &ephemeral_verifying_key(ephemeral_key)?,
+                        &payload,
+                        signature,
+                    )
+                })
+        })
+        .map_err(|_| CustomError::InvalidIssuedProof)
+    }
+
+    /// Presentation proof of the payloads disclosed (or proven by a predicate) in `payloads`, selected from the
+    /// `issuer_proof`. No key is needed: the holder only drops the signatures of the undisclosed payloads.
+    pub fn generate_presentation_proof(
+        alg: PresentationProofAlgorithm,
+        issuer_proof: &[u8],
+        payloads: &Payloads,
+    ) -> Result<Vec<u8>, CustomError> {
+        if issuer_proof.len() != Self::issuer_proof_len(payloads.len()) {
+            return Err(generation_error(
+                alg,
+                payloads,
+                "issuer proof does not match the payloads",
+            ));
+        }
+        let (issuer_signature, payload_proofs) = issuer_proof.split_at(Self::SIGNATURE_LEN);
+        let payload_proofs: Vec<(&[u8], &[u8])> = payload_proofs
+            .chunks(Self::EPHEMERAL_KEY_LEN + Self::SIGNATURE_LEN)
+            .map(|payload_proof| payload_proof.split_at(Self::EPHEMERAL_KEY_LEN))
+            .collect();
+        let disclosed_indexes = payloads.get_disclosed_indexes();
+
+        let mut proof = Vec::with_capacity(Self::presentation_proof_len(
+            payloads.len(),
+            disclosed_indexes.len(),
+        ));
+        proof.extend_from_slice(issuer_signature);
+        payload_proofs
+            .iter()
+            .for_each(|(ephemeral_key, _)| proof.extend_from_slice(ephemeral_key));
+        disclosed_indexes
+            .iter()
+            .for_each(|&index| proof.extend_from_slice(payload_proofs[index].1));
+        Ok(proof)
+    }
+
+    pub fn verify_presentation_proof(
+        alg: PresentationProofAlgorithm,
+        key: &Jwk,
+        proof: &[u8],
+        issuer_header: &[u8],
+        payloads: &Payloads,
+    ) -> Result<(), CustomError> {
+        let key_params = Self::public_key_params(key)?;
+        if !check_presentation_alg_curve_compatibility(alg, key_params.crv.clone()) {
+            return Err(CustomError::IncompatibleKeyAlgorithm {
+                alg: alg.to_string(),
+                crv: key_params.crv.to_string(),
+            });
+        }
+        let issuer_key = Self::verifying_key(key_params)?;
+        let disclosed_indexes = payloads.get_disclosed_indexes();
+        let expected = Self::presentation_proof_len(payloads.len(), disclosed_indexes.len());
+        if proof.len() != expected {
+            return Err(CustomError::InvalidProofLength {
+                expected,
+                got: proof.len(),
+            });
+        }
+
+        let (issuer_signature, rest) = proof.split_at(Self::SIGNATURE_LEN);
+        let (ephemeral_keys, payload_signatures) =
+            rest.split_at(Self::EPHEMERAL_KEY_LEN * payloads.len());
+
+        verify(
+            &issuer_key,
+            &[issuer_header, ephemeral_keys].concat(),
+            issuer_signature,
+        )
+        .and_then(|_| {
+            disclosed_indexes
+                .iter()
+                .zip(payload_signatures.chunks(Self::SIGNATURE_LEN))
+                .zip(payloads.disclosed_to_bytes()?)
+                .try_for_each(|((&index, signature), payload)| {
+                    let start = Self::EPHEMERAL_KEY_LEN * index;
+                    let ephemeral_key = ephemeral_verifying_key(
+                        &ephemeral_keys[start..start + Self::EPHEMERAL_KEY_LEN],
+                    )?;
+                    verify(&ephemeral_key, &payload, signature)
+                })
+        })
+        .map_err(|_| CustomError::InvalidPresentedProof)
+    }
+
+    /// Returns the Elliptic Curve parameters of a public key
+    fn public_key_params(key: &Jwk) -> Result<&JwkEllipticCurveKeyParameters, CustomError> {
+        key.check_key_ops(KeyOps::ProofVerification)?;
+        match &key.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) => Ok(params),
+            _ => Err(CustomError::ProofVerificationError(
+                "key is not compatible".to_string(),
+            )),
+        }
+    }
+
+    /// Decodes the P-256 public key from its coordinates
+    fn verifying_key(
+        key_params: &JwkEllipticCurveKeyParameters,
+    ) -> Result<VerifyingKey, CustomError> {
+        let x: [u8; Self::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&key_params.x)?
+            .try_into()
+            .map_err(|_| CustomError::InvalidJwk)?;
+        let y: [u8; Self::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&key_params.y)?
+            .try_into()
+            .map_err(|_| CustomError::InvalidJwk)?;
+
+        VerifyingKey::from_encoded_point(&EncodedPoint::from_affine_coordinates(
+            &x.into(),
+            &y.into(),
+            false,
+        ))
+        .map_err(|_| CustomError::InvalidJwk)
+    }
+}
+
+fn ephemeral_verifying_key(ephemeral_key: &[u8]) -> Result<VerifyingKey, CustomError> {
+    VerifyingKey::from_sec1_bytes(ephemeral_key).map_err(|_| CustomError::InvalidJwk)
+}
+
+fn verify(key: &VerifyingKey, message: &[u8], signature: &[u8]) -> Result<(), CustomError> {
+    let signature = Signature::from_slice(signature).map_err(|_| CustomError::InvalidJwk)?;
+    key.verify(message, &signature)
+        .map_err(|_| CustomError::InvalidJwk)
+}
+
+fn generation_error(
+    alg: impl fmt::Display,
+    payloads: &Payloads,
+    message: impl fmt::Display,
+) -> CustomError {
+    CustomError::ProofGenerationError {
+        alg: alg.to_string(),
+        message: message.to_string(),
+        payload_count: payloads.len(),
+    }
+}
@@ -12,15 +12,46 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::iter::zip;
+use alloc::{
+    borrow::ToOwned,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::iter::zip;
 
 use indexmap::IndexMap;
-use json_unflattening::{flattening::flatten, unflattening::unflatten};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 
-use super::payloads::Payloads;
+use crate::errors::CustomError;
 
+use super::{
+    digest::{DigestAlgorithm, DigestDisclosure},
+    flattening::{claims_tree, flatten, unflatten, ClaimNode, FlattenConfig},
+    payloads::{PayloadType, Payloads},
+};
+
+/// Names of the claims, in the same order of the payloads.
+///
+/// ```
+/// use jsonprooftoken::jpt::{
+///     claims::Claims,
+///     payloads::{PayloadType, Payloads},
+/// };
+/// use serde_json::json;
+///
+/// let claims = Claims(vec!["name".to_owned(), "age".to_owned()]);
+/// let mut payloads = Payloads::new_from_values(vec![json!("John Doe"), json!(42)]);
+/// payloads.set_undisclosed(1);
+///
+/// for (claim, (value, payload_type)) in claims.iter().zip(&payloads) {
+///     if *payload_type == PayloadType::Disclosed {
+///         println!("{claim}: {value}");
+///     }
+/// }
+/// assert!(claims.contains("age"));
+/// assert_eq!(claims.len(), payloads.len());
+/// ```
 #[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Claims(pub Vec<String>);
 
@@ -28,6 +59,57 @@ impl Claims {
     pub fn get_claim_index(&self, name: String) -> Option<usize> {
         self.0.iter().position(|x| *x == name)
     }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|x| x == name)
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, String> {
+        self.0.iter()
+    }
+
+    /// Tree of the claim names, following the hierarchy of the JSON they were flattened from
+    /// (e.g. `vc.degree.name` is the child `name` of `degree`, child of `vc`)
+    pub fn tree(&self) -> Result<Vec<ClaimNode>, CustomError> {
+        self.tree_with_config(&FlattenConfig::default())
+    }
+
+    /// Same as [`Claims::tree`], for claims flattened with a custom [`FlattenConfig`]
+    pub fn tree_with_config(&self, config: &FlattenConfig) -> Result<Vec<ClaimNode>, CustomError> {
+        claims_tree(&self.0, config)
+    }
+}
+
+impl IntoIterator for Claims {
+    type Item = String;
+    type IntoIter = alloc::vec::IntoIter<String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Claims {
+    type Item = &'a String;
+    type IntoIter = core::slice::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Resolves the claim names referenced by the `cid` of an issuer protected header without `claims`
+/// (see [`crate::jwp::issued::JwpIssuedDecoder::decode_with_resolver`]).
+pub trait ClaimsResolver {
+    fn resolve(&self, cid: &str) -> Option<Claims>;
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
@@ -37,6 +119,11 @@ pub struct CustomValue {
     flattening: bool,
 }
 
+/// Map holding the custom claims of a [`JptClaims`].
+///
+/// The hasher is the same with and without the `std` feature, so enabling it does not change this type.
+pub type CustomClaims = IndexMap<String, Value, foldhash::fast::RandomState>;
+
 /** These claims are taken from the JWT RFC (https://tools.ietf.org/html/rfc7519)
  * making the hypothesis that in the future will be used also for the JPTs **/
 
@@ -64,7 +151,7 @@ pub struct JptClaims {
     pub jti: Option<String>,
     /// Other custom claims (age, name, surname, Verifiable Credential, ...)
     #[serde(flatten)]
-    pub custom: IndexMap<String, Value>,
+    pub custom: CustomClaims,
 }
 
 impl JptClaims {
@@ -76,7 +163,7 @@ impl JptClaims {
             nbf: None,
             iat: None,
             jti: None,
-            custom: IndexMap::new(),
+            custom: CustomClaims::default(),
         }
     }
 
@@ -104,23 +191,164 @@ impl JptClaims {
         self.jti = Some(value);
     }
 
+    /// Set `jti` to `id`, in its hyphenated form
+    #[cfg(feature = "uuid")]
+    pub fn set_jti_uuid(&mut self, id: uuid::Uuid) {
+        self.jti = Some(id.hyphenated().to_string());
+    }
+
+    /// `jti` parsed as a UUID, `None` if it is not set. Errors with [`CustomError::InvalidUuid`] if it is not a UUID.
+    #[cfg(feature = "uuid")]
+    pub fn jti_as_uuid(&self) -> Option<Result<uuid::Uuid, CustomError>> {
+        self.jti.as_deref().map(|jti| {
+            uuid::Uuid::parse_str(jti).map_err(|e| CustomError::InvalidUuid(e.to_string()))
+        })
+    }
+
+    /// `exp` as a [`SystemTime`](std::time::SystemTime)
+    #[cfg(feature = "std")]
+    pub fn exp_as_time(&self) -> Option<std::time::SystemTime> {
+        self.exp.and_then(numeric_date_to_time)
+    }
+
+    /// `nbf` as a [`SystemTime`](std::time::SystemTime)
+    #[cfg(feature = "std")]
+    pub fn nbf_as_time(&self) -> Option<std::time::SystemTime> {
+        self.nbf.and_then(numeric_date_to_time)
+    }
+
+    /// `iat` as a [`SystemTime`](std::time::SystemTime)
+    #[cfg(feature = "std")]
+    pub fn iat_as_time(&self) -> Option<std::time::SystemTime> {
+        self.iat.and_then(numeric_date_to_time)
+    }
+
+    /// Check `exp` and `nbf` against `now`, tolerating a clock skew of `leeway`.
+    ///
+    /// As in RFC 7519, the claims are expired from `exp` on ([`CustomError::Expired`]) and valid from `nbf` on
+    /// ([`CustomError::NotYetValid`]). Claims that are not set are not checked. Times are compared in whole seconds.
+    #[cfg(feature = "std")]
+    pub fn validate_time(
+        &self,
+        now: std::time::SystemTime,
+        leeway: std::time::Duration,
+    ) -> Result<(), CustomError> {
+        let now = time_to_numeric_date(now);
+        let leeway = i64::try_from(leeway.as_secs()).unwrap_or(i64::MAX);
+        if let Some(exp) = self.exp {
+            if now >= exp.saturating_add(leeway) {
+                return Err(CustomError::Expired);
+            }
+        }
+        if let Some(nbf) = self.nbf {
+            if now < nbf.saturating_sub(leeway) {
+                return Err(CustomError::NotYetValid);
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that neither `nbf` nor `iat` is after `exp`, which no valid JPT can have whatever the current time.
+    /// Errors with [`CustomError::InconsistentTemporalClaims`] naming the claim otherwise. Claims that are not set
+    /// are not checked.
+    pub fn check_temporal_consistency(&self) -> Result<(), CustomError> {
+        let exp = match self.exp {
+            Some(exp) => exp,
+            None => return Ok(()),
+        };
+        for (name, value) in [("nbf", self.nbf), ("iat", self.iat)] {
+            if value.is_some_and(|value| value > exp) {
+                return Err(CustomError::InconsistentTemporalClaims(name.to_owned()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as [`JptClaims::validate_time`], with the system clock
+    #[cfg(feature = "std")]
+    pub fn validate_time_now(&self, leeway: std::time::Duration) -> Result<(), CustomError> {
+        self.validate_time(std::time::SystemTime::now(), leeway)
+    }
+
+    /// Set `exp` from a [`SystemTime`](std::time::SystemTime), truncated to seconds
+    #[cfg(feature = "std")]
+    pub fn set_exp_time(&mut self, value: std::time::SystemTime) {
+        self.exp = Some(time_to_numeric_date(value));
+    }
+
+    /// Set `nbf` from a [`SystemTime`](std::time::SystemTime), truncated to seconds
+    #[cfg(feature = "std")]
+    pub fn set_nbf_time(&mut self, value: std::time::SystemTime) {
+        self.nbf = Some(time_to_numeric_date(value));
+    }
+
+    /// Set `iat` from a [`SystemTime`](std::time::SystemTime), truncated to seconds
+    #[cfg(feature = "std")]
+    pub fn set_iat_time(&mut self, value: std::time::SystemTime) {
+        self.iat = Some(time_to_numeric_date(value));
+    }
+
+    /// Set a claim. With `flattened`, every leaf of an object value becomes its own claim (e.g. `vc.name`),
+    /// disclosable independently of the others. Otherwise the value is a single payload, disclosed or hidden
+    /// as a unit: its leaves cannot be selected by path, see [`JptClaims::set_atomic_claim`].
+    ///
+    /// # Panics
+    ///
+    /// If `value` cannot be represented as JSON (e.g. a map with non-string keys) or cannot be flattened.
+    /// Use [`JptClaims::set_claim_with_config`] with [`FlattenConfig::default`] to get the error instead.
     pub fn set_claim<T: Serialize>(&mut self, claim: Option<&str>, value: T, flattened: bool) {
-        let serde_value = serde_json::to_value(value).unwrap();
+        self.set_claim_with_config(claim, value, flattened, &FlattenConfig::default())
+            .unwrap();
+    }
+
+    /// Set a claim that is never flattened, for values that must be disclosed all-or-nothing
+    /// (e.g. a signature block). Errors with [`CustomError::JsonError`] if `value` cannot be represented as JSON.
+    pub fn set_atomic_claim<T: Serialize>(
+        &mut self,
+        name: &str,
+        value: T,
+    ) -> Result<(), CustomError> {
+        self.set_claim_with_config(Some(name), value, false, &FlattenConfig::default())
+    }
+
+    /// Same as [`JptClaims::set_claim`], but flattens the value using the delimiters in `config`
+    /// and returns an error instead of panicking.
+    pub fn set_claim_with_config<T: Serialize>(
+        &mut self,
+        claim: Option<&str>,
+        value: T,
+        flattened: bool,
+        config: &FlattenConfig,
+    ) -> Result<(), CustomError> {
+        let serde_value = serde_json::to_value(value)?;
         if !serde_value.is_object() {
             self.custom
                 .insert(claim.unwrap_or("").to_string(), serde_value);
+        } else if flattened {
+            let v = match claim {
+                Some(c) => json!({c: serde_value}),
+                None => serde_value,
+            };
+            self.custom.extend(flatten(&v, config)?);
         } else {
-            if flattened {
-                let v = match claim {
-                    Some(c) => json!({c: serde_value}),
-                    None => serde_value,
-                };
-                self.custom.extend(flatten(&v).unwrap());
-            } else {
-                self.custom
-                    .insert(claim.unwrap_or("").to_string(), serde_value);
-            }
+            self.custom
+                .insert(claim.unwrap_or("").to_string(), serde_value);
         };
+        Ok(())
+    }
+
+    /// Set a claim holding only the digest of `value` (see [`crate::jpt::digest`]). The returned disclosure
+    /// carries the value and is kept by the holder, to be checked by the verifier against the claim.
+    pub fn set_digest_claim<T: Serialize>(
+        &mut self,
+        name: &str,
+        value: T,
+        hash_alg: DigestAlgorithm,
+    ) -> Result<DigestDisclosure, CustomError> {
+        let value = serde_json::to_value(value)?;
+        self.custom
+            .insert(name.to_owned(), hash_alg.digest_claim(&value)?);
+        Ok(DigestDisclosure::new(name, value))
     }
 
     pub fn get_claim(&self, claim: &str) -> Option<&Value> {
@@ -131,24 +359,172 @@ impl JptClaims {
         self.custom.insert(claim.to_owned(), value)
     }
 
+    /// Number of payloads (BBS messages) [`JptClaims::get_claims_and_payloads`] would produce:
+    /// the registered claims that are set plus the custom claims, already flattened by [`JptClaims::set_claim`].
+    pub fn payload_count(&self) -> usize {
+        let registered = [
+            self.iss.is_some(),
+            self.sub.is_some(),
+            self.exp.is_some(),
+            self.nbf.is_some(),
+            self.iat.is_some(),
+            self.jti.is_some(),
+        ];
+        registered.iter().filter(|set| **set).count() + self.custom.len()
+    }
+
+    /// Fold the registered and custom claims of `other` into these claims. The custom claims of `other`
+    /// that are not already set come after the existing ones.
+    ///
+    /// A claim set in both with the same value is kept once. Fails with [`CustomError::DuplicateClaim`],
+    /// leaving these claims unchanged, if a claim is set in both with different values or if a custom claim
+    /// of one is a flattened path below a custom claim of the other (`vc` and `vc.name`).
+    pub fn merge(&mut self, other: JptClaims) -> Result<(), CustomError> {
+        self.merge_with_config(other, &FlattenConfig::default())
+    }
+
+    /// Same as [`JptClaims::merge`], for claims flattened with a custom [`FlattenConfig`].
+    pub fn merge_with_config(
+        &mut self,
+        other: JptClaims,
+        config: &FlattenConfig,
+    ) -> Result<(), CustomError> {
+        fn conflict<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+            matches!((a, b), (Some(a), Some(b)) if a != b)
+        }
+        let registered = [
+            ("iss", conflict(&self.iss, &other.iss)),
+            ("sub", conflict(&self.sub, &other.sub)),
+            ("exp", conflict(&self.exp, &other.exp)),
+            ("nbf", conflict(&self.nbf, &other.nbf)),
+            ("iat", conflict(&self.iat, &other.iat)),
+            ("jti", conflict(&self.jti, &other.jti)),
+        ];
+        if let Some((name, _)) = registered.iter().find(|(_, conflict)| *conflict) {
+            return Err(CustomError::DuplicateClaim((*name).to_owned()));
+        }
+
+        for (claim, value) in &other.custom {
+            let duplicate = match self.custom.get(claim) {
+                Some(existing) => existing != value,
+                None => self.custom.keys().any(|existing| {
+                    config.is_path_below(existing, claim) || config.is_path_below(claim, existing)
+                }),
+            };
+            if duplicate {
+                return Err(CustomError::DuplicateClaim(claim.clone()));
+            }
+        }
+
+        self.iss = self.iss.take().or(other.iss);
+        self.sub = self.sub.take().or(other.sub);
+        self.exp = self.exp.or(other.exp);
+        self.nbf = self.nbf.or(other.nbf);
+        self.iat = self.iat.or(other.iat);
+        self.jti = self.jti.take().or(other.jti);
+        self.custom.extend(other.custom);
+        Ok(())
+    }
+
     /// Extracts claims and payloads into separate vectors.
-    pub fn get_claims_and_payloads(&self) -> (Claims, Payloads) {
-        let jptclaims_json_value = serde_json::to_value(self).unwrap();
+    ///
+    /// The registered claims that are set come first, in the order `iss`, `sub`, `exp`, `nbf`, `iat`, `jti`,
+    /// followed by the custom claims in insertion order.
+    ///
+    /// Fails with [`CustomError::DuplicateClaim`] if a custom claim collides with a registered claim that is set:
+    /// either it has the same name (`jti`) or it is a flattened path below it (`jti.id`, `jti[0]`).
+    pub fn get_claims_and_payloads(&self) -> Result<(Claims, Payloads), CustomError> {
+        self.get_claims_and_payloads_with_config(&FlattenConfig::default())
+    }
 
-        let claims_payloads_pairs = jptclaims_json_value.as_object().unwrap().to_owned();
+    /// Same as [`JptClaims::get_claims_and_payloads`], for claims flattened with a custom [`FlattenConfig`].
+    pub fn get_claims_and_payloads_with_config(
+        &self,
+        config: &FlattenConfig,
+    ) -> Result<(Claims, Payloads), CustomError> {
+        let registered: [(&str, Option<Value>); 6] = [
+            ("iss", self.iss.clone().map(Value::from)),
+            ("sub", self.sub.clone().map(Value::from)),
+            ("exp", self.exp.map(Value::from)),
+            ("nbf", self.nbf.map(Value::from)),
+            ("iat", self.iat.map(Value::from)),
+            ("jti", self.jti.clone().map(Value::from)),
+        ];
+        let registered: Vec<(String, Value)> = registered
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.to_owned(), value?)))
+            .collect();
+
+        if let Some(duplicate) = self.custom.keys().find(|claim| {
+            registered
+                .iter()
+                .any(|(name, _)| *claim == name || config.is_path_below(claim, name))
+        }) {
+            return Err(CustomError::DuplicateClaim(duplicate.clone()));
+        }
 
-        let (keys, values): (Vec<String>, Vec<Value>) =
-            claims_payloads_pairs.to_owned().into_iter().unzip();
+        let (keys, values): (Vec<String>, Vec<Value>) = registered
+            .into_iter()
+            .chain(self.custom.iter().map(|(k, v)| (k.clone(), v.clone())))
+            .unzip();
 
-        (Claims(keys), Payloads::new_from_values(values))
+        Ok((Claims(keys), Payloads::new_from_values(values)))
     }
 
-    /// Reconstruct JptClaims from Claims and Payloads
-    pub fn from_claims_and_payloads(claims: &Claims, payloads: &Payloads) -> Self {
+    /// Same as [`JptClaims::get_claims_and_payloads`], with the claims sorted lexicographically
+    /// (byte order of the names) and each payload kept next to its claim.
+    ///
+    /// BBS messages are indexed by position, so use this when the other party expects sorted claims
+    /// instead of the insertion order. The order ends up in the issuer protected header, so verification
+    /// and selective disclosure follow it without further options.
+    pub fn get_claims_and_payloads_sorted(&self) -> Result<(Claims, Payloads), CustomError> {
+        let (claims, payloads) = self.get_claims_and_payloads()?;
+        let mut pairs: Vec<(String, (Value, PayloadType))> = zip(claims.0, payloads.0).collect();
+        pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let (keys, values): (Vec<String>, Vec<(Value, PayloadType)>) = pairs.into_iter().unzip();
+
+        Ok((Claims(keys), Payloads(values)))
+    }
+
+    /// Reconstruct JptClaims from Claims and Payloads.
+    ///
+    /// Claims that are not present (e.g. `vc.degree.ciao[0]` removed while `vc.degree.ciao[1]` is kept)
+    /// leave a `null` in their array position, see [`unflatten`].
+    pub fn from_claims_and_payloads(
+        claims: &Claims,
+        payloads: &Payloads,
+    ) -> Result<Self, CustomError> {
+        Self::from_claims_and_payloads_with_config(claims, payloads, &FlattenConfig::default())
+    }
+
+    /// Same as [`JptClaims::from_claims_and_payloads`], for claims flattened with a custom [`FlattenConfig`].
+    pub fn from_claims_and_payloads_with_config(
+        claims: &Claims,
+        payloads: &Payloads,
+        config: &FlattenConfig,
+    ) -> Result<Self, CustomError> {
         let zip: Map<String, Value> = zip(claims.0.clone(), payloads.get_values()).collect();
-        let unflat = unflatten(&zip).unwrap();
-        let jpt_claims: Self = serde_json::from_value(unflat).unwrap();
+        let unflat = unflatten(&zip, config)?;
+        serde_json::from_value(unflat).map_err(CustomError::from)
+    }
+}
+
+/// Convert a NumericDate (seconds since the Unix epoch, see RFC 7519) to a [`SystemTime`](std::time::SystemTime)
+#[cfg(feature = "std")]
+fn numeric_date_to_time(seconds: i64) -> Option<std::time::SystemTime> {
+    let offset = std::time::Duration::from_secs(seconds.unsigned_abs());
+    if seconds >= 0 {
+        std::time::UNIX_EPOCH.checked_add(offset)
+    } else {
+        std::time::UNIX_EPOCH.checked_sub(offset)
+    }
+}
 
-        jpt_claims
+/// Convert a [`SystemTime`](std::time::SystemTime) to a NumericDate, truncating to seconds
+#[cfg(feature = "std")]
+fn time_to_numeric_date(time: std::time::SystemTime) -> i64 {
+    match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(after) => after.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
     }
 }
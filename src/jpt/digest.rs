@@ -0,0 +1,104 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Digest-only claims, for large values (e.g. a photo) that would bloat the token.
+//!
+//! The payload holds `{"...": "<base64url digest>", "_sd_alg": "<hash algorithm>"}` instead of the value,
+//! as in SD-JWT. The holder keeps the value in a [`DigestDisclosure`] and hands it to the verifier
+//! next to the presentation, where it is checked against the disclosed payload.
+
+use alloc::{borrow::ToOwned, string::String};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    encoding::{base64url_encode, sort_keys},
+    errors::CustomError,
+};
+
+/// Member of a digest claim holding the digest
+pub const DIGEST_KEY: &str = "...";
+/// Member of a digest claim holding the hash algorithm
+pub const DIGEST_ALG_KEY: &str = "_sd_alg";
+
+/// Hash algorithms for digest claims, named as in the IANA Named Information Hash Algorithm registry
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DigestAlgorithm {
+    #[serde(rename = "sha-256")]
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// Digest of the compact JSON serialization of `value`, with the keys of its objects sorted
+    pub fn digest(&self, value: &Value) -> Result<String, CustomError> {
+        let bytes = serde_json::to_vec(&sort_keys(value.clone()))?;
+        let digest = match self {
+            DigestAlgorithm::Sha256 => Sha256::digest(bytes),
+        };
+        Ok(base64url_encode(digest))
+    }
+
+    /// Claim value that replaces `value` in the payloads
+    pub(crate) fn digest_claim(&self, value: &Value) -> Result<Value, CustomError> {
+        Ok(json!({
+            DIGEST_KEY: self.digest(value)?,
+            DIGEST_ALG_KEY: self,
+        }))
+    }
+}
+
+/// Value of a digest claim, kept by the holder (see [`crate::jpt::claims::JptClaims::set_digest_claim`])
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DigestDisclosure {
+    claim: String,
+    value: Value,
+}
+
+impl DigestDisclosure {
+    pub fn new(claim: &str, value: Value) -> Self {
+        Self {
+            claim: claim.to_owned(),
+            value,
+        }
+    }
+
+    // Getter for claim
+    pub fn claim(&self) -> &str {
+        &self.claim
+    }
+
+    // Getter for value
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Check the value against `digest_claim`, the disclosed payload of the claim, recomputing the digest
+    /// with the algorithm it names. Returns the value if it matches, [`CustomError::DigestMismatch`] otherwise.
+    pub fn verify(&self, digest_claim: &Value) -> Result<&Value, CustomError> {
+        let mismatch = || CustomError::DigestMismatch(self.claim.clone());
+        let digest = digest_claim
+            .get(DIGEST_KEY)
+            .and_then(Value::as_str)
+            .ok_or_else(mismatch)?;
+        let alg: DigestAlgorithm = digest_claim
+            .get(DIGEST_ALG_KEY)
+            .and_then(|alg| serde_json::from_value(alg.clone()).ok())
+            .ok_or_else(mismatch)?;
+        if alg.digest(&self.value)? != digest {
+            return Err(mismatch());
+        }
+        Ok(&self.value)
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloc::{
+    borrow::ToOwned,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::errors::CustomError;
+
+/// Delimiters used to build (and parse) the flattened claim names.
+///
+/// The default produces names like `vc.degree.ciao[0].u1`. Pick delimiters that never appear
+/// in the keys of your claims, otherwise flattening and unflattening cannot be done unambiguously.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlattenConfig {
+    /// Placed between an object key and its parent
+    pub key_separator: String,
+    /// Placed before an array index
+    pub array_start: String,
+    /// Placed after an array index
+    pub array_end: String,
+    /// Keep empty objects and arrays as claims instead of dropping them
+    pub preserve_empty: bool,
+}
+
+impl Default for FlattenConfig {
+    fn default() -> Self {
+        Self {
+            key_separator: ".".to_owned(),
+            array_start: "[".to_owned(),
+            array_end: "]".to_owned(),
+            preserve_empty: false,
+        }
+    }
+}
+
+impl FlattenConfig {
+    fn check(&self) -> Result<(), CustomError> {
+        if self.key_separator.is_empty() || self.array_start.is_empty() || self.array_end.is_empty()
+        {
+            return Err(CustomError::FlatteningError);
+        }
+        Ok(())
+    }
+
+    /// Whether `claim` is a flattened path below `name` (`name.child`, `name[0]`)
+    pub(crate) fn is_path_below(&self, claim: &str, name: &str) -> bool {
+        claim.strip_prefix(name).is_some_and(|rest| {
+            rest.starts_with(&self.key_separator) || rest.starts_with(&self.array_start)
+        })
+    }
+}
+
+/// Flattens a JSON object into a map of `path -> value`, using the delimiters in `config`.
+pub fn flatten(value: &Value, config: &FlattenConfig) -> Result<Map<String, Value>, CustomError> {
+    config.check()?;
+    let mut flattened = Map::new();
+
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten_value(&mut flattened, key.clone(), value, config)?;
+            }
+        }
+        _ => return Err(CustomError::FlatteningError),
+    }
+
+    Ok(flattened)
+}
+
+fn flatten_value(
+    flattened: &mut Map<String, Value>,
+    path: String,
+    value: &Value,
+    config: &FlattenConfig,
+) -> Result<(), CustomError> {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let path = format!("{}{}{}", path, config.key_separator, key);
+                flatten_value(flattened, path, value, config)?;
+            }
+        }
+        Value::Array(array) if !array.is_empty() => {
+            for (index, value) in array.iter().enumerate() {
+                let path = format!(
+                    "{}{}{}{}",
+                    path, config.array_start, index, config.array_end
+                );
+                flatten_value(flattened, path, value, config)?;
+            }
+        }
+        Value::Object(_) | Value::Array(_) if !config.preserve_empty => {}
+        _ => {
+            if flattened.insert(path, value.clone()).is_some() {
+                return Err(CustomError::FlatteningError);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+enum PathSegment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn parse_path<'a>(
+    path: &'a str,
+    config: &FlattenConfig,
+) -> Result<Vec<PathSegment<'a>>, CustomError> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+
+    if rest.is_empty() {
+        segments.push(PathSegment::Key(rest));
+    }
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix(config.array_start.as_str()) {
+            let end = after
+                .find(config.array_end.as_str())
+                .ok_or(CustomError::FlatteningError)?;
+            let index = after[..end]
+                .parse::<usize>()
+                .map_err(|_| CustomError::FlatteningError)?;
+            segments.push(PathSegment::Index(index));
+            rest = &after[end + config.array_end.len()..];
+        } else {
+            let rest_key = if segments.is_empty() {
+                rest
+            } else {
+                rest.strip_prefix(config.key_separator.as_str())
+                    .ok_or(CustomError::FlatteningError)?
+            };
+            let end = [
+                rest_key.find(config.key_separator.as_str()),
+                rest_key.find(config.array_start.as_str()),
+            ]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(rest_key.len());
+            segments.push(PathSegment::Key(&rest_key[..end]));
+            rest = &rest_key[end..];
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Rebuilds a JSON object from its flattened representation (e.g. `a.b[0].c`), using the delimiters in `config`.
+///
+/// Array positions are preserved: if some indexes are missing (e.g. only `ciao[1]` is present)
+/// the gaps are filled with `null`, so that each element keeps the index it had when it was flattened.
+pub fn unflatten(
+    flattened: &Map<String, Value>,
+    config: &FlattenConfig,
+) -> Result<Value, CustomError> {
+    config.check()?;
+    let mut output = Value::Object(Map::new());
+
+    for (path, value) in flattened {
+        let segments = parse_path(path, config)?;
+        let mut current = &mut output;
+
+        for (i, segment) in segments.iter().enumerate() {
+            let is_last = i == segments.len() - 1;
+            let next = match (segment, current) {
+                (PathSegment::Key(key), Value::Object(map)) => {
+                    map.entry(key.to_string()).or_insert(Value::Null)
+                }
+                (PathSegment::Index(index), Value::Array(array)) => {
+                    if array.len() <= *index {
+                        array.resize(*index + 1, Value::Null);
+                    }
+                    &mut array[*index]
+                }
+                _ => return Err(CustomError::FlatteningError),
+            };
+
+            if is_last {
+                if !next.is_null() {
+                    return Err(CustomError::FlatteningError);
+                }
+                *next = value.clone();
+            } else if next.is_null() {
+                *next = match segments[i + 1] {
+                    PathSegment::Key(_) => Value::Object(Map::new()),
+                    PathSegment::Index(_) => Value::Array(Vec::new()),
+                };
+            }
+            current = next;
+        }
+    }
+
+    Ok(output)
+}
+
+/// Segment of a flattened claim name: an object key or an array index
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum ClaimSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Node of the tree of the flattened claim names, mirroring the hierarchy of the original JSON
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ClaimNode {
+    pub segment: ClaimSegment,
+    /// Flattened name of the claim ending at this node, `None` for intermediate objects and arrays
+    pub claim: Option<String>,
+    pub children: Vec<ClaimNode>,
+}
+
+impl ClaimNode {
+    /// Number of levels of the subtree rooted at this node, 1 for a leaf
+    pub fn depth(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(ClaimNode::depth)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Builds the tree of the flattened claim `names`, using the delimiters in `config`.
+/// Siblings keep the order in which they first appear in `names`.
+pub fn claims_tree(
+    names: &[String],
+    config: &FlattenConfig,
+) -> Result<Vec<ClaimNode>, CustomError> {
+    config.check()?;
+    let mut roots: Vec<ClaimNode> = Vec::new();
+
+    for name in names {
+        let mut level = &mut roots;
+        let segments = parse_path(name, config)?;
+        let last = segments.len() - 1;
+        for (i, segment) in segments.into_iter().enumerate() {
+            let segment = match segment {
+                PathSegment::Key(key) => ClaimSegment::Key(key.to_owned()),
+                PathSegment::Index(index) => ClaimSegment::Index(index),
+            };
+            let position = match level.iter().position(|node| node.segment == segment) {
+                Some(position) => position,
+                None => {
+                    level.push(ClaimNode {
+                        segment,
+                        claim: None,
+                        children: Vec::new(),
+                    });
+                    level.len() - 1
+                }
+            };
+            let node = &mut level[position];
+            if i == last {
+                node.claim = Some(name.clone());
+            }
+            level = &mut node.children;
+        }
+    }
+
+    Ok(roots)
+}
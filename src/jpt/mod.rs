@@ -13,4 +13,6 @@
 // limitations under the License.
 
 pub mod claims;
+pub mod digest;
+pub mod flattening;
 pub mod payloads;
@@ -12,33 +12,119 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::errors::CustomError;
+use crate::{encoding::SortedKeys, errors::CustomError};
+use alloc::{format, string::String, vec::Vec};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 ///TODO: Not clear what to do with this information
 /// (https://datatracker.ietf.org/doc/html/draft-ietf-jose-json-proof-token#name-payloads)
+///
+/// This is the representation used inside the crate (and by the derived serde implementation of [`Payloads`]),
+/// see [`json_serialization`] for the one of the JWP serializations.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum PayloadType {
     Disclosed,
     Undisclosed,
     ProofMethods,
+    /// Not included in the serialization, but proven equal to the constant of a predicate in the presentation
+    /// protected header (see [`crate::jwp::header::Predicate`]). The BBS proof reveals it as a disclosed message.
+    Predicate,
 }
 
+/// Payloads of a JWP, in the same order of the claims in the issuer protected header.
+///
+/// ```
+/// use jsonprooftoken::jpt::payloads::{PayloadType, Payloads};
+/// use serde_json::json;
+///
+/// let mut payloads = Payloads::new_from_values(vec![json!("John Doe"), json!(42)]);
+/// payloads.set_undisclosed(1);
+///
+/// let disclosed: Vec<_> = payloads
+///     .iter()
+///     .filter(|(_, payload_type)| *payload_type == PayloadType::Disclosed)
+///     .map(|(value, _)| value)
+///     .collect();
+/// assert_eq!(disclosed, vec![&json!("John Doe")]);
+/// assert_eq!(payloads.len(), 2);
+/// assert_eq!(payloads.get(1), Some(&(json!(42), PayloadType::Undisclosed)));
+/// ```
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Payloads(pub Vec<(Value, PayloadType)>);
 
+impl IntoIterator for Payloads {
+    type Item = (Value, PayloadType);
+    type IntoIter = alloc::vec::IntoIter<(Value, PayloadType)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Payloads {
+    type Item = &'a (Value, PayloadType);
+    type IntoIter = core::slice::Iter<'a, (Value, PayloadType)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 impl Payloads {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&(Value, PayloadType)> {
+        self.0.get(index)
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, (Value, PayloadType)> {
+        self.0.iter()
+    }
+
+    /// Octets of the payloads signed by the issuer and checked by the proofs.
+    ///
+    /// Each value is serialized as compact JSON with the keys of its objects sorted,
+    /// so equal values produce the same bytes regardless of how they were built.
+    /// The values are serialized again on every call: the payloads are public, so there is no cache to keep in sync.
     pub fn to_bytes(&self) -> Result<Vec<Vec<u8>>, CustomError> {
-        let p: Result<Vec<Vec<u8>>, CustomError> = self
-            .0
+        self.0
             .iter()
-            .map(|v| match serde_json::to_vec(&v.0) {
-                Ok(vec) => Ok(vec),
-                Err(_) => Err(CustomError::SerializationError),
+            .map(|(value, _)| Self::value_bytes(value))
+            .collect()
+    }
+
+    /// Same as `get_disclosed_payloads().to_bytes()`, without copying the disclosed payloads first
+    pub fn disclosed_to_bytes(&self) -> Result<Vec<Vec<u8>>, CustomError> {
+        self.0
+            .iter()
+            .filter(|(_, payload_type)| {
+                matches!(
+                    payload_type,
+                    PayloadType::Disclosed | PayloadType::Predicate
+                )
             })
-            .collect();
-        p
+            .map(|(value, _)| Self::value_bytes(value))
+            .collect()
+    }
+
+    /// Length of each payload as signed, i.e. of each message of [`Payloads::to_bytes`], in payload order.
+    /// Useful to find the payloads that make a proof slow or a token large.
+    pub fn byte_sizes(&self) -> Result<Vec<usize>, CustomError> {
+        self.0
+            .iter()
+            .map(|(value, _)| Self::value_bytes(value).map(|bytes| bytes.len()))
+            .collect()
+    }
+
+    fn value_bytes(value: &Value) -> Result<Vec<u8>, CustomError> {
+        serde_json::to_vec(&SortedKeys(value)).map_err(CustomError::from)
     }
     pub fn new_from_values(values: Vec<Value>) -> Self {
         let mut payloads = Vec::new();
@@ -64,11 +150,12 @@ impl Payloads {
         undisclosed_indexes
     }
 
+    /// Indexes of the payloads revealed by the BBS proof, including the ones proven by a predicate
     pub fn get_disclosed_indexes(&self) -> Vec<usize> {
         let mut disclosed_indexes = Vec::new();
 
         for (index, (_, payload_type)) in self.0.iter().enumerate() {
-            if let PayloadType::Disclosed = payload_type {
+            if let PayloadType::Disclosed | PayloadType::Predicate = payload_type {
                 disclosed_indexes.push(index);
             }
         }
@@ -112,13 +199,107 @@ impl Payloads {
         });
     }
 
+    pub fn set_predicate(&mut self, index: usize) {
+        if let Some(v) = self.0.get_mut(index) {
+            v.1 = PayloadType::Predicate;
+        }
+    }
+
     pub fn replace_payload_at_index(
         &mut self,
         index: usize,
         value: Value,
     ) -> Result<Value, CustomError> {
         let dest = self.0.get_mut(index).ok_or(CustomError::IndexOutOfBounds)?;
-        let old = std::mem::replace(dest, (value, PayloadType::Disclosed));
+        let old = core::mem::replace(dest, (value, PayloadType::Disclosed));
         Ok(old.0)
     }
+
+    /// Wrap every payload in a `[salt, value]` pair, using one salt per payload
+    pub fn salted(&self, salts: Vec<String>) -> Result<Payloads, CustomError> {
+        if salts.len() != self.0.len() {
+            return Err(CustomError::IndexOutOfBounds);
+        }
+        Ok(Payloads(
+            self.0
+                .iter()
+                .zip(salts)
+                .map(|((value, payload_type), salt)| {
+                    (
+                        Value::Array(alloc::vec![Value::String(salt), value.clone()]),
+                        *payload_type,
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    /// Strip the salts from payloads built with [`Payloads::salted`]. Undisclosed payloads are left as they are.
+    pub fn unsalted(&self) -> Result<Payloads, CustomError> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(
+                |(index, (value, payload_type))| match (value, payload_type) {
+                    (_, PayloadType::Undisclosed) => Ok((value.clone(), *payload_type)),
+                    (Value::Array(pair), _) if pair.len() == 2 && pair[0].is_string() => {
+                        Ok((pair[1].clone(), *payload_type))
+                    }
+                    _ => Err(CustomError::SerializationError(format!(
+                        "payload {index} is not a [salt, value] pair"
+                    ))),
+                },
+            )
+            .collect::<Result<Vec<_>, _>>()
+            .map(Payloads)
+    }
+}
+
+/// Serde representation of [`Payloads`] as the `payloads` member of the JWP JSON serialization
+/// (https://datatracker.ietf.org/doc/html/draft-ietf-jose-json-web-proof#name-json-serialization):
+/// an array with the base64url encoded JSON of each disclosed payload and `null` for each undisclosed one.
+///
+/// Predicate payloads are not included, as in the compact serialization, and are serialized as `null`.
+/// The draft has no marker for proof methods, so they are serialized as disclosed payloads.
+/// When deserializing, the empty string of the compact serialization is also accepted for undisclosed payloads.
+///
+/// Use it with `#[serde(with = "jsonprooftoken::jpt::payloads::json_serialization")]`.
+pub mod json_serialization {
+    use alloc::{string::String, vec::Vec};
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+
+    use super::{PayloadType, Payloads};
+    use crate::encoding::{base64url_decode, base64url_encode};
+
+    pub fn serialize<S: Serializer>(payloads: &Payloads, serializer: S) -> Result<S::Ok, S::Error> {
+        payloads
+            .iter()
+            .map(|(value, payload_type)| match payload_type {
+                PayloadType::Undisclosed | PayloadType::Predicate => Ok(None),
+                PayloadType::Disclosed | PayloadType::ProofMethods => {
+                    serde_json::to_vec(value).map(|value_oct| Some(base64url_encode(value_oct)))
+                }
+            })
+            .collect::<Result<Vec<Option<String>>, _>>()
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Payloads, D::Error> {
+        Vec::<Option<String>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|payload| match payload.as_deref() {
+                None | Some("") => Ok((Value::Null, PayloadType::Undisclosed)),
+                Some(encoded) => {
+                    let value_oct = base64url_decode(encoded).map_err(de::Error::custom)?;
+                    Ok((
+                        serde_json::from_slice(&value_oct).map_err(de::Error::custom)?,
+                        PayloadType::Disclosed,
+                    ))
+                }
+            })
+            .collect::<Result<Vec<_>, D::Error>>()
+            .map(Payloads)
+    }
 }
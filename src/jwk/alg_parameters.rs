@@ -13,8 +13,9 @@
 // limitations under the License.
 
 use crate::{encoding::base64url_encode, jpa::algs::ProofAlgorithm};
+use alloc::string::String;
+use core::fmt;
 use serde::{Deserialize, Serialize};
-use std::fmt;
 
 use super::{curves::EllipticCurveTypes, types::KeyType};
 
@@ -95,12 +96,9 @@ impl JwkOctetKeyPairParameters {
     pub fn new<T: AsRef<[u8]>>(crv: EllipticCurveTypes, x: T, d: Option<T>) -> Self {
         Self {
             kty: KeyType::OctetKeyPair,
-            crv: crv,
+            crv,
             x: base64url_encode(x),
-            d: match d {
-                Some(d) => Some(base64url_encode(d)),
-                None => None,
-            },
+            d: d.map(base64url_encode),
         }
     }
 
@@ -156,13 +154,10 @@ impl JwkEllipticCurveKeyParameters {
     pub fn new(crv: EllipticCurveTypes, x: &[u8], y: &[u8], d: Option<&[u8]>) -> Self {
         Self {
             kty: KeyType::EllipticCurve,
-            crv: crv,
+            crv,
             x: base64url_encode(x),
             y: base64url_encode(y),
-            d: match d {
-                Some(d) => Some(base64url_encode(d)),
-                None => None,
-            },
+            d: d.map(base64url_encode),
         }
     }
 
@@ -12,17 +12,42 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::errors::CustomError;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
 use serde::{Deserialize, Serialize};
+
+use super::{
+    alg_parameters::{Algorithm, JwkAlgorithmParameters},
+    curves::EllipticCurveTypes,
+    types::{AlgorithmFamily, KeyType},
+    utils::{check_alg_curve_compatibility, check_presentation_alg_curve_compatibility},
+};
+use crate::jpa::algs::{PresentationProofAlgorithm, ProofAlgorithm};
+
+use crate::errors::CustomError;
+#[cfg(feature = "std")]
+use p256::ecdsa::SigningKey;
+#[cfg(feature = "std")]
+use rand_core::OsRng;
+#[cfg(feature = "std")]
 use zkryptium::{
+    bbsplus::keys::{BBSplusPublicKey, BBSplusSecretKey},
     keys::pair::KeyPair,
     schemes::algorithms::{BbsBls12381Sha256, BbsBls12381Shake256},
 };
 
+#[cfg(feature = "std")]
 use super::{
-    alg_parameters::{Algorithm, JwkAlgorithmParameters, JwkEllipticCurveKeyParameters},
+    alg_parameters::{JwkEllipticCurveKeyParameters, JwkOctetKeyPairParameters},
     types::KeyPairSubtype,
 };
+#[cfg(feature = "std")]
+use crate::{
+    encoding::{base64url_decode, base64url_encode},
+    jpa::bbs_plus::BBSplusAlgorithm,
+};
 
 /// JWK parameters defined at https://datatracker.ietf.org/doc/html/rfc7517#section-4
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -66,6 +91,9 @@ pub struct Jwk {
 }
 
 impl Jwk {
+    /// Generate a BLS12381G2 or P-256 key pair. The `alg` of the key is left unset, so the key is not tied to a
+    /// ciphersuite: set it with [`Jwk::set_alg`] to have [`crate::jwp::issued::JwpIssuedBuilder::build`] check it.
+    #[cfg(feature = "std")]
     pub fn generate(key_type: KeyPairSubtype) -> Result<Self, CustomError> {
         match key_type {
             KeyPairSubtype::BLS12381G2Sha256 => {
@@ -116,9 +144,76 @@ impl Jwk {
                     key_params: jwk_params,
                 })
             }
+            KeyPairSubtype::P256 => {
+                let sk = SigningKey::random(&mut OsRng);
+                let pk = sk.verifying_key().to_encoded_point(false);
+                let (x, y) = pk
+                    .x()
+                    .zip(pk.y())
+                    .ok_or(CustomError::JwkGenerationError("Keygen failed".to_owned()))?;
+                let ec_params = JwkEllipticCurveKeyParameters::new(
+                    EllipticCurveTypes::P256,
+                    x,
+                    y,
+                    Some(&sk.to_bytes()),
+                );
+                Ok(Self {
+                    kid: None,
+                    pk_use: None,
+                    key_ops: None,
+                    alg: None,
+                    x5u: None,
+                    x5c: None,
+                    x5t: None,
+                    key_params: JwkAlgorithmParameters::EllipticCurve(ec_params),
+                })
+            }
         }
     }
 
+    /// BLS12381G2 public key from its 96-byte compressed form (e.g. `BBSplusPublicKey::to_bytes` of zkryptium).
+    /// The key is in the EC representation, the one used for the proofs (see [`Jwk::to_okp_form`]).
+    #[cfg(feature = "std")]
+    pub fn from_bls_public_bytes(pk: &[u8]) -> Result<Jwk, CustomError> {
+        Ok(Self::from_key_params(
+            JwkAlgorithmParameters::EllipticCurve(Self::bls_public_params(pk)?),
+        ))
+    }
+
+    /// BLS12381G2 key pair from the 32-byte secret key and the 96-byte compressed public key.
+    /// Errors with [`CustomError::InvalidJwk`] if `pk` is not the public key of `sk`.
+    #[cfg(feature = "std")]
+    pub fn from_bls_keypair_bytes(sk: &[u8], pk: &[u8]) -> Result<Jwk, CustomError> {
+        if sk.len() != BBSplusAlgorithm::SECRET_KEY_LEN {
+            return Err(CustomError::InvalidJwk);
+        }
+        let secret_key = BBSplusSecretKey::from_bytes(sk).map_err(|_| CustomError::InvalidJwk)?;
+        let mut params = Self::bls_public_params(pk)?;
+        if secret_key.public_key().to_bytes()[..] != *pk {
+            return Err(CustomError::InvalidJwk);
+        }
+        params.d = Some(base64url_encode(sk));
+        Ok(Self::from_key_params(
+            JwkAlgorithmParameters::EllipticCurve(params),
+        ))
+    }
+
+    #[cfg(feature = "std")]
+    fn bls_public_params(pk: &[u8]) -> Result<JwkEllipticCurveKeyParameters, CustomError> {
+        if pk.len() != BBSplusAlgorithm::PUBLIC_KEY_LEN {
+            return Err(CustomError::InvalidJwk);
+        }
+        let (x, y) = BBSplusPublicKey::from_bytes(pk)
+            .map_err(|_| CustomError::InvalidJwk)?
+            .to_coordinates();
+        Ok(JwkEllipticCurveKeyParameters::new(
+            EllipticCurveTypes::BLS12381G2,
+            &x,
+            &y,
+            None,
+        ))
+    }
+
     pub fn set_kid(&mut self, kid: &str) {
         self.kid = Some(kid.to_string());
     }
@@ -147,6 +242,41 @@ impl Jwk {
         self.x5t = Some(x5t.to_string());
     }
 
+    /// DER of the leaf certificate of `x5c`, the first of the chain
+    pub fn x5c_leaf_der(&self) -> Option<Vec<u8>> {
+        super::x5c::leaf_der(self.x5c.as_ref()?)
+    }
+
+    /// Check that the leaf certificate of `x5c` certifies the public key of this BLS12381G2 JWK, i.e. that its
+    /// subject public key is the compressed point (as `x` of the OKP form) or `x || y` (as in the EC form).
+    /// The chain itself is not validated.
+    #[cfg(feature = "std")]
+    pub fn verify_x5c_binding(&self) -> Result<(), CustomError> {
+        let leaf = self.x5c_leaf_der().ok_or_else(|| {
+            CustomError::InvalidCertificate("missing or not base64 leaf certificate".to_owned())
+        })?;
+        let certified = super::x5c::subject_public_key(&leaf)?;
+
+        let compressed = match &self.to_okp_form()?.key_params {
+            JwkAlgorithmParameters::OctetKeyPair(params) => base64url_decode(&params.x)?,
+            JwkAlgorithmParameters::EllipticCurve(_) => return Err(CustomError::InvalidJwk),
+        };
+        let uncompressed = match &self.to_ec_form()?.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) => {
+                let mut point = base64url_decode(&params.x)?;
+                point.extend(base64url_decode(&params.y)?);
+                point
+            }
+            JwkAlgorithmParameters::OctetKeyPair(_) => return Err(CustomError::InvalidJwk),
+        };
+
+        if certified == compressed || certified == uncompressed {
+            Ok(())
+        } else {
+            Err(CustomError::X5cKeyMismatch)
+        }
+    }
+
     /// Returns `true` if _all_ private key components of the key are unset, `false` otherwise.
     pub fn is_public(&self) -> bool {
         self.key_params.is_public()
@@ -157,8 +287,177 @@ impl Jwk {
         self.key_params.is_private()
     }
 
+    /// Returns `true` if `other` has the same key material (`kty`, `crv`, `x`, `y` and `d`), whatever
+    /// its metadata (`kid`, `use`, `key_ops`, `alg`, `x5*`). The EC and OKP forms of a key are not the same key.
+    pub fn same_key(&self, other: &Jwk) -> bool {
+        self.key_params == other.key_params
+    }
+
+    /// Like [`Jwk::same_key`], comparing only the public components: a private key matches its own public form.
+    pub fn matches_public(&self, other: &Jwk) -> bool {
+        match (self.key_params.to_public(), other.key_params.to_public()) {
+            (Some(public), Some(other_public)) => public == other_public,
+            _ => false,
+        }
+    }
+
+    /// Returns `false` if `key_ops` or `use`, when present, forbid `op`
+    pub fn permits(&self, op: KeyOps) -> bool {
+        let signing = matches!(
+            op,
+            KeyOps::Sign | KeyOps::Verify | KeyOps::ProofGeneration | KeyOps::ProofVerification
+        );
+        let use_permits = match self.pk_use {
+            None => true,
+            Some(PKUse::Signature | PKUse::Proof) => signing,
+            Some(PKUse::Encryption) => !signing,
+        };
+        use_permits && self.key_ops.as_ref().map_or(true, |ops| ops.contains(&op))
+    }
+
+    /// Errors with [`CustomError::KeyOpsNotPermitted`] unless the key [permits](Jwk::permits) `op`
+    #[cfg(feature = "std")]
+    pub(crate) fn check_key_ops(&self, op: KeyOps) -> Result<(), CustomError> {
+        if self.permits(op) {
+            Ok(())
+        } else {
+            Err(CustomError::KeyOpsNotPermitted(op))
+        }
+    }
+
+    /// Algorithm family of the key, `None` if `kty` and `crv` are not consistent
+    pub fn algorithm_family(&self) -> Option<AlgorithmFamily> {
+        let (kty, crv) = self.kty_and_crv();
+        match (kty, crv) {
+            (_, EllipticCurveTypes::BLS12381G1 | EllipticCurveTypes::BLS12381G2) => {
+                Some(AlgorithmFamily::Bls12381)
+            }
+            (_, EllipticCurveTypes::BLS48581G1 | EllipticCurveTypes::BLS48581G2) => {
+                Some(AlgorithmFamily::Bls48581)
+            }
+            (
+                KeyType::EllipticCurve,
+                EllipticCurveTypes::P256
+                | EllipticCurveTypes::P384
+                | EllipticCurveTypes::P521
+                | EllipticCurveTypes::Secp256K1,
+            ) => Some(AlgorithmFamily::Ec),
+            (KeyType::OctetKeyPair, EllipticCurveTypes::Ed25519 | EllipticCurveTypes::Ed448) => {
+                Some(AlgorithmFamily::Ed)
+            }
+            (KeyType::OctetKeyPair, EllipticCurveTypes::X25519 | EllipticCurveTypes::X448) => {
+                Some(AlgorithmFamily::X)
+            }
+            _ => None,
+        }
+    }
+
+    /// Errors with [`CustomError::IncompatibleKeyAlgorithm`] if the `alg` of the key is set to an algorithm
+    /// other than `alg`, e.g. a key declared for the SHAKE-256 ciphersuite used with [`ProofAlgorithm::BBS`]
+    #[cfg(feature = "std")]
+    pub(crate) fn check_proof_alg(&self, alg: ProofAlgorithm) -> Result<(), CustomError> {
+        match self.alg {
+            Some(Algorithm::Proof(key_alg)) if key_alg != alg => {
+                Err(CustomError::IncompatibleKeyAlgorithm {
+                    alg: alg.to_string(),
+                    crv: self.kty_and_crv().1.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `true` if the key can be used to generate or verify proofs with `alg`
+    pub fn supports_proof_alg(&self, alg: ProofAlgorithm) -> bool {
+        check_alg_curve_compatibility(Algorithm::Proof(alg), self.kty_and_crv().1.clone())
+    }
+
+    /// Returns `true` if the key can be used to generate or verify presentation proofs with `alg`
+    pub fn supports_presentation_alg(&self, alg: PresentationProofAlgorithm) -> bool {
+        check_presentation_alg_curve_compatibility(alg, self.kty_and_crv().1.clone())
+    }
+
+    fn kty_and_crv(&self) -> (KeyType, &EllipticCurveTypes) {
+        match &self.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) => (params.kty, &params.crv),
+            JwkAlgorithmParameters::OctetKeyPair(params) => (params.kty, &params.crv),
+        }
+    }
+
+    /// Convert a BLS12381G2 key to the OKP representation, where `x` is the compressed G2 point.
+    /// Keys already in the OKP representation are returned unchanged.
+    #[cfg(feature = "std")]
+    pub fn to_okp_form(&self) -> Result<Jwk, CustomError> {
+        let params = match &self.key_params {
+            JwkAlgorithmParameters::OctetKeyPair(_) => return Ok(self.clone()),
+            JwkAlgorithmParameters::EllipticCurve(params) => params,
+        };
+        if params.crv != EllipticCurveTypes::BLS12381G2 {
+            return Err(CustomError::CurveNotSupported);
+        }
+        let x: [u8; BBSplusAlgorithm::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&params.x)?
+            .try_into()
+            .map_err(|_| CustomError::InvalidJwk)?;
+        let y: [u8; BBSplusAlgorithm::PUBLIC_KEY_COORDINATE_LEN] = base64url_decode(&params.y)?
+            .try_into()
+            .map_err(|_| CustomError::InvalidJwk)?;
+        let pk = BBSplusPublicKey::from_coordinates(&x, &y).map_err(|_| CustomError::InvalidJwk)?;
+
+        let mut okp_params =
+            JwkOctetKeyPairParameters::new(EllipticCurveTypes::BLS12381G2, pk.to_bytes(), None);
+        okp_params.d = params.d.clone();
+        Ok(self.with_key_params(JwkAlgorithmParameters::OctetKeyPair(okp_params)))
+    }
+
+    /// Convert a BLS12381G2 key to the EC representation, where `x` and `y` are the coordinates of the G2 point.
+    /// Keys already in the EC representation are returned unchanged.
+    #[cfg(feature = "std")]
+    pub fn to_ec_form(&self) -> Result<Jwk, CustomError> {
+        let params = match &self.key_params {
+            JwkAlgorithmParameters::EllipticCurve(_) => return Ok(self.clone()),
+            JwkAlgorithmParameters::OctetKeyPair(params) => params,
+        };
+        if params.crv != EllipticCurveTypes::BLS12381G2 {
+            return Err(CustomError::CurveNotSupported);
+        }
+        let pk = BBSplusPublicKey::from_bytes(&base64url_decode(&params.x)?)
+            .map_err(|_| CustomError::InvalidJwk)?;
+        let (x, y) = pk.to_coordinates();
+
+        let mut ec_params =
+            JwkEllipticCurveKeyParameters::new(EllipticCurveTypes::BLS12381G2, &x, &y, None);
+        ec_params.d = params.d.clone();
+        Ok(self.with_key_params(JwkAlgorithmParameters::EllipticCurve(ec_params)))
+    }
+
+    #[cfg(feature = "std")]
+    fn with_key_params(&self, key_params: JwkAlgorithmParameters) -> Jwk {
+        Jwk {
+            key_params,
+            ..self.clone()
+        }
+    }
+
+    /// Import a BLS12381G2 key (private or public) from PEM, see [`Jwk::to_pem`]
+    pub fn from_pem(pem: &str) -> Result<Self, CustomError> {
+        Ok(Self::from_key_params(
+            JwkAlgorithmParameters::EllipticCurve(super::pem::from_pem(pem)?),
+        ))
+    }
+
+    /// Export a BLS12381G2 key in the EC representation to PEM.
+    ///
+    /// Private keys are exported as `BLS12381G2 PRIVATE KEY`, public keys as `BLS12381G2 PUBLIC KEY`.
+    /// The DER structure is crate specific, as no standard one exists yet for BLS keys.
+    pub fn to_pem(&self) -> Result<String, CustomError> {
+        match &self.key_params {
+            JwkAlgorithmParameters::EllipticCurve(params) => super::pem::to_pem(params),
+            JwkAlgorithmParameters::OctetKeyPair(_) => Err(CustomError::InvalidJwk),
+        }
+    }
+
     pub fn from_key_params(key_params: JwkAlgorithmParameters) -> Self {
-        let params: JwkAlgorithmParameters = key_params.into();
+        let params: JwkAlgorithmParameters = key_params;
         Self {
             kid: None,
             pk_use: None,
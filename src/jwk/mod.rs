@@ -15,6 +15,8 @@
 pub mod alg_parameters;
 pub mod curves;
 pub mod key;
+pub(crate) mod pem;
 pub mod set;
 pub mod types;
 pub(crate) mod utils;
+pub(crate) mod x5c;
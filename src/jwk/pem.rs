@@ -0,0 +1,167 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! PEM encoding of BLS12381G2 keys.
+//!
+//! There is no registered PKCS#8 algorithm identifier for BLS keys yet, so this is a crate specific
+//! DER wrapper modeled on SEC1 (`x || y` are the 96-byte coordinates of the public key, as in the JWK):
+//!
+//! ```text
+//! -----BEGIN BLS12381G2 PRIVATE KEY-----
+//! SEQUENCE { INTEGER 1, OCTET STRING d, [1] EXPLICIT BIT STRING x || y }
+//!
+//! -----BEGIN BLS12381G2 PUBLIC KEY-----
+//! SEQUENCE { BIT STRING x || y }
+//! ```
+
+use alloc::{format, string::String, vec::Vec};
+use data_encoding::BASE64;
+
+use crate::{
+    encoding::{base64url_decode, base64url_encode},
+    errors::CustomError,
+};
+
+use super::{alg_parameters::JwkEllipticCurveKeyParameters, curves::EllipticCurveTypes};
+
+const PRIVATE_KEY_LABEL: &str = "BLS12381G2 PRIVATE KEY";
+const PUBLIC_KEY_LABEL: &str = "BLS12381G2 PUBLIC KEY";
+const COORDINATE_BYTES: usize = 96;
+const PEM_LINE_LEN: usize = 64;
+
+const TAG_INTEGER: u8 = 0x02;
+pub(super) const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+pub(super) const TAG_SEQUENCE: u8 = 0x30;
+const TAG_PUBLIC_KEY: u8 = 0xa1;
+
+pub(crate) fn to_pem(params: &JwkEllipticCurveKeyParameters) -> Result<String, CustomError> {
+    if params.crv != EllipticCurveTypes::BLS12381G2 {
+        return Err(CustomError::CurveNotSupported);
+    }
+    let mut point = base64url_decode(&params.x)?;
+    point.extend(base64url_decode(&params.y)?);
+    if point.len() != 2 * COORDINATE_BYTES {
+        return Err(CustomError::InvalidJwk);
+    }
+    let public_key = der_bit_string(&point);
+
+    let (label, der) = match &params.d {
+        Some(d) => {
+            let mut content = der_tlv(TAG_INTEGER, &[1]);
+            content.extend(der_tlv(TAG_OCTET_STRING, &base64url_decode(d)?));
+            content.extend(der_tlv(TAG_PUBLIC_KEY, &public_key));
+            (PRIVATE_KEY_LABEL, der_tlv(TAG_SEQUENCE, &content))
+        }
+        None => (PUBLIC_KEY_LABEL, der_tlv(TAG_SEQUENCE, &public_key)),
+    };
+
+    let body = BASE64.encode(&der);
+    let lines: Vec<&str> = body
+        .as_bytes()
+        .chunks(PEM_LINE_LEN)
+        .map(|line| core::str::from_utf8(line).unwrap())
+        .collect();
+    Ok(format!(
+        "-----BEGIN {label}-----\n{}\n-----END {label}-----\n",
+        lines.join("\n")
+    ))
+}
+
+pub(crate) fn from_pem(pem: &str) -> Result<JwkEllipticCurveKeyParameters, CustomError> {
+    let mut lines = pem.lines().map(str::trim).filter(|line| !line.is_empty());
+    let label = lines
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .ok_or(CustomError::InvalidPem)?;
+    let end = format!("-----END {label}-----");
+    let body: String = lines.by_ref().take_while(|line| *line != end).collect();
+    let der = BASE64
+        .decode(body.as_bytes())
+        .map_err(|_| CustomError::InvalidPem)?;
+
+    let (content, rest) = der_parse(TAG_SEQUENCE, &der)?;
+    if !rest.is_empty() {
+        return Err(CustomError::InvalidPem);
+    }
+
+    let (d, public_key) = match label {
+        PRIVATE_KEY_LABEL => {
+            let (version, content) = der_parse(TAG_INTEGER, content)?;
+            if version != [1] {
+                return Err(CustomError::InvalidPem);
+            }
+            let (d, content) = der_parse(TAG_OCTET_STRING, content)?;
+            let (public_key, _) = der_parse(TAG_PUBLIC_KEY, content)?;
+            (Some(d), public_key)
+        }
+        PUBLIC_KEY_LABEL => (None, content),
+        _ => return Err(CustomError::InvalidPem),
+    };
+
+    let (point, _) = der_parse(TAG_BIT_STRING, public_key)?;
+    let point = match point {
+        [0, point @ ..] if point.len() == 2 * COORDINATE_BYTES => point,
+        _ => return Err(CustomError::InvalidPem),
+    };
+    let (x, y) = point.split_at(COORDINATE_BYTES);
+
+    Ok(JwkEllipticCurveKeyParameters {
+        d: d.map(base64url_encode),
+        ..JwkEllipticCurveKeyParameters::new(EllipticCurveTypes::BLS12381G2, x, y, None)
+    })
+}
+
+fn der_bit_string(bytes: &[u8]) -> Vec<u8> {
+    let mut content = Vec::with_capacity(bytes.len() + 1);
+    // No unused bits
+    content.push(0);
+    content.extend_from_slice(bytes);
+    der_tlv(TAG_BIT_STRING, &content)
+}
+
+fn der_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut tlv = Vec::with_capacity(content.len() + 4);
+    tlv.push(tag);
+    match content.len() {
+        len @ 0..=0x7f => tlv.push(len as u8),
+        len @ 0x80..=0xff => tlv.extend([0x81, len as u8]),
+        len => tlv.extend([0x82, (len >> 8) as u8, len as u8]),
+    }
+    tlv.extend_from_slice(content);
+    tlv
+}
+
+/// Parse a DER element with the expected `tag`, returning its content and the remaining bytes
+pub(super) fn der_parse(tag: u8, der: &[u8]) -> Result<(&[u8], &[u8]), CustomError> {
+    match der_element(der)? {
+        (t, content, rest) if t == tag => Ok((content, rest)),
+        _ => Err(CustomError::InvalidPem),
+    }
+}
+
+/// Parse a DER element with any tag, returning its tag, its content and the remaining bytes
+pub(super) fn der_element(der: &[u8]) -> Result<(u8, &[u8], &[u8]), CustomError> {
+    let (tag, len, header_len) = match der {
+        [t, len @ 0..=0x7f, ..] => (*t, *len as usize, 2),
+        [t, 0x81, len, ..] => (*t, *len as usize, 3),
+        [t, 0x82, high, low, ..] => (*t, ((*high as usize) << 8) | *low as usize, 4),
+        _ => return Err(CustomError::InvalidPem),
+    };
+    let content = der
+        .get(header_len..header_len + len)
+        .ok_or(CustomError::InvalidPem)?;
+    Ok((tag, content, &der[header_len + len..]))
+}
@@ -12,7 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::errors::CustomError;
 
 use super::key::Jwk;
 
@@ -28,4 +36,55 @@ impl JwkSet {
             .iter()
             .find(|jwk| jwk.kid.is_some() && jwk.kid.as_ref().unwrap() == kid)
     }
+
+    /// Parse a JWK Set, e.g. the body of a JWKS endpoint response. Fails with [`CustomError::JsonError`] if any key
+    /// cannot be parsed, see [`JwkSet::from_json_lenient`] to skip them instead.
+    pub fn from_json(json: &str) -> Result<JwkSet, CustomError> {
+        serde_json::from_str(json).map_err(CustomError::from)
+    }
+
+    /// Serialize the JWK Set as JSON
+    pub fn to_json(&self) -> Result<String, CustomError> {
+        serde_json::to_string(self).map_err(CustomError::from)
+    }
+
+    /// Parse a JWK Set parsing each key independently, so that keys this crate can't use (e.g. RSA keys
+    /// served by the same JWKS endpoint) are skipped instead of failing the whole set.
+    ///
+    /// Returns the usable keys together with a description of every skipped entry.
+    pub fn from_json_lenient(json: &str) -> (JwkSet, Vec<String>) {
+        let mut set = JwkSet { keys: Vec::new() };
+        let mut skipped = Vec::new();
+
+        let entries = match serde_json::from_str::<Value>(json) {
+            Ok(Value::Object(mut object)) => match object.remove("keys") {
+                Some(Value::Array(entries)) => entries,
+                _ => {
+                    skipped.push("missing \"keys\" array".to_string());
+                    return (set, skipped);
+                }
+            },
+            Ok(_) => {
+                skipped.push("not a JSON object".to_string());
+                return (set, skipped);
+            }
+            Err(err) => {
+                skipped.push(err.to_string());
+                return (set, skipped);
+            }
+        };
+
+        for (index, entry) in entries.into_iter().enumerate() {
+            let kid = entry.get("kid").and_then(Value::as_str).map(String::from);
+            match serde_json::from_value::<Jwk>(entry) {
+                Ok(jwk) => set.keys.push(jwk),
+                Err(err) => skipped.push(match kid {
+                    Some(kid) => format!("key {index} (kid {kid}): {err}"),
+                    None => format!("key {index}: {err}"),
+                }),
+            }
+        }
+
+        (set, skipped)
+    }
 }
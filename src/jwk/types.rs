@@ -12,10 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::str::FromStr;
+use core::str::FromStr;
 
 use serde::{Deserialize, Serialize};
 
+use crate::jpa::algs::ProofAlgorithm;
+
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Copy, Clone)]
 pub enum KeyType {
     #[serde(rename = "EC")]
@@ -28,11 +30,48 @@ pub enum KeyType {
     OctetKeyPair,
 }
 
+/// Family of algorithms a key can be used with, derived from its `kty` and `crv`
+#[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize, Copy, Clone)]
+pub enum AlgorithmFamily {
+    /// NIST and secp256k1 curves (ECDSA)
+    Ec,
+    /// Ed25519 and Ed448 (EdDSA)
+    Ed,
+    /// X25519 and X448 (ECDH)
+    X,
+    /// BLS12-381 curves (BBS)
+    Bls12381,
+    /// BLS48-581 curves
+    Bls48581,
+}
+
 #[derive(Debug, Eq, PartialEq, Serialize, Deserialize, Copy, Clone)]
 
 pub enum KeyPairSubtype {
     BLS12381G2Sha256,
     BLS12381G2Shake256,
+    P256,
+}
+
+impl KeyPairSubtype {
+    /// Key pair subtype for the ciphersuite of `alg`, `None` for the algorithms not implemented
+    pub fn for_proof_alg(alg: ProofAlgorithm) -> Option<KeyPairSubtype> {
+        match alg {
+            ProofAlgorithm::BBS => Some(KeyPairSubtype::BLS12381G2Sha256),
+            ProofAlgorithm::BBS_SHAKE256 => Some(KeyPairSubtype::BLS12381G2Shake256),
+            ProofAlgorithm::SU_ES256 => Some(KeyPairSubtype::P256),
+            _ => None,
+        }
+    }
+
+    /// Proof algorithm using the ciphersuite of the key pair subtype
+    pub fn proof_alg(&self) -> ProofAlgorithm {
+        match self {
+            KeyPairSubtype::BLS12381G2Sha256 => ProofAlgorithm::BBS,
+            KeyPairSubtype::BLS12381G2Shake256 => ProofAlgorithm::BBS_SHAKE256,
+            KeyPairSubtype::P256 => ProofAlgorithm::SU_ES256,
+        }
+    }
 }
 
 impl FromStr for KeyPairSubtype {
@@ -42,6 +81,7 @@ impl FromStr for KeyPairSubtype {
         match s.to_lowercase().as_str() {
             "bls12381sha256" => Ok(KeyPairSubtype::BLS12381G2Sha256),
             "bls12381shake256" => Ok(KeyPairSubtype::BLS12381G2Shake256),
+            "p256" => Ok(KeyPairSubtype::P256),
             _ => Err(()),
         }
     }
@@ -20,9 +20,8 @@ pub fn check_alg_curve_compatibility(alg: Algorithm, crv: EllipticCurveTypes) ->
     match (alg, crv) {
         // (Algorithm::Signature(SignatureAlgorithm::ES256), EllipticCurveTypes::P256) => true, EXAMPLE
         (Algorithm::Proof(ProofAlgorithm::BBS), EllipticCurveTypes::BLS12381G2) => true,
-        (Algorithm::Proof(ProofAlgorithm::BBS_SHAKE256), EllipticCurveTypes::BLS12381G2) => {
-            true
-        }
+        (Algorithm::Proof(ProofAlgorithm::BBS_SHAKE256), EllipticCurveTypes::BLS12381G2) => true,
+        (Algorithm::Proof(ProofAlgorithm::SU_ES256), EllipticCurveTypes::P256) => true,
         _ => false,
     }
 }
@@ -34,9 +33,8 @@ pub fn check_presentation_alg_curve_compatibility(
     match (alg, crv) {
         // (Algorithm::Signature(SignatureAlgorithm::ES256), EllipticCurveTypes::P256) => true, EXAMPLE
         (PresentationProofAlgorithm::BBS_PROOF, EllipticCurveTypes::BLS12381G2) => true,
-        (PresentationProofAlgorithm::BBS_SHAKE256_PROOF, EllipticCurveTypes::BLS12381G2) => {
-            true
-        }
+        (PresentationProofAlgorithm::BBS_SHAKE256_PROOF, EllipticCurveTypes::BLS12381G2) => true,
+        (PresentationProofAlgorithm::SU_ES256, EllipticCurveTypes::P256) => true,
         _ => false,
     }
 }
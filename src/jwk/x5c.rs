@@ -0,0 +1,66 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Key binding of the `x5c` certificate chain of a JWK (https://www.rfc-editor.org/rfc/rfc7517#section-4.7).
+//!
+//! Only the leaf certificate is parsed, down to its subject public key:
+//!
+//! ```text
+//! Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signature }
+//! tbsCertificate ::= SEQUENCE { [0] version OPTIONAL, serialNumber, signature, issuer, validity, subject,
+//!                               SEQUENCE { algorithm, BIT STRING subjectPublicKey }, ... }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use data_encoding::BASE64;
+
+#[cfg(feature = "std")]
+use crate::errors::CustomError;
+
+#[cfg(feature = "std")]
+use super::pem::{der_element, der_parse, TAG_BIT_STRING, TAG_SEQUENCE};
+
+#[cfg(feature = "std")]
+const TAG_VERSION: u8 = 0xa0;
+
+/// DER of the leaf certificate, the first of the chain (standard base64, not base64url)
+pub(crate) fn leaf_der(x5c: &[String]) -> Option<Vec<u8>> {
+    BASE64.decode(x5c.first()?.as_bytes()).ok()
+}
+
+/// Subject public key of a DER encoded certificate
+#[cfg(feature = "std")]
+pub(crate) fn subject_public_key(certificate: &[u8]) -> Result<&[u8], CustomError> {
+    let invalid = |_| CustomError::InvalidCertificate("malformed DER".into());
+
+    let (certificate, _) = der_parse(TAG_SEQUENCE, certificate).map_err(invalid)?;
+    let (mut tbs_certificate, _) = der_parse(TAG_SEQUENCE, certificate).map_err(invalid)?;
+    if tbs_certificate.first() == Some(&TAG_VERSION) {
+        tbs_certificate = der_element(tbs_certificate).map_err(invalid)?.2;
+    }
+    // serialNumber, signature, issuer, validity, subject
+    for _ in 0..5 {
+        tbs_certificate = der_element(tbs_certificate).map_err(invalid)?.2;
+    }
+    let (public_key_info, _) = der_parse(TAG_SEQUENCE, tbs_certificate).map_err(invalid)?;
+    let (_, public_key_info) = der_parse(TAG_SEQUENCE, public_key_info).map_err(invalid)?;
+    let (public_key, _) = der_parse(TAG_BIT_STRING, public_key_info).map_err(invalid)?;
+    match public_key {
+        // No unused bits
+        [0, public_key @ ..] => Ok(public_key),
+        _ => Err(CustomError::InvalidCertificate(
+            "subject public key is not a whole number of bytes".into(),
+        )),
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Presentations combining the claims of several Issued JWPs, e.g. credentials of different issuers.
+//!
+//! Each credential keeps its own presentation proof: the combined token is the list of the Presented JWPs,
+//! in the order of the Issued JWPs they come from. Its compact serialization joins the compact serializations
+//! of the presentations with [`COMBINED_SEPARATOR`], which is neither a base64url character nor a JWP separator:
+//!
+//! ```text
+//! <presentation 1>,<presentation 2>,...
+//! ```
+//!
+//! All the presentations must carry the same `nonce` and `aud`, so the challenge of the verifier covers all of them.
+//! A verifier checks the presentations in order, each one with the key of its issuer, and rejects the token
+//! at the first failure.
+
+use alloc::{string::String, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "std")]
+use crate::jwk::key::Jwk;
+use crate::{encoding::SerializationType, errors::CustomError};
+
+use super::{
+    header::PresentationProtectedHeader,
+    issued::JwpIssued,
+    presented::{JwpPresented, JwpPresentedBuilder, JwpPresentedDecoder},
+};
+
+/// Separator of the presentations in the compact serialization of a combined presentation
+pub const COMBINED_SEPARATOR: &str = ",";
+
+/// Used to build a presentation of several Issued JWPs, see [`JwpPresentedBuilder::combine`]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JwpCombinedPresentedBuilder {
+    builders: Vec<JwpPresentedBuilder>,
+}
+
+impl JwpCombinedPresentedBuilder {
+    /// One [`JwpPresentedBuilder`] per Issued JWP, with a presentation protected header
+    /// made by [`PresentationProtectedHeader::from_issued`]
+    pub fn new(issued: &[JwpIssued]) -> Self {
+        Self {
+            builders: issued
+                .iter()
+                .map(|issued_jwp| {
+                    let mut builder = JwpPresentedBuilder::new(issued_jwp);
+                    builder.set_presentation_protected_header(
+                        PresentationProtectedHeader::from_issued(issued_jwp),
+                    );
+                    builder
+                })
+                .collect(),
+        }
+    }
+
+    /// Set the `nonce` of all the presentations
+    pub fn with_nonce(&mut self, nonce: &str) -> Result<&mut Self, CustomError> {
+        for builder in &mut self.builders {
+            builder.with_nonce(nonce)?;
+        }
+        Ok(self)
+    }
+
+    /// Set the `aud` of all the presentations
+    pub fn with_aud(&mut self, aud: &str) -> Result<&mut Self, CustomError> {
+        for builder in &mut self.builders {
+            builder.with_aud(aud)?;
+        }
+        Ok(self)
+    }
+
+    /// Builder of the presentation of the `index`-th Issued JWP, e.g. to choose the claims to disclose
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut JwpPresentedBuilder> {
+        self.builders.get_mut(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.builders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.builders.is_empty()
+    }
+
+    /// Build all the presentations, the `index`-th one with the `index`-th key
+    #[cfg(feature = "std")]
+    pub fn build(&self, keys: &[&Jwk]) -> Result<JwpCombinedPresented, CustomError> {
+        check_key_count(self.builders.len(), keys.len())?;
+        let presentations = self
+            .builders
+            .iter()
+            .zip(keys)
+            .map(|(builder, key)| builder.build(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(JwpCombinedPresented { presentations })
+    }
+}
+
+/// Decoder of a combined presentation
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JwpCombinedPresentedDecoder {
+    decoders: Vec<JwpPresentedDecoder>,
+}
+
+impl JwpCombinedPresentedDecoder {
+    pub fn decode(jpt: &str, serialization: SerializationType) -> Result<Self, CustomError> {
+        match serialization {
+            SerializationType::COMPACT => Ok(Self {
+                decoders: jpt
+                    .split(COMBINED_SEPARATOR)
+                    .map(|presentation| JwpPresentedDecoder::decode(presentation, serialization))
+                    .collect::<Result<_, _>>()?,
+            }),
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
+        }
+    }
+
+    /// Verify the presentations in order, the `index`-th one with the `index`-th key.
+    ///
+    /// Errors with [`CustomError::KeyCountMismatch`] if there is not one key per presentation and with
+    /// [`CustomError::InvalidPresentedJwp`] if the presentations do not share the same `nonce` and `aud`,
+    /// before checking any proof. Otherwise it returns the error of the first presentation that fails.
+    #[cfg(feature = "std")]
+    pub fn verify(&self, keys: &[&Jwk]) -> Result<JwpCombinedPresented, CustomError> {
+        check_key_count(self.decoders.len(), keys.len())?;
+        let headers: Vec<_> = self
+            .decoders
+            .iter()
+            .map(JwpPresentedDecoder::get_presentation_header)
+            .collect();
+        if headers
+            .windows(2)
+            .any(|pair| pair[0].nonce() != pair[1].nonce() || pair[0].aud() != pair[1].aud())
+        {
+            return Err(CustomError::InvalidPresentedJwp);
+        }
+
+        let presentations = self
+            .decoders
+            .iter()
+            .zip(keys)
+            .map(|(decoder, key)| decoder.verify(key))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(JwpCombinedPresented { presentations })
+    }
+
+    pub fn get_decoders(&self) -> &[JwpPresentedDecoder] {
+        &self.decoders
+    }
+}
+
+/// Presentation of several Issued JWPs, one Presented JWP each
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JwpCombinedPresented {
+    presentations: Vec<JwpPresented>,
+}
+
+impl JwpCombinedPresented {
+    pub fn encode(&self, serialization: SerializationType) -> Result<String, CustomError> {
+        match serialization {
+            SerializationType::COMPACT => Ok(self
+                .presentations
+                .iter()
+                .map(|presentation| presentation.encode(serialization))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(COMBINED_SEPARATOR)),
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
+        }
+    }
+
+    pub fn get_presentations(&self) -> &[JwpPresented] {
+        &self.presentations
+    }
+}
+
+#[cfg(feature = "std")]
+fn check_key_count(expected: usize, got: usize) -> Result<(), CustomError> {
+    if expected != got {
+        return Err(CustomError::KeyCountMismatch { expected, got });
+    }
+    Ok(())
+}
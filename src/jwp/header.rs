@@ -12,13 +12,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use serde::{Deserialize, Serialize};
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{cell::RefCell, fmt};
+use serde::{
+    de::{self, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::{Map, Value};
+
+use sha2::{Digest, Sha256};
 
 use crate::{
+    encoding::{base64url_decode, base64url_encode, sort_keys},
+    errors::CustomError,
     jpa::algs::{PresentationProofAlgorithm, ProofAlgorithm},
-    jpt::claims::Claims,
+    jpt::claims::{Claims, ClaimsResolver},
+    jwk::key::Jwk,
 };
 
+use super::issued::JwpIssued;
+
+/// Protected header of the issuer, signed together with the payloads.
+///
+/// It is always disclosed in full: its octets are the header input of the proof, which the verifier needs to check
+/// it, so a presentation cannot withhold any of its members. To keep the `kid` from correlating presentations, the
+/// issuer can leave it out and the holder can reference the key with [`PresentationProtectedHeader::set_blinded_kid`].
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct IssuerProtectedHeader {
     /// JWP type (JPT)
@@ -26,15 +49,34 @@ pub struct IssuerProtectedHeader {
     typ: Option<String>,
     /// Algorithm used for the JWP
     alg: ProofAlgorithm,
+    /// `api_id` of the BBS ciphersuite. Optional, as it is implied by `alg`, but if set it must be the one of `alg`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_id: Option<String>,
     /// ID for the key used for the JWP.
     #[serde(skip_serializing_if = "Option::is_none")]
     kid: Option<String>,
-    /// cid can be used similar to a cid in order to ensure externally resolve of claims. FOr now this is not handled!
+    /// Issuer of the JWP (see [`crate::jwp::issued::JwpIssued::issuer`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    iss: Option<String>,
+    /// ID of an externally resolved set of claims, used instead of `claims` (see [`crate::jpt::claims::ClaimsResolver`])
     #[serde(skip_serializing_if = "Option::is_none")]
     cid: Option<String>,
     /// if you want you can put the claims directly into the header
     #[serde(skip_serializing_if = "Option::is_none")]
     claims: Option<Claims>,
+    /// When true every payload is a `[salt, value]` pair (see [`crate::jpt::payloads::Payloads::unsalted`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    salted: Option<bool>,
+    /// Issuer public key, for self-contained tokens (see [`crate::jwp::issued::JwpIssuedDecoder::verify_self_contained`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    proof_key: Option<Jwk>,
+    /// Public key of the holder the issuer binds the JWP to, covered by the issuer proof
+    /// (see [`crate::jwp::presented::JwpPresentedDecoder::verify_holder_binding`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presentation_key: Option<Jwk>,
+    /// Members not defined by this type, kept so that a decoded header re-serializes to the members it was signed with
+    #[serde(flatten)]
+    extra: Map<String, Value>,
 }
 
 impl IssuerProtectedHeader {
@@ -42,9 +84,15 @@ impl IssuerProtectedHeader {
         Self {
             typ: Some("JPT".to_owned()),
             alg,
+            api_id: None,
             kid: None,
+            iss: None,
             cid: None,
             claims: None,
+            salted: None,
+            proof_key: None,
+            presentation_key: None,
+            extra: Map::new(),
         }
     }
 
@@ -53,6 +101,30 @@ impl IssuerProtectedHeader {
         self.alg
     }
 
+    // Getter for api_id
+    pub fn api_id(&self) -> Option<&String> {
+        self.api_id.as_ref()
+    }
+
+    // Setter for api_id
+    pub fn set_api_id(&mut self, value: Option<String>) {
+        self.api_id = value;
+    }
+
+    /// Errors with [`CustomError::CiphersuiteMismatch`] if `api_id` is set and is not the one of the ciphersuite of `alg`.
+    /// Only the ciphersuites implied by the algorithms are supported, so a matching `api_id` changes nothing in the proofs.
+    pub(crate) fn check_api_id(&self) -> Result<(), CustomError> {
+        match &self.api_id {
+            Some(api_id) if self.alg.bbs_api_id() != Some(api_id.as_str()) => {
+                Err(CustomError::CiphersuiteMismatch {
+                    alg: self.alg.to_string(),
+                    api_id: api_id.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
     // Getter for typ
     pub fn typ(&self) -> Option<&String> {
         self.typ.as_ref()
@@ -73,6 +145,16 @@ impl IssuerProtectedHeader {
         self.kid = value;
     }
 
+    // Getter for iss
+    pub fn iss(&self) -> Option<&String> {
+        self.iss.as_ref()
+    }
+
+    // Setter for iss
+    pub fn set_iss(&mut self, value: Option<String>) {
+        self.iss = value;
+    }
+
     // Getter for cid
     pub fn cid(&self) -> Option<&String> {
         self.cid.as_ref()
@@ -92,6 +174,104 @@ impl IssuerProtectedHeader {
     pub(crate) fn set_claims(&mut self, value: Option<Claims>) {
         self.claims = value;
     }
+
+    /// Fill in the claims referenced by `cid` when the header does not carry them
+    pub(crate) fn resolve_claims(
+        &mut self,
+        resolver: Option<&dyn ClaimsResolver>,
+    ) -> Result<(), CustomError> {
+        if self.claims.is_none() {
+            if let Some(cid) = &self.cid {
+                let claims = resolver
+                    .and_then(|r| r.resolve(cid))
+                    .ok_or_else(|| CustomError::UnresolvedClaims(cid.clone()))?;
+                self.claims = Some(claims);
+            }
+        }
+        Ok(())
+    }
+
+    /// Canonical bytes of the header used as input of the issuer proof.
+    ///
+    /// Unlike the serialization used for encoding, these bytes do not depend on how serde orders the fields:
+    /// the fields are written in a fixed order, without whitespace, and keys of nested objects are sorted.
+    /// Fields order: `typ`, `alg`, `api_id`, `kid`, `iss`, `cid`, `claims`, `salted`, `proof_key`, `presentation_key`,
+    /// then the [`IssuerProtectedHeader::extra`] members in decoding order.
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>, CustomError> {
+        canonical_bytes(
+            [
+                ("typ", to_value(&self.typ)?),
+                ("alg", to_value(&self.alg)?),
+                ("api_id", to_value(&self.api_id)?),
+                ("kid", to_value(&self.kid)?),
+                ("iss", to_value(&self.iss)?),
+                ("cid", to_value(&self.cid)?),
+                ("claims", to_value(&self.claims)?),
+                ("salted", to_value(&self.salted)?),
+                ("proof_key", to_value(&self.proof_key)?),
+                ("presentation_key", to_value(&self.presentation_key)?),
+            ],
+            &self.extra,
+        )
+    }
+
+    /// Deserialize the header for closed-world profiles: parameters other than the ones of this type are rejected
+    /// with [`CustomError::UnknownHeaderParameter`], unless listed in the `crit` parameter of the header.
+    /// The default deserialization accepts them instead. Either way `crit` and the other parameters not defined by
+    /// this type are kept in [`IssuerProtectedHeader::extra`], so the header still verifies.
+    pub fn from_slice_strict(header: &[u8]) -> Result<Self, CustomError> {
+        from_slice_strict(
+            header,
+            &[
+                "typ",
+                "alg",
+                "api_id",
+                "kid",
+                "iss",
+                "cid",
+                "claims",
+                "salted",
+                "proof_key",
+                "presentation_key",
+            ],
+        )
+    }
+
+    // Getter for salted
+    pub fn salted(&self) -> bool {
+        self.salted.unwrap_or(false)
+    }
+
+    // Setter for salted
+    #[cfg(feature = "std")]
+    pub(crate) fn set_salted(&mut self, value: Option<bool>) {
+        self.salted = value;
+    }
+
+    // Getter for proof_key
+    pub fn proof_key(&self) -> Option<&Jwk> {
+        self.proof_key.as_ref()
+    }
+
+    // Setter for proof_key
+    pub fn set_proof_key(&mut self, value: Option<Jwk>) {
+        self.proof_key = value;
+    }
+
+    // Getter for presentation_key
+    pub fn presentation_key(&self) -> Option<&Jwk> {
+        self.presentation_key.as_ref()
+    }
+
+    // Setter for presentation_key
+    pub fn set_presentation_key(&mut self, value: Option<Jwk>) {
+        self.presentation_key = value;
+    }
+
+    /// Members of the decoded header not defined by this type, e.g. `crit` and the parameters it lists
+    pub fn extra(&self) -> &Map<String, Value> {
+        &self.extra
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
@@ -100,12 +280,30 @@ pub struct PresentationProtectedHeader {
     /// ID for the key used for the JWP.
     #[serde(skip_serializing_if = "Option::is_none")]
     kid: Option<String>,
+    /// Salted hash of the issuer `kid`, in place of the `kid` itself (see [`PresentationProtectedHeader::set_blinded_kid`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blinded_kid: Option<BlindedKid>,
     /// Who have to receive the JPT.
     #[serde(skip_serializing_if = "Option::is_none")]
     aud: Option<String>,
     /// For replay attacks
     #[serde(skip_serializing_if = "Option::is_none")]
     nonce: Option<String>,
+    /// Holder public key, provides proof of possession and integrity protection of the presentation protected header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presentation_key: Option<Jwk>,
+    /// Signature of the holder over the presentation protected header (without this parameter), base64url encoded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    holder_signature: Option<String>,
+    /// Predicates proven over claims in place of their payloads, by claim name. The constants reveal the values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    predicates: Option<BTreeMap<String, Predicate>>,
+    /// Pairs of claims whose payloads are not included in the presentation, proven to have the same value
+    #[serde(skip_serializing_if = "Option::is_none")]
+    equalities: Option<Vec<(String, String)>>,
+    /// Members not defined by this type, kept so that a decoded header re-serializes to the members it was signed with
+    #[serde(flatten)]
+    extra: Map<String, Value>,
 }
 
 impl PresentationProtectedHeader {
@@ -113,11 +311,26 @@ impl PresentationProtectedHeader {
         Self {
             alg,
             kid: None,
+            blinded_kid: None,
             aud: None,
             nonce: None,
+            presentation_key: None,
+            holder_signature: None,
+            predicates: None,
+            equalities: None,
+            extra: Map::new(),
         }
     }
 
+    /// Presentation header for `issued`, with the presentation algorithm matching the issuer one
+    /// and the `kid` of the issuer protected header. Only `aud` and `nonce` are left to set.
+    pub fn from_issued(issued: &JwpIssued) -> Self {
+        let issuer_protected_header = issued.get_issuer_protected_header();
+        let mut header = Self::new(issuer_protected_header.alg().into());
+        header.set_kid(issuer_protected_header.kid().cloned());
+        header
+    }
+
     // Getter for alg
     pub fn alg(&self) -> PresentationProofAlgorithm {
         self.alg
@@ -133,6 +346,31 @@ impl PresentationProtectedHeader {
         self.kid = value;
     }
 
+    // Getter for blinded_kid
+    pub fn blinded_kid(&self) -> Option<&BlindedKid> {
+        self.blinded_kid.as_ref()
+    }
+
+    /// Reference the issuer key with a salted hash of its `kid`, so that the same `kid` does not correlate
+    /// presentations. Use a fresh random `salt` for every presentation; the verifier, who knows the candidate
+    /// keys, finds the issuer one with [`PresentationProtectedHeader::matches_blinded_kid`].
+    ///
+    /// The issuer protected header is always disclosed, so this is only useful if the issuer left the `kid` out of it.
+    pub fn set_blinded_kid(&mut self, kid: &str, salt: &[u8]) {
+        self.blinded_kid = Some(BlindedKid {
+            salt: base64url_encode(salt),
+            digest: BlindedKid::digest(salt, kid),
+        });
+    }
+
+    /// Whether the blinded kid of the header is the one of `candidate_kid`
+    pub fn matches_blinded_kid(&self, candidate_kid: &str) -> bool {
+        self.blinded_kid.as_ref().is_some_and(|blinded_kid| {
+            base64url_decode(&blinded_kid.salt)
+                .is_ok_and(|salt| BlindedKid::digest(&salt, candidate_kid) == blinded_kid.digest)
+        })
+    }
+
     // Getter for aud
     pub fn aud(&self) -> Option<&String> {
         self.aud.as_ref()
@@ -152,4 +390,333 @@ impl PresentationProtectedHeader {
     pub fn set_nonce(&mut self, value: Option<String>) {
         self.nonce = value;
     }
+
+    // Getter for presentation_key
+    pub fn presentation_key(&self) -> Option<&Jwk> {
+        self.presentation_key.as_ref()
+    }
+
+    // Setter for presentation_key
+    pub fn set_presentation_key(&mut self, value: Option<Jwk>) {
+        self.presentation_key = value;
+    }
+
+    // Getter for holder_signature
+    pub fn holder_signature(&self) -> Option<&String> {
+        self.holder_signature.as_ref()
+    }
+
+    // Setter for holder_signature
+    pub fn set_holder_signature(&mut self, value: Option<String>) {
+        self.holder_signature = value;
+    }
+
+    // Getter for predicates
+    pub fn predicates(&self) -> Option<&BTreeMap<String, Predicate>> {
+        self.predicates.as_ref()
+    }
+
+    // Setter for predicates
+    pub(crate) fn set_predicates(&mut self, value: Option<BTreeMap<String, Predicate>>) {
+        self.predicates = value;
+    }
+
+    // Getter for equalities
+    pub fn equalities(&self) -> Option<&Vec<(String, String)>> {
+        self.equalities.as_ref()
+    }
+
+    // Setter for equalities
+    pub(crate) fn set_equalities(&mut self, value: Option<Vec<(String, String)>>) {
+        self.equalities = value;
+    }
+
+    /// Members of the decoded header not defined by this type, e.g. `crit` and the parameters it lists
+    pub fn extra(&self) -> &Map<String, Value> {
+        &self.extra
+    }
+
+    /// Same as [`IssuerProtectedHeader::from_slice_strict`], for the presentation protected header
+    pub fn from_slice_strict(header: &[u8]) -> Result<Self, CustomError> {
+        from_slice_strict(
+            header,
+            &[
+                "alg",
+                "kid",
+                "blinded_kid",
+                "aud",
+                "nonce",
+                "presentation_key",
+                "holder_signature",
+                "predicates",
+                "equalities",
+            ],
+        )
+    }
+
+    /// Bytes signed by the holder: the canonical header without `holder_signature`
+    pub fn holder_signing_input(&self) -> Result<Vec<u8>, CustomError> {
+        let mut header = self.clone();
+        header.holder_signature = None;
+        header.canonical_bytes()
+    }
+
+    /// Canonical bytes of the header used as input of the presentation proof, see [`IssuerProtectedHeader::canonical_bytes`].
+    ///
+    /// Fields order: `alg`, `kid`, `blinded_kid`, `aud`, `nonce`, `presentation_key`, `holder_signature`, `predicates`,
+    /// `equalities`, then the [`PresentationProtectedHeader::extra`] members in decoding order.
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>, CustomError> {
+        canonical_bytes(
+            [
+                ("alg", to_value(&self.alg)?),
+                ("kid", to_value(&self.kid)?),
+                ("blinded_kid", to_value(&self.blinded_kid)?),
+                ("aud", to_value(&self.aud)?),
+                ("nonce", to_value(&self.nonce)?),
+                ("presentation_key", to_value(&self.presentation_key)?),
+                ("holder_signature", to_value(&self.holder_signature)?),
+                ("predicates", to_value(&self.predicates)?),
+                ("equalities", to_value(&self.equalities)?),
+            ],
+            &self.extra,
+        )
+    }
+}
+
+/// Salted SHA-256 hash of a `kid`: `digest` is the base64url of `SHA-256(salt || kid)`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BlindedKid {
+    /// Base64url encoded salt
+    salt: String,
+    /// Base64url encoded digest
+    digest: String,
+}
+
+impl BlindedKid {
+    fn digest(salt: &[u8], kid: &str) -> String {
+        base64url_encode(
+            Sha256::new()
+                .chain_update(salt)
+                .chain_update(kid)
+                .finalize(),
+        )
+    }
+}
+
+/// Statement about a claim proven by a presented JWP in place of its payload,
+/// see [`crate::jwp::presented::JwpPresentedBuilder::set_predicate`].
+///
+/// The verifier inserts the constant in place of the payload before checking the BBS proof,
+/// so the proof only verifies if the value signed by the issuer satisfies the predicate.
+/// The constant travels in the presentation protected header: an `eq` predicate **reveals** the claim value
+/// just as disclosing it would, it only moves it from the payloads to the header.
+///
+/// Only equality is supported. Range predicates (e.g. `age >= 18`) need range proofs,
+/// which the underlying BBS implementation does not provide yet.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Predicate {
+    /// The claim value is equal to the constant
+    Eq(Value),
+}
+
+impl Predicate {
+    /// Whether `value` satisfies the predicate. Numbers are compared by value, so `1` equals `1.0`.
+    pub fn holds(&self, value: &Value) -> bool {
+        match self {
+            Predicate::Eq(constant) => json_eq(value, constant),
+        }
+    }
+
+    /// The predicate with its constant replaced by `value`, which satisfies it
+    pub(crate) fn with_value(self, value: &Value) -> Self {
+        match self {
+            Predicate::Eq(_) => Predicate::Eq(value.clone()),
+        }
+    }
+
+    /// Value of the payload revealed to the BBS proof
+    pub(crate) fn revealed_value(&self) -> &Value {
+        match self {
+            Predicate::Eq(constant) => constant,
+        }
+    }
+}
+
+/// Equality of JSON values comparing numbers by value instead of by representation
+fn json_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => match (a.as_u64(), b.as_u64()) {
+                (Some(a), Some(b)) => a == b,
+                _ => a.as_f64() == b.as_f64(),
+            },
+        },
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_eq(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| json_eq(a, b)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Octets of a protected header as signed: `raw` (the bytes it was decoded from) if set, otherwise `canonical`.
+///
+/// Verifying against the decoded bytes, instead of re-serializing the header, keeps JWPs whose header was
+/// produced by another implementation (e.g. with a different member order) valid.
+pub(crate) fn header_octets(
+    raw: &[u8],
+    canonical: impl FnOnce() -> Result<Vec<u8>, CustomError>,
+) -> Result<Vec<u8>, CustomError> {
+    if raw.is_empty() {
+        canonical()
+    } else {
+        Ok(raw.to_vec())
+    }
+}
+
+/// Check that the header re-serialized as `canonical` still carries every member of the decoded `raw` octets.
+///
+/// Member order and whitespace may differ, but a member dropped or changed by the re-serialization (e.g. a
+/// `proof_key` with a member unknown to [`Jwk`]) is reported as [`CustomError::HeaderReserializationMismatch`],
+/// instead of as a failed proof once the header is signed or verified again from its canonical octets.
+/// Top-level members unknown to the header type are kept in its `extra` members and survive.
+/// Empty `raw` octets (a header that was not decoded) have nothing to compare.
+pub(crate) fn check_reserialization(raw: &[u8], canonical: &[u8]) -> Result<(), CustomError> {
+    if raw.is_empty() {
+        return Ok(());
+    }
+    let raw: Map<String, Value> = serde_json::from_slice(raw)?;
+    let canonical: Map<String, Value> = serde_json::from_slice(canonical)?;
+    match raw
+        .iter()
+        .find(|(key, value)| !value.is_null() && canonical.get(*key) != Some(value))
+    {
+        Some((key, _)) => Err(CustomError::HeaderReserializationMismatch(key.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Deserialize a header with only the `known` parameters, and the ones listed in its `crit`
+fn from_slice_strict<T: DeserializeOwned>(header: &[u8], known: &[&str]) -> Result<T, CustomError> {
+    let header: Map<String, Value> = serde_json::from_slice(header)?;
+    let crit: Vec<&str> = header
+        .get("crit")
+        .and_then(Value::as_array)
+        .map(|crit| crit.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    if let Some(unknown) = header.keys().find(|parameter| {
+        *parameter != "crit"
+            && !known.contains(&parameter.as_str())
+            && !crit.contains(&parameter.as_str())
+    }) {
+        return Err(CustomError::UnknownHeaderParameter(unknown.clone()));
+    }
+    serde_json::from_value(Value::Object(header)).map_err(CustomError::from)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, CustomError> {
+    serde_json::to_value(value).map_err(CustomError::from)
+}
+
+/// Compact JSON object with the fields in the given order followed by the `extra` members, skipping unset (`null`)
+/// fields. Keys of nested objects are sorted.
+fn canonical_bytes<const N: usize>(
+    fields: [(&str, Value); N],
+    extra: &Map<String, Value>,
+) -> Result<Vec<u8>, CustomError> {
+    let object: Map<String, Value> = fields
+        .into_iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| (key.to_owned(), sort_keys(value)))
+        .chain(
+            extra
+                .iter()
+                .map(|(key, value)| (key.clone(), sort_keys(value.clone()))),
+        )
+        .collect();
+    serde_json::to_vec(&object).map_err(CustomError::from)
+}
+
+/// Check that no object in the header JSON has duplicated members, which serde_json would silently
+/// resolve by taking the last value
+pub(crate) fn check_duplicate_parameters(header: &[u8]) -> Result<(), CustomError> {
+    let duplicate = RefCell::new(None);
+    let mut deserializer = serde_json::Deserializer::from_slice(header);
+    let result = UniqueKeys(&duplicate)
+        .deserialize(&mut deserializer)
+        .and_then(|_| deserializer.end());
+
+    match (result, duplicate.into_inner()) {
+        (_, Some(key)) => Err(CustomError::DuplicateHeaderParameter(key)),
+        (Err(error), None) => Err(error.into()),
+        (Ok(()), None) => Ok(()),
+    }
+}
+
+/// Visits a JSON value storing the first duplicated object key found
+#[derive(Clone, Copy)]
+struct UniqueKeys<'a>(&'a RefCell<Option<String>>);
+
+impl<'de> DeserializeSeed<'de> for UniqueKeys<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for UniqueKeys<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq.next_element_seed(self)?.is_some() {}
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut keys: Vec<String> = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if keys.contains(&key) {
+                *self.0.borrow_mut() = Some(key);
+                return Err(de::Error::custom("duplicate object member"));
+            }
+            map.next_value_seed(self)?;
+            keys.push(key);
+        }
+        Ok(())
+    }
 }
@@ -12,22 +12,38 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     encoding::{
-        base64url_decode, base64url_encode, base64url_encode_serializable, SerializationType,
+        base64url_decode_lenient, base64url_encode, base64url_encode_serializable,
+        base64url_encoded_len, compress, decompress, Compression, SerializationType,
     },
     errors::CustomError,
-    jpa::{algs::ProofAlgorithm, bbs_plus::BBSplusAlgorithm},
+    jpa::{algs::ProofAlgorithm, proof::IssuerProof},
     jpt::{
-        claims::{Claims, JptClaims},
+        claims::{Claims, ClaimsResolver, JptClaims},
+        flattening::ClaimNode,
         payloads::{PayloadType, Payloads},
     },
+};
+#[cfg(feature = "std")]
+use crate::{
+    jpa::{bbs_plus::BBSplusAlgorithm, single_use::SingleUseAlgorithm},
     jwk::key::Jwk,
 };
 
-use super::header::IssuerProtectedHeader;
+use super::header::{check_duplicate_parameters, header_octets, IssuerProtectedHeader};
+#[cfg(feature = "std")]
+use super::{
+    header::{check_reserialization, PresentationProtectedHeader},
+    presented::{JwpPresented, JwpPresentedBuilder},
+};
 
 /// Takes the result of a rsplit and ensure we only get 3 parts (JwpIssued)
 /// Errors if we don't
@@ -46,19 +62,42 @@ macro_rules! expect_three {
 pub struct JwpIssuedBuilder {
     issuer_protected_header: Option<IssuerProtectedHeader>,
     payloads: Option<Payloads>,
+    /// Reject building a JWP without payloads, see [`JwpIssuedBuilder::require_claims`]
+    #[serde(default)]
+    require_claims: bool,
 }
 
 impl JwpIssuedBuilder {
-    pub fn new(issuer_protected_header: IssuerProtectedHeader, jpt_claims: JptClaims) -> Self {
-        let (claims, payloads) = jpt_claims.get_claims_and_payloads();
+    pub fn new(
+        issuer_protected_header: IssuerProtectedHeader,
+        jpt_claims: JptClaims,
+    ) -> Result<Self, CustomError> {
+        let (claims, payloads) = jpt_claims.get_claims_and_payloads()?;
         //Set claims
         let mut issuer_protected_header = issuer_protected_header;
         issuer_protected_header.set_claims(Some(claims));
 
-        Self {
+        Ok(Self {
             issuer_protected_header: Some(issuer_protected_header),
             payloads: Some(payloads),
-        }
+            require_claims: false,
+        })
+    }
+
+    /// Same as [`JwpIssuedBuilder::new`], with the claims sorted, see [`JptClaims::get_claims_and_payloads_sorted`]
+    pub fn new_sorted(
+        issuer_protected_header: IssuerProtectedHeader,
+        jpt_claims: JptClaims,
+    ) -> Result<Self, CustomError> {
+        let (claims, payloads) = jpt_claims.get_claims_and_payloads_sorted()?;
+        let mut issuer_protected_header = issuer_protected_header;
+        issuer_protected_header.set_claims(Some(claims));
+
+        Ok(Self {
+            issuer_protected_header: Some(issuer_protected_header),
+            payloads: Some(payloads),
+            require_claims: false,
+        })
     }
 
     pub fn get_issuer_protected_header(&self) -> Option<&IssuerProtectedHeader> {
@@ -69,10 +108,160 @@ impl JwpIssuedBuilder {
         self.payloads.as_ref()
     }
 
+    /// Salt every payload with 16 random bytes, so that tokens issued for the same claims do not share
+    /// payload segments. The `salted` issuer header parameter is set; use [`Payloads::unsalted`] to get the values back.
+    #[cfg(feature = "std")]
+    pub fn set_salted(&mut self) -> Result<&mut Self, CustomError> {
+        let payloads = self
+            .payloads
+            .as_ref()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoClaimsAndPayloads,
+            ))?;
+        let salts = (0..payloads.0.len())
+            .map(|_| {
+                let mut salt = [0u8; 16];
+                getrandom::getrandom(&mut salt).map_err(|e| CustomError::ProofGenerationError {
+                    alg: self
+                        .issuer_protected_header
+                        .as_ref()
+                        .map(|header| header.alg().to_string())
+                        .unwrap_or_default(),
+                    message: e.to_string(),
+                    payload_count: payloads.len(),
+                })?;
+                Ok(base64url_encode(salt))
+            })
+            .collect::<Result<Vec<String>, CustomError>>()?;
+        self.payloads = Some(payloads.salted(salts)?);
+        if let Some(issuer_protected_header) = self.issuer_protected_header.as_mut() {
+            issuer_protected_header.set_salted(Some(true));
+        }
+        Ok(self)
+    }
+
+    /// Replace the payload of `claim` before the proof is generated, e.g. with a value derived from the original one.
+    /// The value is not salted, so call it before [`JwpIssuedBuilder::set_salted`].
+    pub fn replace_claim_value(
+        &mut self,
+        claim: &str,
+        value: serde_json::Value,
+    ) -> Result<&mut Self, CustomError> {
+        let index = self
+            .issuer_protected_header
+            .as_ref()
+            .and_then(|h| h.claims())
+            .and_then(|c| c.0.iter().position(|x| x == claim))
+            .ok_or(CustomError::SelectiveDisclosureError)?;
+        self.payloads
+            .as_mut()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoClaimsAndPayloads,
+            ))?
+            .replace_payload_at_index(index, value)?;
+        Ok(self)
+    }
+
+    /// Make building fail with [`IncompleteJwpBuild::NoClaimsAndPayloads`](crate::errors::IncompleteJwpBuild)
+    /// when there are no claims at all, e.g. because no claim of the [`JptClaims`] was set. Such a JWP has a valid
+    /// proof over no messages, but proves nothing.
+    pub fn require_claims(&mut self) -> &mut Self {
+        self.require_claims = true;
+        self
+    }
+
+    /// Reorder the claims, and their payloads, as in `order` before the proof is generated, e.g. to match the message
+    /// indexes expected by another party (BBS binds each message to its index).
+    /// Errors with [`CustomError::ClaimOrderMismatch`] if `order` does not list each claim exactly once.
+    pub fn with_claim_order(&mut self, order: &[&str]) -> Result<&mut Self, CustomError> {
+        let issuer_protected_header =
+            self.issuer_protected_header
+                .as_mut()
+                .ok_or(CustomError::IncompleteJwpBuild(
+                    crate::errors::IncompleteJwpBuild::NoIssuerHeader,
+                ))?;
+        let payloads = self
+            .payloads
+            .as_ref()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoClaimsAndPayloads,
+            ))?;
+        let claims = issuer_protected_header.claims().map_or(&[][..], |c| &c.0);
+        if order.len() != claims.len() || order.len() != payloads.len() {
+            return Err(CustomError::ClaimOrderMismatch);
+        }
+
+        let mut indexes = order
+            .iter()
+            .map(|claim| claims.iter().position(|c| c == claim))
+            .collect::<Option<Vec<usize>>>()
+            .ok_or(CustomError::ClaimOrderMismatch)?;
+        let reordered_payloads = Payloads(indexes.iter().map(|&i| payloads.0[i].clone()).collect());
+        indexes.sort_unstable();
+        indexes.dedup();
+        if indexes.len() != order.len() {
+            return Err(CustomError::ClaimOrderMismatch);
+        }
+
+        issuer_protected_header.set_claims(Some(Claims(
+            order.iter().map(|claim| claim.to_string()).collect(),
+        )));
+        self.payloads = Some(reordered_payloads);
+        Ok(self)
+    }
+
+    /// Check that the builder is complete and that there is one payload for each claim of the issuer
+    /// protected header, otherwise errors with [`CustomError::InvalidIssuedJwp`].
+    /// Also checks the `api_id` of the header, see [`IssuerProtectedHeader::api_id`], and that there are claims
+    /// if [`JwpIssuedBuilder::require_claims`] was set
+    pub fn validate(&self) -> Result<(), CustomError> {
+        let issuer_protected_header =
+            self.issuer_protected_header
+                .as_ref()
+                .ok_or(CustomError::IncompleteJwpBuild(
+                    crate::errors::IncompleteJwpBuild::NoIssuerHeader,
+                ))?;
+        let payloads = self
+            .payloads
+            .as_ref()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoClaimsAndPayloads,
+            ))?;
+        if issuer_protected_header.claims().map_or(0, Claims::len) != payloads.len() {
+            return Err(CustomError::InvalidIssuedJwp);
+        }
+        if self.require_claims && payloads.is_empty() {
+            return Err(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoClaimsAndPayloads,
+            ));
+        }
+        issuer_protected_header.check_api_id()
+    }
+
+    /// Build the JWP with a proof generated elsewhere, e.g. by an external signer (see [`ProofInput`]).
+    /// `proof` is typed with the algorithm of the issuer protected header, so its length must fit it
+    /// ([`CustomError::InvalidProofLength`] otherwise), see [`IssuerProof::new`].
     pub fn build_with_proof(&self, proof: Vec<u8>) -> Result<JwpIssued, CustomError> {
+        self.validate()?;
+        let alg = self
+            .issuer_protected_header
+            .as_ref()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoIssuerHeader,
+            ))?
+            .alg();
+        self.build_with_typed_proof(IssuerProof::new(alg, proof)?)
+    }
+
+    /// Same as [`JwpIssuedBuilder::build_with_proof`], also checking that `proof` was produced by the algorithm
+    /// of the issuer protected header ([`CustomError::ProofAlgorithmMismatch`] otherwise)
+    pub fn build_with_typed_proof(&self, proof: IssuerProof) -> Result<JwpIssued, CustomError> {
+        self.validate()?;
         if let Some(issuer_protected_header) = self.issuer_protected_header.clone() {
             if let Some(payloads) = self.payloads.clone() {
+                proof.check_alg(issuer_protected_header.alg())?;
                 Ok(JwpIssued {
+                    issuer_header_oct: issuer_protected_header.canonical_bytes()?,
                     issuer_protected_header,
                     payloads,
                     proof,
@@ -89,19 +278,71 @@ impl JwpIssuedBuilder {
         }
     }
 
+    /// Inputs of the issuer proof generated by [`JwpIssuedBuilder::build`], without signing: the issuer protected
+    /// header octets (see [`IssuerProtectedHeader::canonical_bytes`]) and the BBS messages (see [`Payloads::to_bytes`]).
+    /// Useful to compare byte-for-byte against other BBS implementations.
+    pub fn signing_input(&self) -> Result<(Vec<u8>, Vec<Vec<u8>>), CustomError> {
+        self.validate()?;
+        let issuer_protected_header =
+            self.issuer_protected_header
+                .as_ref()
+                .ok_or(CustomError::IncompleteJwpBuild(
+                    crate::errors::IncompleteJwpBuild::NoIssuerHeader,
+                ))?;
+        let payloads = self
+            .payloads
+            .as_ref()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoClaimsAndPayloads,
+            ))?;
+        Ok((
+            issuer_protected_header.canonical_bytes()?,
+            payloads.to_bytes()?,
+        ))
+    }
+
+    /// Input of the issuer proof for an external signer (e.g. an HSM), see [`ProofInput`].
+    /// The resulting proof is then passed to [`JwpIssuedBuilder::build_with_proof`].
+    pub fn proof_input(&self) -> Result<ProofInput, CustomError> {
+        let alg = self
+            .issuer_protected_header
+            .as_ref()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoIssuerHeader,
+            ))?
+            .alg();
+        let (header, messages) = self.signing_input()?;
+        Ok(ProofInput {
+            alg,
+            header,
+            messages,
+        })
+    }
+
+    /// Build the JWP generating the proof with `jwk`.
+    ///
+    /// BBS signatures are deterministic: the same key, header and payloads always give the same proof.
+    /// Keys with an `alg` (as the ones from [`Jwk::generate`]) must have the `alg` of the issuer protected header.
+    #[cfg(feature = "std")]
     pub fn build(&self, jwk: &Jwk) -> Result<JwpIssued, CustomError> {
+        self.validate()?;
         if let Some(issuer_protected_header) = self.issuer_protected_header.clone() {
             if let Some(payloads) = self.payloads.clone() {
-                let issuer_header_oct = serde_json::to_vec(&self.issuer_protected_header).unwrap();
-                let proof = Self::generate_proof(
+                jwk.check_proof_alg(issuer_protected_header.alg())?;
+                let issuer_header_oct = issuer_protected_header.canonical_bytes()?;
+                let proof = IssuerProof::new(
                     issuer_protected_header.alg(),
-                    &jwk,
-                    &issuer_header_oct,
-                    &payloads,
+                    Self::generate_proof(
+                        issuer_protected_header.alg(),
+                        jwk,
+                        &issuer_header_oct,
+                        &payloads,
+                    )?,
                 )?;
 
                 Ok(JwpIssued {
                     issuer_protected_header,
+                    issuer_header_oct,
                     payloads,
                     proof,
                 })
@@ -117,6 +358,7 @@ impl JwpIssuedBuilder {
         }
     }
 
+    #[cfg(feature = "std")]
     fn generate_proof(
         alg: ProofAlgorithm,
         key: &Jwk,
@@ -127,99 +369,264 @@ impl JwpIssuedBuilder {
             ProofAlgorithm::BBS | ProofAlgorithm::BBS_SHAKE256 => {
                 BBSplusAlgorithm::generate_issuer_proof(alg, payloads, key, issuer_header_oct)?
             }
-            ProofAlgorithm::SU_ES256 => todo!(),
-            ProofAlgorithm::MAC_H256 => todo!(),
-            ProofAlgorithm::MAC_H384 => todo!(),
-            ProofAlgorithm::MAC_H512 => todo!(),
-            ProofAlgorithm::MAC_K25519 => todo!(),
-            ProofAlgorithm::MAC_K448 => todo!(),
-            ProofAlgorithm::MAC_H256K => todo!(),
+            ProofAlgorithm::SU_ES256 => {
+                SingleUseAlgorithm::generate_issuer_proof(alg, payloads, key, issuer_header_oct)?
+            }
+            ProofAlgorithm::MAC_H256
+            | ProofAlgorithm::MAC_H384
+            | ProofAlgorithm::MAC_H512
+            | ProofAlgorithm::MAC_K25519
+            | ProofAlgorithm::MAC_K448
+            | ProofAlgorithm::MAC_H256K => {
+                return Err(CustomError::UnsupportedAlgorithm(alg.to_string()))
+            }
         };
 
         Ok(proof)
     }
 }
 
+/// Everything an external signer needs to generate the issuer proof of a JWP.
+///
+/// For [`ProofAlgorithm::BBS`] and [`ProofAlgorithm::BBS_SHAKE256`] the proof is the BBS signature
+/// (`Sign(SK, PK, header, messages)`, with the BLS12-381-SHA-256 and BLS12-381-SHAKE-256 ciphersuites respectively)
+/// over `messages`, in this order, with `header` as the BBS header. The 80-byte signature is the proof to pass
+/// to [`JwpIssuedBuilder::build_with_proof`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofInput {
+    /// Algorithm of the issuer protected header
+    pub alg: ProofAlgorithm,
+    /// Octets of the issuer protected header
+    pub header: Vec<u8>,
+    /// One message per payload, see [`Payloads::to_bytes`]
+    pub messages: Vec<Vec<u8>>,
+}
+
 /// Used for both decoding and verifing a JSON Proof Token representing a JWP in the Issuer form
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct JwpIssuedDecoder {
     issuer_protected_header: IssuerProtectedHeader,
+    /// Decoded octets of the issuer protected header, the ones signed by the issuer
+    #[serde(skip)]
+    issuer_header_oct: Vec<u8>,
     payloads: Payloads,
     proof: Vec<u8>,
 }
 
 impl JwpIssuedDecoder {
     /// Decode a JSON Proof Token. The token must represent an Issued JWP, otherwise will return an error.
+    ///
+    /// An issuer protected header with a `cid` but no `claims` fails with [`CustomError::UnresolvedClaims`],
+    /// see [`JwpIssuedDecoder::decode_with_resolver`].
+    /// Segments padded with `=` are accepted, see [`crate::encoding::base64url_decode_lenient`].
     pub fn decode(jpt: &str, serialization: SerializationType) -> Result<Self, CustomError> {
+        Self::decode_resolving(jpt.as_bytes(), serialization, None)
+    }
+
+    /// Same as [`JwpIssuedDecoder::decode`], splitting and decoding the segments directly from bytes
+    /// (e.g. a network buffer) without validating them as UTF-8 first. Non-base64url bytes are rejected
+    /// with [`CustomError::Base64Error`], or with [`CustomError::InvalidIssuedJwp`] when they are in a payload.
+    pub fn decode_bytes(jpt: &[u8], serialization: SerializationType) -> Result<Self, CustomError> {
+        Self::decode_resolving(jpt, serialization, None)
+    }
+
+    /// Decode a JWP compressed with [`JwpIssued::encode_compressed`]
+    pub fn decode_compressed(
+        compressed: &[u8],
+        serialization: SerializationType,
+    ) -> Result<Self, CustomError> {
+        Self::decode_bytes(&decompress(compressed)?, serialization)
+    }
+
+    /// Like [`JwpIssuedDecoder::decode`], taking the claims from `resolver` when the issuer protected header
+    /// carries a `cid` instead of `claims`. The proof is still checked against the header as decoded.
+    pub fn decode_with_resolver(
+        jpt: &str,
+        serialization: SerializationType,
+        resolver: &impl ClaimsResolver,
+    ) -> Result<Self, CustomError> {
+        Self::decode_resolving(jpt.as_bytes(), serialization, Some(resolver))
+    }
+
+    fn decode_resolving(
+        jpt: &[u8],
+        serialization: SerializationType,
+        resolver: Option<&dyn ClaimsResolver>,
+    ) -> Result<Self, CustomError> {
         match serialization {
             SerializationType::COMPACT => {
                 let (encoded_issuer_protected_header, encoded_payloads, encoded_proof) =
-                    expect_three!(jpt.splitn(3, '.'));
-                let issuer_protected_header: IssuerProtectedHeader =
-                    serde_json::from_slice(&base64url_decode(encoded_issuer_protected_header))
-                        .map_err(|_| CustomError::SerializationError)?;
+                    expect_three!(jpt.splitn(3, |b| *b == b'.'));
+                let issuer_header_oct = base64url_decode_lenient(encoded_issuer_protected_header)?;
+                let mut issuer_protected_header: IssuerProtectedHeader =
+                    serde_json::from_slice(&issuer_header_oct)?;
+                issuer_protected_header.resolve_claims(resolver)?;
                 //TODO: this could not have much sense for now (maybe useful to handle blind signatures?)
                 let payloads = Payloads(
                     encoded_payloads
-                        .splitn(issuer_protected_header.claims().unwrap().0.len(), "~")
+                        .splitn(
+                            issuer_protected_header.claims().map_or(0, Claims::len),
+                            |b| *b == b'~',
+                        )
                         .map(|v| {
-                            if v == "" {
-                                (serde_json::Value::Null, PayloadType::Undisclosed)
+                            // Only undisclosed payloads are empty: a disclosed `null` is encoded as `bnVsbA`
+                            if v.is_empty() {
+                                Ok((serde_json::Value::Null, PayloadType::Undisclosed))
                             } else {
-                                (
-                                    serde_json::from_slice(&base64url_decode(v)).unwrap(),
-                                    PayloadType::Disclosed,
-                                )
+                                // The last segment also holds any `~` beyond the expected separators
+                                let value_oct = base64url_decode_lenient(v)
+                                    .map_err(|_| CustomError::InvalidIssuedJwp)?;
+                                Ok((serde_json::from_slice(&value_oct)?, PayloadType::Disclosed))
                             }
                         })
-                        .collect(),
+                        .collect::<Result<_, CustomError>>()?,
                 );
 
                 if !match issuer_protected_header.claims() {
                     Some(claims) => claims.0.len() == payloads.0.len(),
-                    None => payloads.0.len() == 0,
+                    None => payloads.0.is_empty(),
                 } {
                     return Err(CustomError::InvalidIssuedJwp);
                 }
 
-                let proof = base64url_decode(encoded_proof);
+                let proof = base64url_decode_lenient(encoded_proof)?;
                 Ok(Self {
                     issuer_protected_header,
+                    issuer_header_oct,
                     payloads,
-                    proof: proof,
+                    proof,
                 })
             }
-            SerializationType::JSON => todo!(),
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
+        }
+    }
+
+    /// Like [`JwpIssuedDecoder::decode`], but rejects an issuer protected header with duplicated members
+    /// with [`CustomError::DuplicateHeaderParameter`]. Besides the known parameters, this also covers unknown
+    /// parameters and nested objects, where the lenient decoding silently keeps the last value.
+    pub fn decode_strict(jpt: &str, serialization: SerializationType) -> Result<Self, CustomError> {
+        match serialization {
+            SerializationType::COMPACT => {
+                let (encoded_issuer_protected_header, _, _) = expect_three!(jpt.splitn(3, '.'));
+                check_duplicate_parameters(&base64url_decode_lenient(
+                    encoded_issuer_protected_header,
+                )?)?;
+                Self::decode(jpt, serialization)
+            }
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
         }
     }
 
-    /// Verify the decoded JWP
+    /// Verify the decoded JWP.
+    ///
+    /// The proof is checked against the issuer protected header octets as decoded, not re-serialized.
+    /// Still, a decoded header with members that its re-serialization would drop or change is rejected with
+    /// [`CustomError::HeaderReserializationMismatch`].
+    /// A malformed proof errors with [`CustomError::InvalidIssuedJwp`], while a proof that does not verify errors
+    /// with [`CustomError::InvalidIssuedProof`]: either the key is not the one of the issuer or the token was modified.
+    #[cfg(feature = "std")]
     pub fn verify(&self, key: &Jwk) -> Result<JwpIssued, CustomError> {
-        let issuer_header_oct = serde_json::to_vec(&self.issuer_protected_header).unwrap();
+        self.verify_only(key)?;
+
+        Ok(JwpIssued {
+            issuer_protected_header: self.issuer_protected_header.clone(),
+            issuer_header_oct: header_octets(&self.issuer_header_oct, || {
+                self.issuer_protected_header.canonical_bytes()
+            })?,
+            payloads: self.payloads.clone(),
+            proof: IssuerProof::new(self.issuer_protected_header.alg(), self.proof.clone())?,
+        })
+    }
+
+    /// Same checks as [`JwpIssuedDecoder::verify`], without building the verified [`JwpIssued`].
+    /// Useful when only the validity of the proof matters, e.g. to forward the token as it is.
+    #[cfg(feature = "std")]
+    pub fn verify_only(&self, key: &Jwk) -> Result<(), CustomError> {
+        self.issuer_protected_header.check_api_id()?;
+        let canonical = self.issuer_protected_header.canonical_bytes()?;
+        check_reserialization(&self.issuer_header_oct, &canonical)?;
+        let issuer_header_oct = if self.issuer_header_oct.is_empty() {
+            &canonical
+        } else {
+            &self.issuer_header_oct
+        };
 
         Self::verify_proof(
             self.issuer_protected_header.alg(),
             key,
             &self.proof,
-            &issuer_header_oct,
+            issuer_header_oct,
             &self.payloads,
-        )?;
+        )
+    }
 
-        Ok(JwpIssued {
-            issuer_protected_header: self.issuer_protected_header.clone(),
-            payloads: self.payloads.clone(),
-            proof: self.proof.clone(),
-        })
+    /// Like [`JwpIssuedDecoder::verify`], but also requires the `typ` of the issuer protected header to be
+    /// `expected_typ` (e.g. `"vc+jpt"`), compared ignoring ASCII case as media types are.
+    /// Errors with [`CustomError::TypeMismatch`] otherwise, also when `typ` is missing.
+    #[cfg(feature = "std")]
+    pub fn verify_with_typ(&self, key: &Jwk, expected_typ: &str) -> Result<JwpIssued, CustomError> {
+        let typ = self.issuer_protected_header.typ();
+        if !typ.is_some_and(|typ| typ.eq_ignore_ascii_case(expected_typ)) {
+            return Err(CustomError::TypeMismatch {
+                expected: expected_typ.to_owned(),
+                found: typ.cloned(),
+            });
+        }
+        self.verify(key)
+    }
+
+    /// Verify the decoded JWP and start presenting it, see [`JwpPresentedBuilder::new`]
+    #[cfg(feature = "std")]
+    pub fn verify_into_presentation_builder(
+        &self,
+        key: &Jwk,
+    ) -> Result<JwpPresentedBuilder, CustomError> {
+        Ok(JwpPresentedBuilder::new(&self.verify(key)?))
+    }
+
+    /// Verify the decoded JWP with the `proof_key` carried in its issuer protected header.
+    ///
+    /// The key comes from the token itself, so a successful verification only proves that the token was
+    /// signed by whoever holds that key. Meant for trust-on-first-use and development flows: it is not a
+    /// substitute for checking that the key belongs to a trusted issuer.
+    /// Errors with [`CustomError::MissingProofKey`] if the header has no `proof_key`.
+    #[cfg(feature = "std")]
+    pub fn verify_self_contained(&self) -> Result<JwpIssued, CustomError> {
+        let proof_key = self
+            .issuer_protected_header
+            .proof_key()
+            .ok_or(CustomError::MissingProofKey)?;
+        self.verify(proof_key)
     }
 
+    /// Issuer protected header of the decoded JWP. **Unverified**: use it only to select the key (e.g. with `kid`)
     pub fn get_header(&self) -> &IssuerProtectedHeader {
         &self.issuer_protected_header
     }
 
+    /// Claims of the decoded JWP. **Unverified**
+    pub fn get_claims(&self) -> Option<&Claims> {
+        self.issuer_protected_header.claims()
+    }
+
+    /// Payloads of the decoded JWP. **Unverified**
     pub fn get_payloads(&self) -> &Payloads {
         &self.payloads
     }
 
+    /// Proof of the decoded JWP. **Unverified**
+    pub fn get_proof(&self) -> &[u8] {
+        &self.proof
+    }
+
+    /// Split the decoded JWP into header, payloads and proof without verifying it.
+    ///
+    /// None of the returned data is trusted: use [`JwpIssuedDecoder::verify`] to get a [`JwpIssued`].
+    pub fn into_unverified_parts(self) -> (IssuerProtectedHeader, Payloads, Vec<u8>) {
+        (self.issuer_protected_header, self.payloads, self.proof)
+    }
+
+    #[cfg(feature = "std")]
     fn verify_proof(
         alg: ProofAlgorithm,
         key: &Jwk,
@@ -227,48 +634,183 @@ impl JwpIssuedDecoder {
         issuer_header_oct: &[u8],
         payloads: &Payloads,
     ) -> Result<(), CustomError> {
-        let check = match alg {
+        match alg {
             ProofAlgorithm::BBS | ProofAlgorithm::BBS_SHAKE256 => {
-                BBSplusAlgorithm::verify_issuer_proof(alg, &key, proof, issuer_header_oct, payloads)
+                BBSplusAlgorithm::verify_issuer_proof(alg, key, proof, issuer_header_oct, payloads)
             }
-            ProofAlgorithm::SU_ES256 => todo!(),
-            ProofAlgorithm::MAC_H256 => todo!(),
-            ProofAlgorithm::MAC_H384 => todo!(),
-            ProofAlgorithm::MAC_H512 => todo!(),
-            ProofAlgorithm::MAC_K25519 => todo!(),
-            ProofAlgorithm::MAC_K448 => todo!(),
-            ProofAlgorithm::MAC_H256K => todo!(),
-        };
-
-        check
+            ProofAlgorithm::SU_ES256 => SingleUseAlgorithm::verify_issuer_proof(
+                alg,
+                key,
+                proof,
+                issuer_header_oct,
+                payloads,
+            ),
+            ProofAlgorithm::MAC_H256
+            | ProofAlgorithm::MAC_H384
+            | ProofAlgorithm::MAC_H512
+            | ProofAlgorithm::MAC_K25519
+            | ProofAlgorithm::MAC_K448
+            | ProofAlgorithm::MAC_H256K => Err(CustomError::UnsupportedAlgorithm(alg.to_string())),
+        }
     }
 }
 
+/// Sizes in bytes of the payloads of a JWP, see [`JwpIssued::payload_size_report`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PayloadSizeReport {
+    /// Sum of the sizes of all the payloads
+    pub total: usize,
+    /// Size of the payload of each claim, in payload order
+    pub claims: Vec<(String, usize)>,
+}
+
 /// Decoded and verified JSON Web Proof in the Issuer form
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct JwpIssued {
     issuer_protected_header: IssuerProtectedHeader,
+    /// Octets of the issuer protected header signed by the issuer
+    #[serde(skip)]
+    issuer_header_oct: Vec<u8>,
     payloads: Payloads,
-    proof: Vec<u8>,
+    proof: IssuerProof,
 }
 
 impl JwpIssued {
+    /// Value replacing the payloads redacted by [`JwpIssued::redact_for_display`]
+    pub const REDACTED_PLACEHOLDER: &'static str = "[redacted]";
+
     pub fn encode(&self, serialization: SerializationType) -> Result<String, CustomError> {
         // let encoded_issuer_header = base64url_encode_serializable(&self.issuer_protected_header);
 
-        let issuer_header_oct = serde_json::to_vec(&self.issuer_protected_header)
-            .map_err(|_| CustomError::SerializationError)?;
+        let issuer_header_oct = self.issuer_header_oct()?;
 
         let jwp = Self::serialize(
             serialization,
             &issuer_header_oct,
             &self.payloads,
-            &self.proof,
-        );
+            self.proof.bytes(),
+        )?;
 
         Ok(jwp)
     }
 
+    /// Encode the JWP with `serialization` and compress it, e.g. to fit in a QR code.
+    /// The first byte of the result identifies the compression (see [`JwpIssuedDecoder::decode_compressed`]).
+    pub fn encode_compressed(
+        &self,
+        serialization: SerializationType,
+        compression: Compression,
+    ) -> Result<Vec<u8>, CustomError> {
+        Ok(compress(
+            self.encode(serialization)?.as_bytes(),
+            compression,
+        ))
+    }
+
+    /// Length in bytes of the JWP encoded with `serialization`, without building the encoded string
+    pub fn encoded_len(&self, serialization: SerializationType) -> Result<usize, CustomError> {
+        let issuer_header_oct = self.issuer_header_oct()?;
+
+        match serialization {
+            SerializationType::COMPACT => Ok(base64url_encoded_len(issuer_header_oct.len())
+                + 1
+                + compact_payloads_len(&self.payloads)?
+                + 1
+                + base64url_encoded_len(self.proof.bytes().len())),
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
+        }
+    }
+
+    /// Storage format of the JWP, distinct from the wire [`SerializationType`]s: a version byte followed by the
+    /// issuer protected header (JSON), the payloads (JSON) and the proof, each prefixed by its length
+    /// as a big-endian `u32`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CustomError> {
+        let header = self.issuer_header_oct()?;
+        let payloads = serde_json::to_vec(&self.payloads)?;
+
+        let proof = self.proof.bytes();
+
+        let mut bytes = Vec::with_capacity(1 + 12 + header.len() + payloads.len() + proof.len());
+        bytes.push(STORAGE_FORMAT_VERSION);
+        for field in [&header, &payloads, proof] {
+            let len = u32::try_from(field.len()).map_err(|_| {
+                CustomError::SerializationError(format!(
+                    "field of {} bytes does not fit the storage format",
+                    field.len()
+                ))
+            })?;
+            bytes.extend_from_slice(&len.to_be_bytes());
+            bytes.extend_from_slice(field);
+        }
+        Ok(bytes)
+    }
+
+    /// Load a JWP stored with [`JwpIssued::to_bytes`].
+    ///
+    /// The proof is **not** verified again: only load bytes from a storage you trust,
+    /// or decode the wire format with [`JwpIssuedDecoder`] instead.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CustomError> {
+        let rest = match bytes {
+            [STORAGE_FORMAT_VERSION, rest @ ..] => rest,
+            _ => {
+                return Err(CustomError::SerializationError(format!(
+                    "missing or unknown storage format version, expected {STORAGE_FORMAT_VERSION}"
+                )))
+            }
+        };
+        let (header, rest) = split_length_prefixed(rest)?;
+        let (payloads, rest) = split_length_prefixed(rest)?;
+        let (proof, rest) = split_length_prefixed(rest)?;
+        if !rest.is_empty() {
+            return Err(CustomError::SerializationError(format!(
+                "{} trailing bytes after the proof",
+                rest.len()
+            )));
+        }
+
+        let issuer_protected_header: IssuerProtectedHeader = serde_json::from_slice(header)?;
+        Ok(Self {
+            proof: IssuerProof::new(issuer_protected_header.alg(), proof.to_vec())?,
+            issuer_protected_header,
+            issuer_header_oct: header.to_vec(),
+            payloads: serde_json::from_slice(payloads)?,
+        })
+    }
+
+    /// Build a presentation disclosing all the payloads.
+    ///
+    /// `jwk` is the key used to generate the presentation proof, as in [`JwpPresentedBuilder::build`].
+    #[cfg(feature = "std")]
+    pub fn present_all(
+        &self,
+        presentation_header: PresentationProtectedHeader,
+        jwk: &Jwk,
+    ) -> Result<JwpPresented, CustomError> {
+        JwpPresentedBuilder::new(self)
+            .set_presentation_protected_header(presentation_header)
+            .build(jwk)
+    }
+
+    /// Copy of the JWP with the payloads of `claims` replaced by [`JwpIssued::REDACTED_PLACEHOLDER`],
+    /// e.g. to preview a stored credential without showing its sensitive values.
+    ///
+    /// **Display only**: the proof no longer matches the payloads, so the copy cannot be verified nor presented.
+    /// Keep the original JWP to present it. Names that are not claims of the JWP are ignored.
+    pub fn redact_for_display(&self, claims: &[&str]) -> JwpIssued {
+        let mut redacted = self.clone();
+        for (index, _) in self
+            .disclosable_paths()
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| claims.contains(&name.as_str()))
+        {
+            if let Some((value, _)) = redacted.payloads.0.get_mut(index) {
+                *value = Self::REDACTED_PLACEHOLDER.into();
+            }
+        }
+        redacted
+    }
+
     pub fn get_issuer_protected_header(&self) -> &IssuerProtectedHeader {
         &self.issuer_protected_header
     }
@@ -277,8 +819,48 @@ impl JwpIssued {
         self.issuer_protected_header.claims()
     }
 
+    /// Size of the payload of each claim, see [`Payloads::byte_sizes`]
+    pub fn payload_size_report(&self) -> Result<PayloadSizeReport, CustomError> {
+        let claims: Vec<(String, usize)> = self
+            .disclosable_paths()
+            .iter()
+            .cloned()
+            .zip(self.payloads.byte_sizes()?)
+            .collect();
+        Ok(PayloadSizeReport {
+            total: claims.iter().map(|(_, size)| size).sum(),
+            claims,
+        })
+    }
+
+    /// Flattened names of the claims the holder can disclose or hide, in payload order
+    pub fn disclosable_paths(&self) -> &[String] {
+        self.get_claims().map_or(&[], |claims| claims.0.as_slice())
+    }
+
+    /// Same as [`JwpIssued::disclosable_paths`], as a tree following the hierarchy of the original JSON,
+    /// see [`Claims::tree`]
+    pub fn disclosable_tree(&self) -> Result<Vec<ClaimNode>, CustomError> {
+        self.get_claims().map_or(Ok(Vec::new()), Claims::tree)
+    }
+
     pub fn set_claims(&mut self, claims: Claims) {
         self.issuer_protected_header.set_claims(Some(claims));
+        // The header changed, so the signed octets no longer apply
+        self.issuer_header_oct = Vec::new();
+    }
+
+    /// Octets of the issuer protected header signed by the issuer: the decoded ones for a decoded JWP,
+    /// the canonical ones otherwise
+    pub(crate) fn issuer_header_oct(&self) -> Result<Vec<u8>, CustomError> {
+        header_octets(&self.issuer_header_oct, || {
+            self.issuer_protected_header.canonical_bytes()
+        })
+    }
+
+    /// Octets of the issuer protected header as decoded, empty if the JWP was not decoded
+    pub(crate) fn raw_issuer_header_oct(&self) -> &[u8] {
+        &self.issuer_header_oct
     }
 
     pub fn get_payloads(&self) -> &Payloads {
@@ -289,12 +871,75 @@ impl JwpIssued {
         self.payloads = payloads;
     }
 
+    /// Value of the claim `name` (e.g. one of the registered claims `iss`, `sub`, `exp`, `nbf`, `iat`, `jti`),
+    /// without the salt for salted JWPs
+    pub fn registered_claim(&self, name: &str) -> Option<&serde_json::Value> {
+        let index = self
+            .get_claims()?
+            .0
+            .iter()
+            .position(|claim| claim == name)?;
+        match self.payloads.get(index)? {
+            (_, PayloadType::Undisclosed) => None,
+            (serde_json::Value::Array(pair), _) if self.issuer_protected_header.salted() => {
+                pair.get(1)
+            }
+            (value, _) => Some(value),
+        }
+    }
+
+    /// Issuer (`iss`) of the JWP
+    pub fn iss(&self) -> Option<&str> {
+        self.registered_claim("iss")?.as_str()
+    }
+
+    /// Issuer of the JWP: the `iss` of the issuer protected header if present, otherwise the disclosed `iss` claim.
+    ///
+    /// Use [`JwpIssued::verify_issuer_consistency`] to make sure the two do not disagree.
+    pub fn issuer(&self) -> Option<String> {
+        self.issuer_protected_header
+            .iss()
+            .map(String::as_str)
+            .or_else(|| self.iss())
+            .map(String::from)
+    }
+
+    /// Check that the `iss` of the issuer protected header and the `iss` claim are equal when both are present
+    pub fn verify_issuer_consistency(&self) -> Result<(), CustomError> {
+        match (self.issuer_protected_header.iss(), self.iss()) {
+            (Some(header), Some(claim)) if header != claim => Err(CustomError::IssuerMismatch {
+                header: header.clone(),
+                claim: claim.to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Subject (`sub`) of the JWP
+    pub fn sub(&self) -> Option<&str> {
+        self.registered_claim("sub")?.as_str()
+    }
+
+    /// Expiration time (`exp`) of the JWP, as NumericDate
+    pub fn exp(&self) -> Option<i64> {
+        self.registered_claim("exp")?.as_i64()
+    }
+
     pub fn get_proof(&self) -> &[u8] {
-        self.proof.as_ref()
+        self.proof.bytes()
     }
 
-    pub fn set_proof(&mut self, proof: &[u8]) {
-        self.proof = proof.to_vec();
+    /// The proof typed with the algorithm of the issuer protected header
+    pub fn typed_proof(&self) -> &IssuerProof {
+        &self.proof
+    }
+
+    /// Replace the proof. Errors with [`CustomError::ProofAlgorithmMismatch`] if `proof` was not produced by
+    /// the algorithm of the issuer protected header.
+    pub fn set_proof(&mut self, proof: IssuerProof) -> Result<(), CustomError> {
+        proof.check_alg(self.issuer_protected_header.alg())?;
+        self.proof = proof;
+        Ok(())
     }
 
     fn serialize(
@@ -302,7 +947,7 @@ impl JwpIssued {
         issuer_header_oct: &[u8],
         payloads: &Payloads,
         proof: &[u8],
-    ) -> String {
+    ) -> Result<String, CustomError> {
         let encoded_issuer_header = base64url_encode(issuer_header_oct);
         let encoded_proof = base64url_encode(proof);
         let jwp = match serialization {
@@ -311,7 +956,7 @@ impl JwpIssued {
                     .0
                     .iter()
                     .map(|p| {
-                        if p.1 == PayloadType::Undisclosed {
+                        if matches!(p.1, PayloadType::Undisclosed | PayloadType::Predicate) {
                             "".to_string()
                         } else {
                             base64url_encode_serializable(&p.0)
@@ -325,9 +970,46 @@ impl JwpIssued {
                     encoded_issuer_header, encoded_payloads, encoded_proof
                 )
             }
-            SerializationType::JSON => todo!(),
+            SerializationType::JSON => return Err(CustomError::UnsupportedSerialization),
         };
 
-        jwp
+        Ok(jwp)
+    }
+}
+
+/// Version of the storage format of [`JwpIssued::to_bytes`]
+const STORAGE_FORMAT_VERSION: u8 = 1;
+
+/// Split a field prefixed by its length (big-endian `u32`) from the rest of `bytes`
+fn split_length_prefixed(bytes: &[u8]) -> Result<(&[u8], &[u8]), CustomError> {
+    let (len, rest) = match bytes {
+        [a, b, c, d, rest @ ..] => (u32::from_be_bytes([*a, *b, *c, *d]) as usize, rest),
+        _ => {
+            return Err(CustomError::SerializationError(
+                "truncated length prefix".to_string(),
+            ))
+        }
+    };
+    if rest.len() < len {
+        return Err(CustomError::SerializationError(format!(
+            "field of {len} bytes truncated to {}",
+            rest.len()
+        )));
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Length of the payloads in the compact serialization (base64url encoded and separated by `~`)
+pub(super) fn compact_payloads_len(payloads: &Payloads) -> Result<usize, CustomError> {
+    let mut len = payloads.0.len().saturating_sub(1);
+    for (value, payload_type) in &payloads.0 {
+        if !matches!(
+            payload_type,
+            PayloadType::Undisclosed | PayloadType::Predicate
+        ) {
+            let value_oct = serde_json::to_vec(value)?;
+            len += base64url_encoded_len(value_oct.len());
+        }
     }
+    Ok(len)
 }
@@ -12,6 +12,49 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod combined;
 pub mod header;
 pub mod issued;
 pub mod presented;
+
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{encoding::SerializationType, errors::CustomError, jpt::payloads::PayloadType};
+
+use self::{
+    issued::{JwpIssued, JwpIssuedDecoder},
+    presented::{JwpPresented, JwpPresentedDecoder},
+};
+
+/// Fuzzing entry point (e.g. for `cargo fuzz`): decode `data` as a compact Issued and Presented JWP, both lenient
+/// and strict. Returns an error for malformed input instead of panicking.
+pub fn fuzz_decode(data: &[u8]) -> Result<(), CustomError> {
+    let jpt = core::str::from_utf8(data)
+        .map_err(|e| CustomError::SerializationError(format!("JWP is not UTF-8: {e}")))?;
+    let issued = JwpIssuedDecoder::decode(jpt, SerializationType::COMPACT)
+        .and_then(|_| JwpIssuedDecoder::decode_strict(jpt, SerializationType::COMPACT))
+        .map(|_| ());
+    let presented = JwpPresentedDecoder::decode(jpt, SerializationType::COMPACT)
+        .and_then(|_| JwpPresentedDecoder::decode_strict(jpt, SerializationType::COMPACT))
+        .map(|_| ());
+    issued.or(presented)
+}
+
+/// Names of the claims of `issued` that `presented` keeps undisclosed, in payload order.
+/// Claims proven by a predicate are not included, as the constant of the predicate reveals their value.
+/// Errors with [`CustomError::UnrelatedPresentation`] if the two JWPs do not share the issuer protected header.
+pub fn claims_withheld(
+    issued: &JwpIssued,
+    presented: &JwpPresented,
+) -> Result<Vec<String>, CustomError> {
+    if issued.get_issuer_protected_header() != presented.get_issuer_protected_header() {
+        return Err(CustomError::UnrelatedPresentation);
+    }
+    Ok(issued
+        .disclosable_paths()
+        .iter()
+        .zip(presented.get_payloads())
+        .filter(|(_, (_, payload_type))| *payload_type == PayloadType::Undisclosed)
+        .map(|(claim, _)| claim.clone())
+        .collect())
+}
@@ -12,24 +12,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
+#[cfg(feature = "std")]
+use crate::{
+    encoding::base64url_decode,
+    jpa::{
+        algs::{PresentationProofAlgorithm, ProofAlgorithm},
+        bbs_plus::BBSplusAlgorithm,
+        single_use::SingleUseAlgorithm,
+    },
+    jwk::key::Jwk,
+};
 use crate::{
     encoding::{
-        base64url_decode, base64url_encode, base64url_encode_serializable, SerializationType,
+        base64url_decode_lenient, base64url_encode, base64url_encode_serializable,
+        base64url_encoded_len, compress, decompress, Compression, SerializationType,
     },
     errors::CustomError,
-    jpa::{algs::PresentationProofAlgorithm, bbs_plus::BBSplusAlgorithm},
+    jpa::proof::{IssuerProof, PresentationProof},
     jpt::{
-        claims::Claims,
+        claims::{Claims, ClaimsResolver},
+        flattening::FlattenConfig,
         payloads::{PayloadType, Payloads},
     },
-    jwk::key::Jwk,
 };
 
+use super::header::check_reserialization;
 use super::{
-    header::{IssuerProtectedHeader, PresentationProtectedHeader},
-    issued::JwpIssued,
+    combined::JwpCombinedPresentedBuilder,
+    header::{
+        check_duplicate_parameters, header_octets, IssuerProtectedHeader, Predicate,
+        PresentationProtectedHeader,
+    },
+    issued::{compact_payloads_len, JwpIssued},
 };
 
 /// Takes the result of a rsplit and ensure we only get 4 parts (JwpPresented)
@@ -50,21 +73,39 @@ macro_rules! expect_four {
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct JwpPresentedBuilder {
     issuer_protected_header: IssuerProtectedHeader,
+    /// Octets of the issuer protected header signed by the issuer, empty for the canonical ones
+    #[serde(skip)]
+    issuer_header_oct: Vec<u8>,
     presentation_protected_header: Option<PresentationProtectedHeader>,
     payloads: Payloads,
-    issuer_proof: Vec<u8>,
+    issuer_proof: IssuerProof,
+    predicates: BTreeMap<String, Predicate>,
+    equalities: Vec<(String, String)>,
+    #[cfg(feature = "std")]
+    holder_key: Option<Jwk>,
 }
 
 impl JwpPresentedBuilder {
     pub fn new(issued_jwp: &JwpIssued) -> Self {
         Self {
             issuer_protected_header: issued_jwp.get_issuer_protected_header().clone(),
+            issuer_header_oct: issued_jwp.raw_issuer_header_oct().to_vec(),
             presentation_protected_header: None,
             payloads: issued_jwp.get_payloads().clone(),
-            issuer_proof: issued_jwp.get_proof().to_vec(),
+            issuer_proof: issued_jwp.typed_proof().clone(),
+            predicates: BTreeMap::new(),
+            equalities: Vec::new(),
+            #[cfg(feature = "std")]
+            holder_key: None,
         }
     }
 
+    /// Present the claims of several Issued JWPs, e.g. of different issuers, in a single token.
+    /// See [`super::combined`] for its serialization and verification.
+    pub fn combine(issued: &[JwpIssued]) -> JwpCombinedPresentedBuilder {
+        JwpCombinedPresentedBuilder::new(issued)
+    }
+
     pub fn set_presentation_protected_header(
         &mut self,
         header: PresentationProtectedHeader,
@@ -73,6 +114,39 @@ impl JwpPresentedBuilder {
         self
     }
 
+    /// Replace the `nonce` of the presentation protected header already set, e.g. with a fresh challenge
+    /// received right before sending the presentation
+    pub fn with_nonce(&mut self, nonce: &str) -> Result<&mut Self, CustomError> {
+        self.presentation_header_mut()?
+            .set_nonce(Some(nonce.to_owned()));
+        Ok(self)
+    }
+
+    /// Replace the `aud` of the presentation protected header already set
+    pub fn with_aud(&mut self, aud: &str) -> Result<&mut Self, CustomError> {
+        self.presentation_header_mut()?
+            .set_aud(Some(aud.to_owned()));
+        Ok(self)
+    }
+
+    fn presentation_header_mut(&mut self) -> Result<&mut PresentationProtectedHeader, CustomError> {
+        self.presentation_protected_header
+            .as_mut()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoPresentationHeader,
+            ))
+    }
+
+    /// Bind the presentation to the holder: on [`JwpPresentedBuilder::build`] the public part of `jwk` is set as
+    /// `presentation_key` and the presentation protected header is signed with it.
+    /// Required if the issuer bound the JWP to a `presentation_key`, which must then be the key of `jwk`
+    /// ([`CustomError::HolderBindingError`] on build otherwise).
+    #[cfg(feature = "std")]
+    pub fn set_holder_key(&mut self, jwk: Jwk) -> &mut Self {
+        self.holder_key = Some(jwk);
+        self
+    }
+
     // Getter for issuer_protected_header
     pub fn get_issuer_protected_header(&self) -> &IssuerProtectedHeader {
         &self.issuer_protected_header
@@ -89,62 +163,376 @@ impl JwpPresentedBuilder {
     }
 
     // Getter for issuer_proof
-    pub fn issuer_proof(&self) -> &Vec<u8> {
+    pub fn issuer_proof(&self) -> &IssuerProof {
         &self.issuer_proof
     }
 
+    /// Indexes of the disclosed payloads, as passed to the BBS proof generation by [`JwpPresentedBuilder::build`]
+    pub fn disclosed_indexes(&self) -> Vec<usize> {
+        self.payloads.get_disclosed_indexes()
+    }
+
     pub fn set_undisclosed(&mut self, claim: &str) -> Result<&mut Self, CustomError> {
-        let index = self
+        let index = self.claim_index(claim)?;
+        self.payloads.set_undisclosed(index);
+        self.predicates.remove(claim);
+        Ok(self)
+    }
+
+    /// Mark as undisclosed every claim not in `claims`, the inverse of [`JwpPresentedBuilder::set_undisclosed`].
+    /// The claims in the list keep their current disclosure. Errors with [`CustomError::SelectiveDisclosureError`],
+    /// before changing anything, if one of them is not a claim of the issued JWP.
+    pub fn disclose_only(&mut self, claims: &[&str]) -> Result<&mut Self, CustomError> {
+        for claim in claims {
+            self.claim_index(claim)?;
+        }
+        let hidden: Vec<String> = self
             .issuer_protected_header
             .claims()
-            .and_then(|c| c.0.iter().position(|x| x == claim))
-            .ok_or(CustomError::SelectiveDisclosureError)?;
-        self.payloads.set_undisclosed(index);
+            .map(|all| {
+                all.iter()
+                    .filter(|claim| !claims.contains(&claim.as_str()))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        for claim in &hidden {
+            self.set_undisclosed(claim)?;
+        }
         Ok(self)
     }
 
+    /// Mark as undisclosed every claim below `prefix` in the flattened claims, i.e. whose name starts with
+    /// `prefix.` or `prefix[` (so `vc.deg` does not match `vc.degree.name`).
+    /// Returns how many claims were affected, or [`CustomError::SelectiveDisclosureError`] if none matched.
+    pub fn set_undisclosed_subtree(&mut self, prefix: &str) -> Result<usize, CustomError> {
+        self.set_undisclosed_subtree_with_config(prefix, &FlattenConfig::default())
+    }
+
+    /// Same as [`JwpPresentedBuilder::set_undisclosed_subtree`], for claims flattened with a custom
+    /// [`FlattenConfig`].
+    pub fn set_undisclosed_subtree_with_config(
+        &mut self,
+        prefix: &str,
+        config: &FlattenConfig,
+    ) -> Result<usize, CustomError> {
+        let subtree: Vec<String> = self
+            .issuer_protected_header
+            .claims()
+            .map(|claims| {
+                claims
+                    .iter()
+                    .filter(|claim| config.is_path_below(claim, prefix))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        if subtree.is_empty() {
+            return Err(CustomError::SelectiveDisclosureError);
+        }
+        for claim in &subtree {
+            self.set_undisclosed(claim)?;
+        }
+        Ok(subtree.len())
+    }
+
+    /// Prove `predicate` over `claim` in place of its payload.
+    ///
+    /// The predicate is added to the `predicates` parameter of the presentation protected header on build.
+    /// A [`Predicate::Eq`] constant is in that header, so the verifier learns the claim value as if it was disclosed.
+    /// The constant is recorded as the signed value (e.g. `1` for a predicate on `1.0`), since the proof is checked
+    /// against its serialization.
+    /// Errors with [`CustomError::PredicateNotSatisfied`] if the claim value does not satisfy it
+    /// (this is always the case for salted tokens, since the value signed by the issuer includes the salt).
+    pub fn set_predicate(
+        &mut self,
+        claim: &str,
+        predicate: Predicate,
+    ) -> Result<&mut Self, CustomError> {
+        let index = self.claim_index(claim)?;
+        let (value, _) = self
+            .payloads
+            .get(index)
+            .ok_or(CustomError::IndexOutOfBounds)?;
+        if !predicate.holds(value) {
+            return Err(CustomError::PredicateNotSatisfied(claim.to_owned()));
+        }
+        let predicate = predicate.with_value(value);
+        self.payloads.set_predicate(index);
+        self.predicates.insert(claim.to_owned(), predicate);
+        self.equalities.retain(|(a, b)| a != claim && b != claim);
+        Ok(self)
+    }
+
+    /// Prove that `claim_a` and `claim_b` have the same value without including their payloads in the presentation.
+    ///
+    /// Both claims are marked as undisclosed and the pair is added to the `equalities` parameter of the presentation
+    /// protected header on build. Errors with [`CustomError::SelectiveDisclosureError`] if they are not two distinct
+    /// claims of the issued JWP, and with [`CustomError::EqualityNotSatisfied`] if their values differ
+    /// (this is always the case for salted tokens, since the values signed by the issuer include the salts).
+    ///
+    /// The proof is generated by the crate's own BBS ProofGen instead of zkryptium's, see the `bbs-prover` feature.
+    #[cfg(feature = "bbs-prover")]
+    pub fn prove_equal(&mut self, claim_a: &str, claim_b: &str) -> Result<&mut Self, CustomError> {
+        let index_a = self.claim_index(claim_a)?;
+        let index_b = self.claim_index(claim_b)?;
+        if index_a == index_b {
+            return Err(CustomError::SelectiveDisclosureError);
+        }
+        if self.payloads.get(index_a).map(|(value, _)| value)
+            != self.payloads.get(index_b).map(|(value, _)| value)
+        {
+            return Err(CustomError::EqualityNotSatisfied(
+                claim_a.to_owned(),
+                claim_b.to_owned(),
+            ));
+        }
+        self.set_undisclosed(claim_a)?;
+        self.set_undisclosed(claim_b)?;
+        self.equalities
+            .push((claim_a.to_owned(), claim_b.to_owned()));
+        Ok(self)
+    }
+
+    fn claim_index(&self, claim: &str) -> Result<usize, CustomError> {
+        self.issuer_protected_header
+            .claims()
+            .and_then(|c| c.0.iter().position(|x| x == claim))
+            .ok_or(CustomError::SelectiveDisclosureError)
+    }
+
+    /// Errors with [`CustomError::AlgorithmMismatch`] if `header` cannot present the issued JWP
+    fn check_algorithms(&self, header: &PresentationProtectedHeader) -> Result<(), CustomError> {
+        let issuer_alg = self.issuer_protected_header.alg();
+        if header.alg().is_compatible_with(issuer_alg) {
+            Ok(())
+        } else {
+            Err(CustomError::AlgorithmMismatch {
+                issuer: issuer_alg.to_string(),
+                presentation: header.alg().to_string(),
+            })
+        }
+    }
+
+    /// Presentation protected header with the predicates and the equalities set on the builder
+    fn presentation_header_with_predicates(&self) -> Option<PresentationProtectedHeader> {
+        let mut header = self.presentation_protected_header.clone()?;
+        if !self.predicates.is_empty() {
+            header.set_predicates(Some(self.predicates.clone()));
+        }
+        if !self.equalities.is_empty() {
+            header.set_equalities(Some(self.equalities.clone()));
+        }
+        Some(header)
+    }
+
+    /// Build the presentation with a proof generated elsewhere. `proof` is typed with the algorithm of the
+    /// presentation protected header, so its length must fit the payloads left undisclosed
+    /// ([`CustomError::InvalidProofLength`] otherwise), see [`PresentationProof::new`].
     pub fn build_with_proof(&self, proof: Vec<u8>) -> Result<JwpPresented, CustomError> {
-        if let Some(presentation_protected_header) = self.presentation_protected_header.clone() {
+        let alg = self
+            .presentation_protected_header
+            .as_ref()
+            .ok_or(CustomError::IncompleteJwpBuild(
+                crate::errors::IncompleteJwpBuild::NoPresentationHeader,
+            ))?
+            .alg();
+        self.build_with_typed_proof(PresentationProof::new(
+            alg,
+            proof,
+            self.payloads.get_undisclosed_indexes().len(),
+        )?)
+    }
+
+    /// Same as [`JwpPresentedBuilder::build_with_proof`], also checking that `proof` was produced by the algorithm
+    /// of the presentation protected header ([`CustomError::ProofAlgorithmMismatch`] otherwise)
+    pub fn build_with_typed_proof(
+        &self,
+        proof: PresentationProof,
+    ) -> Result<JwpPresented, CustomError> {
+        if let Some(presentation_protected_header) = self.presentation_header_with_predicates() {
+            self.check_algorithms(&presentation_protected_header)?;
+            proof.check_alg(presentation_protected_header.alg())?;
+            let proof = PresentationProof::new(
+                proof.alg(),
+                proof.into_bytes(),
+                self.payloads.get_undisclosed_indexes().len(),
+            )?;
             Ok(JwpPresented {
                 issuer_protected_header: self.issuer_protected_header.clone(),
+                issuer_header_oct: self.issuer_header_oct()?,
+                presentation_header_oct: presentation_protected_header.canonical_bytes()?,
                 presentation_protected_header,
                 payloads: self.payloads.clone(),
                 proof,
             })
         } else {
             Err(CustomError::IncompleteJwpBuild(
-                crate::errors::IncompleteJwpBuild::NoIssuerHeader,
+                crate::errors::IncompleteJwpBuild::NoPresentationHeader,
             ))
         }
     }
 
+    /// Build the presentation generating the proof with `jwk`.
+    ///
+    /// BBS proofs are randomized with the OS RNG, so each call produces a different proof.
+    /// For reproducible output use `build_with_rng` (`rng` feature).
+    #[cfg(feature = "std")]
     pub fn build(&self, jwk: &Jwk) -> Result<JwpPresented, CustomError> {
-        if let Some(presentation_protected_header) = self.presentation_protected_header.clone() {
-            let issuer_header_oct = serde_json::to_vec(&self.issuer_protected_header).unwrap();
-            let presentation_header_oct =
-                serde_json::to_vec(&self.presentation_protected_header).unwrap();
+        self.build_generating(
+            |alg, issuer_header_oct, presentation_header_oct, equal_indexes| {
+                Self::generate_proof(
+                    alg,
+                    jwk,
+                    self.issuer_proof.bytes(),
+                    issuer_header_oct,
+                    presentation_header_oct,
+                    &self.payloads,
+                    equal_indexes,
+                )
+            },
+        )
+    }
 
-            let proof = Self::generate_proof(
-                presentation_protected_header.alg(),
-                jwk,
-                &self.issuer_proof,
-                &issuer_header_oct,
-                &presentation_header_oct,
+    /// Same as [`JwpPresentedBuilder::build`], drawing the randomness of the proof from `rng`:
+    /// two calls with identically seeded generators give byte-identical presentations.
+    ///
+    /// Meant for test vectors and debugging. `rng` must be cryptographically secure, since the randomness
+    /// hides the undisclosed payloads, and a seed must never be reused for real presentations.
+    /// zkryptium does not accept an RNG, so the proof comes from the crate's own ProofGen (`bbs-prover` feature),
+    /// the one of [`JwpPresentedBuilder::prove_equal`], and not from the prover used by [`JwpPresentedBuilder::build`].
+    #[cfg(feature = "rng")]
+    pub fn build_with_rng<R: rand_core::RngCore + rand_core::CryptoRng>(
+        &self,
+        jwk: &Jwk,
+        rng: &mut R,
+    ) -> Result<JwpPresented, CustomError> {
+        self.build_generating(
+            |alg, issuer_header_oct, presentation_header_oct, equal_indexes| {
+                BBSplusAlgorithm::generate_presentation_proof_with_rng(
+                    alg,
+                    self.issuer_proof.bytes(),
+                    &self.payloads,
+                    jwk,
+                    issuer_header_oct,
+                    presentation_header_oct,
+                    equal_indexes,
+                    rng,
+                )
+            },
+        )
+    }
+
+    /// Build the presentation with the proof returned by `generate`, called with the algorithm, the octets of
+    /// the issuer and presentation protected headers and the indexes of the payloads to prove equal
+    #[cfg(feature = "std")]
+    fn build_generating(
+        &self,
+        generate: impl FnOnce(
+            PresentationProofAlgorithm,
+            &[u8],
+            &[u8],
+            &[(usize, usize)],
+        ) -> Result<Vec<u8>, CustomError>,
+    ) -> Result<JwpPresented, CustomError> {
+        if let Some(mut presentation_protected_header) = self.presentation_header_with_predicates()
+        {
+            self.check_algorithms(&presentation_protected_header)?;
+            if let Some(bound_key) = self.issuer_protected_header.presentation_key() {
+                let holder_key = self.holder_key.as_ref();
+                if !holder_key.is_some_and(|holder_key| bound_key.matches_public(holder_key)) {
+                    return Err(CustomError::HolderBindingError);
+                }
+            }
+            if let Some(holder_key) = &self.holder_key {
+                Self::sign_holder_binding(&mut presentation_protected_header, holder_key)?;
+            }
+            let issuer_header_oct = self.issuer_header_oct()?;
+            let presentation_header_oct = presentation_protected_header.canonical_bytes()?;
+            let equal_indexes = equal_indexes(
+                &self.issuer_protected_header,
+                &presentation_protected_header,
                 &self.payloads,
             )?;
+
+            let proof = PresentationProof::new(
+                presentation_protected_header.alg(),
+                generate(
+                    presentation_protected_header.alg(),
+                    &issuer_header_oct,
+                    &presentation_header_oct,
+                    &equal_indexes,
+                )?,
+                self.payloads.get_undisclosed_indexes().len(),
+            )?;
             Ok(JwpPresented {
                 issuer_protected_header: self.issuer_protected_header.clone(),
+                issuer_header_oct,
                 presentation_protected_header,
+                presentation_header_oct,
                 payloads: self.payloads.clone(),
                 proof,
             })
         } else {
             Err(CustomError::IncompleteJwpBuild(
-                crate::errors::IncompleteJwpBuild::NoIssuerHeader,
+                crate::errors::IncompleteJwpBuild::NoPresentationHeader,
             ))
         }
     }
 
+    fn issuer_header_oct(&self) -> Result<Vec<u8>, CustomError> {
+        header_octets(&self.issuer_header_oct, || {
+            self.issuer_protected_header.canonical_bytes()
+        })
+    }
+
+    /// Count of the claims disclosed, hidden and proven by a predicate by the presentation that would be built
+    pub fn disclosure_summary(&self) -> DisclosureSummary {
+        let mut summary = DisclosureSummary::default();
+        for (_, payload_type) in &self.payloads {
+            match payload_type {
+                PayloadType::Undisclosed => summary.undisclosed += 1,
+                PayloadType::Predicate => summary.predicates += 1,
+                _ => summary.disclosed += 1,
+            }
+        }
+        summary
+    }
+
+    /// Like [`JwpPresentedBuilder::build`], but also returns the [`DisclosureSummary`] of the presentation.
+    ///
+    /// With `require_undisclosed`, errors with [`CustomError::OverDisclosure`] if every claim would be disclosed,
+    /// which usually means that [`JwpPresentedBuilder::set_undisclosed`] was not called.
+    #[cfg(feature = "std")]
+    pub fn build_checked(
+        &self,
+        jwk: &Jwk,
+        require_undisclosed: bool,
+    ) -> Result<(JwpPresented, DisclosureSummary), CustomError> {
+        let summary = self.disclosure_summary();
+        if require_undisclosed && summary.disclosed == summary.total() {
+            return Err(CustomError::OverDisclosure);
+        }
+        Ok((self.build(jwk)?, summary))
+    }
+
+    #[cfg(feature = "std")]
+    fn sign_holder_binding(
+        header: &mut PresentationProtectedHeader,
+        holder_key: &Jwk,
+    ) -> Result<(), CustomError> {
+        let alg = holder_binding_algorithm(header.alg())?;
+        header.set_presentation_key(Some(holder_key.to_public().ok_or(CustomError::InvalidJwk)?));
+        let signature = BBSplusAlgorithm::sign_holder_binding(
+            alg,
+            holder_key,
+            &header.holder_signing_input()?,
+        )?;
+        header.set_holder_signature(Some(base64url_encode(&signature)));
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
     fn generate_proof(
         alg: PresentationProofAlgorithm,
         key: &Jwk,
@@ -152,10 +540,13 @@ impl JwpPresentedBuilder {
         issuer_header_oct: &[u8],
         presentation_header_oct: &[u8],
         payloads: &Payloads,
+        equal_indexes: &[(usize, usize)],
     ) -> Result<Vec<u8>, CustomError> {
         let proof = match alg {
             PresentationProofAlgorithm::BBS_PROOF
-            | PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
+            | PresentationProofAlgorithm::BBS_SHAKE256_PROOF
+                if equal_indexes.is_empty() =>
+            {
                 BBSplusAlgorithm::generate_presentation_proof(
                     alg,
                     issuer_proof,
@@ -165,13 +556,42 @@ impl JwpPresentedBuilder {
                     presentation_header_oct,
                 )?
             }
-            PresentationProofAlgorithm::SU_ES256 => todo!(),
-            PresentationProofAlgorithm::MAC_H256 => todo!(),
-            PresentationProofAlgorithm::MAC_H384 => todo!(),
-            PresentationProofAlgorithm::MAC_H512 => todo!(),
-            PresentationProofAlgorithm::MAC_K25519 => todo!(),
-            PresentationProofAlgorithm::MAC_K448 => todo!(),
-            PresentationProofAlgorithm::MAC_H256K => todo!(),
+            #[cfg(feature = "bbs-prover")]
+            PresentationProofAlgorithm::BBS_PROOF
+            | PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
+                BBSplusAlgorithm::generate_presentation_proof_with_equalities(
+                    alg,
+                    issuer_proof,
+                    payloads,
+                    key,
+                    issuer_header_oct,
+                    presentation_header_oct,
+                    equal_indexes,
+                )?
+            }
+            #[cfg(not(feature = "bbs-prover"))]
+            PresentationProofAlgorithm::BBS_PROOF
+            | PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
+                return Err(CustomError::ProofGenerationError {
+                    alg: alg.to_string(),
+                    message: "equality proofs need the `bbs-prover` feature".to_string(),
+                    payload_count: payloads.len(),
+                })
+            }
+            PresentationProofAlgorithm::SU_ES256 => {
+                if !equal_indexes.is_empty() {
+                    return Err(CustomError::UnsupportedAlgorithm(alg.to_string()));
+                }
+                SingleUseAlgorithm::generate_presentation_proof(alg, issuer_proof, payloads)?
+            }
+            PresentationProofAlgorithm::MAC_H256
+            | PresentationProofAlgorithm::MAC_H384
+            | PresentationProofAlgorithm::MAC_H512
+            | PresentationProofAlgorithm::MAC_K25519
+            | PresentationProofAlgorithm::MAC_K448
+            | PresentationProofAlgorithm::MAC_H256K => {
+                return Err(CustomError::UnsupportedAlgorithm(alg.to_string()))
+            }
         };
 
         Ok(proof)
@@ -182,14 +602,57 @@ impl JwpPresentedBuilder {
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct JwpPresentedDecoder {
     issuer_protected_header: IssuerProtectedHeader,
+    /// Decoded octets of the issuer protected header
+    #[serde(skip)]
+    issuer_header_oct: Vec<u8>,
     presentation_protected_header: PresentationProtectedHeader,
+    /// Decoded octets of the presentation protected header
+    #[serde(skip)]
+    presentation_header_oct: Vec<u8>,
     payloads: Payloads,
     proof: Vec<u8>,
 }
 
 impl JwpPresentedDecoder {
     /// Decode a JSON Proof Token. The token must represent a Presented JWP, otherwise will return an error.
+    ///
+    /// An issuer protected header with a `cid` but no `claims` fails with [`CustomError::UnresolvedClaims`],
+    /// see [`JwpPresentedDecoder::decode_with_resolver`].
+    /// Segments padded with `=` are accepted, see [`crate::encoding::base64url_decode_lenient`].
     pub fn decode(jpt: &str, serialization: SerializationType) -> Result<Self, CustomError> {
+        Self::decode_resolving(jpt.as_bytes(), serialization, None)
+    }
+
+    /// Same as [`JwpPresentedDecoder::decode`], splitting and decoding the segments directly from bytes
+    /// (e.g. a network buffer) without validating them as UTF-8 first. Non-base64url bytes are rejected
+    /// with [`CustomError::Base64Error`], or with [`CustomError::InvalidPresentedJwp`] when they are in a payload.
+    pub fn decode_bytes(jpt: &[u8], serialization: SerializationType) -> Result<Self, CustomError> {
+        Self::decode_resolving(jpt, serialization, None)
+    }
+
+    /// Decode a JWP compressed with [`JwpPresented::encode_compressed`]
+    pub fn decode_compressed(
+        compressed: &[u8],
+        serialization: SerializationType,
+    ) -> Result<Self, CustomError> {
+        Self::decode_bytes(&decompress(compressed)?, serialization)
+    }
+
+    /// Like [`JwpPresentedDecoder::decode`], taking the claims from `resolver` when the issuer protected header
+    /// carries a `cid` instead of `claims`. The proof is still checked against the header as decoded.
+    pub fn decode_with_resolver(
+        jpt: &str,
+        serialization: SerializationType,
+        resolver: &impl ClaimsResolver,
+    ) -> Result<Self, CustomError> {
+        Self::decode_resolving(jpt.as_bytes(), serialization, Some(resolver))
+    }
+
+    fn decode_resolving(
+        jpt: &[u8],
+        serialization: SerializationType,
+        resolver: Option<&dyn ClaimsResolver>,
+    ) -> Result<Self, CustomError> {
         match serialization {
             SerializationType::COMPACT => {
                 let (
@@ -197,56 +660,130 @@ impl JwpPresentedDecoder {
                     encoded_presentation_protected_header,
                     encoded_payloads,
                     encoded_proof,
-                ) = expect_four!(jpt.splitn(4, '.'));
+                ) = expect_four!(jpt.splitn(4, |b| *b == b'.'));
+                let presentation_header_oct =
+                    base64url_decode_lenient(encoded_presentation_protected_header)?;
                 let presentation_protected_header: PresentationProtectedHeader =
-                    serde_json::from_slice(&base64url_decode(
-                        encoded_presentation_protected_header,
-                    ))
-                    .map_err(|_| CustomError::SerializationError)?;
-                let issuer_protected_header: IssuerProtectedHeader =
-                    serde_json::from_slice(&base64url_decode(encoded_issuer_protected_header))
-                        .map_err(|_| CustomError::SerializationError)?;
-                let payloads = Payloads(
+                    serde_json::from_slice(&presentation_header_oct)?;
+                let issuer_header_oct = base64url_decode_lenient(encoded_issuer_protected_header)?;
+                let mut issuer_protected_header: IssuerProtectedHeader =
+                    serde_json::from_slice(&issuer_header_oct)?;
+                issuer_protected_header.resolve_claims(resolver)?;
+                let mut payloads = Payloads(
                     encoded_payloads
-                        .splitn(issuer_protected_header.claims().unwrap().0.len(), "~")
+                        .splitn(
+                            issuer_protected_header.claims().map_or(0, Claims::len),
+                            |b| *b == b'~',
+                        )
                         .map(|v| {
-                            if v == "" {
-                                (serde_json::Value::Null, PayloadType::Undisclosed)
+                            // Only undisclosed payloads are empty: a disclosed `null` is encoded as `bnVsbA`
+                            if v.is_empty() {
+                                Ok((serde_json::Value::Null, PayloadType::Undisclosed))
                             } else {
-                                (
-                                    serde_json::from_slice(&base64url_decode(v)).unwrap(),
-                                    PayloadType::Disclosed,
-                                )
+                                // The last segment also holds any `~` beyond the expected separators
+                                let value_oct = base64url_decode_lenient(v)
+                                    .map_err(|_| CustomError::InvalidPresentedJwp)?;
+                                Ok((serde_json::from_slice(&value_oct)?, PayloadType::Disclosed))
                             }
                         })
-                        .collect(),
+                        .collect::<Result<_, CustomError>>()?,
                 );
 
                 if !match issuer_protected_header.claims() {
                     Some(claims) => claims.0.len() == payloads.0.len(),
-                    None => payloads.0.len() == 0,
+                    None => payloads.0.is_empty(),
                 } {
-                    return Err(CustomError::InvalidIssuedJwp);
+                    return Err(CustomError::InvalidPresentedJwp);
+                }
+
+                if let Some(predicates) = presentation_protected_header.predicates() {
+                    Self::apply_predicates(&issuer_protected_header, predicates, &mut payloads)?;
                 }
 
-                let proof = base64url_decode(encoded_proof);
+                let proof = base64url_decode_lenient(encoded_proof)?;
 
                 Ok(Self {
                     issuer_protected_header,
+                    issuer_header_oct,
                     payloads,
-                    proof: proof,
+                    proof,
                     presentation_protected_header,
+                    presentation_header_oct,
                 })
             }
-            SerializationType::JSON => todo!(),
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
+        }
+    }
+
+    /// Put the constants of the predicates in place of the omitted payloads, so that the BBS proof
+    /// is verified against them. The constants are revealed values: the proof only verifies if a constant
+    /// serializes as the value signed by the issuer, so `1.0` does not stand for a signed `1`.
+    fn apply_predicates(
+        issuer_protected_header: &IssuerProtectedHeader,
+        predicates: &BTreeMap<String, Predicate>,
+        payloads: &mut Payloads,
+    ) -> Result<(), CustomError> {
+        let claims = issuer_protected_header
+            .claims()
+            .ok_or(CustomError::InvalidPresentedJwp)?;
+        for (claim, predicate) in predicates {
+            let payload = claims
+                .0
+                .iter()
+                .position(|c| c == claim)
+                .and_then(|index| payloads.0.get_mut(index))
+                .ok_or(CustomError::InvalidPresentedJwp)?;
+            if payload.1 != PayloadType::Undisclosed {
+                return Err(CustomError::InvalidPresentedJwp);
+            }
+            *payload = (predicate.revealed_value().clone(), PayloadType::Predicate);
         }
+        Ok(())
     }
 
-    /// Verify the decoded JWP
+    /// Like [`JwpPresentedDecoder::decode`], but rejects protected headers with duplicated members
+    /// with [`CustomError::DuplicateHeaderParameter`]. Besides the known parameters, this also covers unknown
+    /// parameters and nested objects, where the lenient decoding silently keeps the last value.
+    pub fn decode_strict(jpt: &str, serialization: SerializationType) -> Result<Self, CustomError> {
+        match serialization {
+            SerializationType::COMPACT => {
+                let (encoded_issuer_protected_header, encoded_presentation_protected_header, _, _) =
+                    expect_four!(jpt.splitn(4, '.'));
+                check_duplicate_parameters(&base64url_decode_lenient(
+                    encoded_issuer_protected_header,
+                )?)?;
+                check_duplicate_parameters(&base64url_decode_lenient(
+                    encoded_presentation_protected_header,
+                )?)?;
+                Self::decode(jpt, serialization)
+            }
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
+        }
+    }
+
+    /// Verify the decoded JWP.
+    ///
+    /// `key` is the public key of the issuer: the BBS presentation proof is derived by the holder from the
+    /// issuer signature, so it verifies under the issuer key. The presentation is bound to a holder only if the
+    /// issuer set a `presentation_key` in the issuer protected header, see [`JwpPresentedDecoder::verify_holder_binding`].
+    /// Otherwise `verify` gives **no holder binding**, whatever the presentation protected header carries:
+    /// use [`JwpPresentedDecoder::verify_with_holder_key`] to require a holder key known to the verifier.
+    ///
+    /// The proof is checked against the protected headers octets as decoded, not re-serialized.
+    /// Still, a decoded header with members that its re-serialization would drop or change is rejected with
+    /// [`CustomError::HeaderReserializationMismatch`].
+    /// Before checking the proof, the JWP is checked with [`JwpPresentedDecoder::validate_structure`]: e.g. the number
+    /// of undisclosed payloads must match the number of undisclosed messages the proof commits to, otherwise it errors
+    /// with [`CustomError::InvalidPresentedJwp`].
+    /// Only when the JWP is structurally valid the proof is checked, and its failure is reported as
+    /// [`CustomError::InvalidPresentedProof`]: either the key is not the one of the issuer or the disclosed
+    /// values were modified.
+    #[cfg(feature = "std")]
     pub fn verify(&self, key: &Jwk) -> Result<JwpPresented, CustomError> {
-        let issuer_header_oct = serde_json::to_vec(&self.issuer_protected_header).unwrap();
-        let presentation_header_oct =
-            serde_json::to_vec(&self.presentation_protected_header).unwrap();
+        self.validate_structure()?;
+        self.verify_equalities()?;
+        self.verify_holder_binding()?;
+        let (issuer_header_oct, presentation_header_oct) = self.header_octets()?;
         Self::verify_proof(
             self.presentation_protected_header.alg(),
             key,
@@ -255,14 +792,231 @@ impl JwpPresentedDecoder {
             &issuer_header_oct,
             &self.payloads,
         )?;
+        self.verified(issuer_header_oct, presentation_header_oct)
+    }
+
+    /// Like [`JwpPresentedDecoder::verify`], also requiring the presentation to be bound to `holder_key`, a key the
+    /// verifier knows to be the holder's: the `presentation_key` must be its public part and the holder signature
+    /// must verify under it.
+    /// `issuer_key` is used for the presentation proof only. Errors with [`CustomError::HolderBindingError`] if the
+    /// presentation has no `presentation_key` or it is another key.
+    #[cfg(feature = "std")]
+    pub fn verify_with_holder_key(
+        &self,
+        issuer_key: &Jwk,
+        holder_key: &Jwk,
+    ) -> Result<JwpPresented, CustomError> {
+        let bound_to_holder = self
+            .presentation_protected_header
+            .presentation_key()
+            .is_some_and(|presentation_key| presentation_key.matches_public(holder_key));
+        if !bound_to_holder {
+            return Err(CustomError::HolderBindingError);
+        }
+        self.verify(issuer_key)
+    }
+
+    /// Run every check of [`JwpPresentedDecoder::verify`] that needs neither the key nor the pairing computations,
+    /// e.g. to cheaply reject malformed tokens before fetching the key of the issuer:
+    /// - the algorithms are supported and the presentation one can present the issuer one
+    ///   ([`CustomError::UnsupportedAlgorithm`], [`CustomError::AlgorithmMismatch`]); without the `std` feature
+    ///   no algorithm is implemented, so only their compatibility is checked;
+    /// - the `api_id` is the one of the ciphersuite ([`CustomError::CiphersuiteMismatch`]);
+    /// - there is one payload per claim ([`CustomError::InvalidPresentedJwp`]);
+    /// - the proof length fits the undisclosed payloads ([`CustomError::InvalidPresentedJwp`],
+    ///   [`CustomError::InvalidProofLength`]);
+    /// - a `presentation_key` comes with a `holder_signature` ([`CustomError::HolderBindingError`]);
+    /// - the `equalities` are between undisclosed claims ([`CustomError::InvalidPresentedJwp`]);
+    /// - the headers survive their re-serialization ([`CustomError::HeaderReserializationMismatch`]).
+    ///
+    /// The segments and the headers are already parsed by the decoder.
+    pub fn validate_structure(&self) -> Result<(), CustomError> {
+        let issuer_alg = self.issuer_protected_header.alg();
+        let alg = self.presentation_protected_header.alg();
+        #[cfg(feature = "std")]
+        if !PresentationProofAlgorithm::supported().contains(&alg) {
+            return Err(CustomError::UnsupportedAlgorithm(alg.to_string()));
+        }
+        if !alg.is_compatible_with(issuer_alg) {
+            return Err(CustomError::AlgorithmMismatch {
+                issuer: issuer_alg.to_string(),
+                presentation: alg.to_string(),
+            });
+        }
+        self.issuer_protected_header.check_api_id()?;
+        if self.issuer_protected_header.claims().map_or(0, Claims::len) != self.payloads.len() {
+            return Err(CustomError::InvalidPresentedJwp);
+        }
+        alg.check_proof_len(
+            self.proof.len(),
+            self.payloads.get_undisclosed_indexes().len(),
+        )?;
+        let header = &self.presentation_protected_header;
+        if header.presentation_key().is_some() && header.holder_signature().is_none() {
+            return Err(CustomError::HolderBindingError);
+        }
+        equal_indexes(&self.issuer_protected_header, header, &self.payloads)?;
+        check_reserialization(
+            &self.issuer_header_oct,
+            &self.issuer_protected_header.canonical_bytes()?,
+        )?;
+        check_reserialization(&self.presentation_header_oct, &header.canonical_bytes()?)
+    }
+
+    /// Octets of the issuer and presentation protected headers
+    #[cfg(feature = "std")]
+    fn header_octets(&self) -> Result<(Vec<u8>, Vec<u8>), CustomError> {
+        Ok((
+            header_octets(&self.issuer_header_oct, || {
+                self.issuer_protected_header.canonical_bytes()
+            })?,
+            header_octets(&self.presentation_header_oct, || {
+                self.presentation_protected_header.canonical_bytes()
+            })?,
+        ))
+    }
+
+    #[cfg(feature = "std")]
+    fn verified(
+        &self,
+        issuer_header_oct: Vec<u8>,
+        presentation_header_oct: Vec<u8>,
+    ) -> Result<JwpPresented, CustomError> {
         Ok(JwpPresented {
             issuer_protected_header: self.issuer_protected_header.clone(),
+            issuer_header_oct,
             presentation_protected_header: self.presentation_protected_header.clone(),
+            presentation_header_oct,
             payloads: self.payloads.clone(),
-            proof: self.proof.clone(),
+            proof: PresentationProof::new(
+                self.presentation_protected_header.alg(),
+                self.proof.clone(),
+                self.payloads.get_undisclosed_indexes().len(),
+            )?,
         })
     }
 
+    /// Verify the decoded JWP with the `proof_key` carried in its issuer protected header.
+    ///
+    /// The holder binding is checked against `presentation_key` as in [`JwpPresentedDecoder::verify`].
+    /// Like [`crate::jwp::issued::JwpIssuedDecoder::verify_self_contained`], this is meant for trust-on-first-use
+    /// and development flows and is not a substitute for checking that the key belongs to a trusted issuer.
+    /// Errors with [`CustomError::MissingProofKey`] if the issuer protected header has no `proof_key`.
+    #[cfg(feature = "std")]
+    pub fn verify_self_contained(&self) -> Result<JwpPresented, CustomError> {
+        let proof_key = self
+            .issuer_protected_header
+            .proof_key()
+            .ok_or(CustomError::MissingProofKey)?;
+        self.verify(proof_key)
+    }
+
+    /// Verify many decoded JWPs with the same key.
+    ///
+    /// The key is decoded only once and reused for all the BBS presentations.
+    /// Results are returned in the same order of `items`, so an invalid JWP does not affect the others.
+    /// With the `rayon` feature enabled the JWPs are verified in parallel.
+    #[cfg(feature = "std")]
+    pub fn verify_batch(
+        items: &[JwpPresentedDecoder],
+        key: &Jwk,
+    ) -> Vec<Result<JwpPresented, CustomError>> {
+        let decoded_key = BBSplusAlgorithm::public_key_params(key)
+            .and_then(|params| Ok((params, BBSplusAlgorithm::public_key(params)?)))
+            .ok();
+
+        let verify_item = |item: &JwpPresentedDecoder| match (
+            &decoded_key,
+            item.presentation_protected_header.alg(),
+        ) {
+            (
+                Some((key_params, pk)),
+                alg @ (PresentationProofAlgorithm::BBS_PROOF
+                | PresentationProofAlgorithm::BBS_SHAKE256_PROOF),
+            ) => {
+                BBSplusAlgorithm::check_presentation_compatibility(alg, key_params)?;
+                item.validate_structure()?;
+                item.verify_equalities()?;
+                item.verify_holder_binding()?;
+                let (issuer_header_oct, presentation_header_oct) = item.header_octets()?;
+                BBSplusAlgorithm::verify_presentation_proof_with_public_key(
+                    alg,
+                    pk,
+                    &item.proof,
+                    &presentation_header_oct,
+                    &issuer_header_oct,
+                    &item.payloads,
+                )?;
+                item.verified(issuer_header_oct, presentation_header_oct)
+            }
+            _ => item.verify(key),
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            items.par_iter().map(verify_item).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            items.iter().map(verify_item).collect()
+        }
+    }
+
+    /// Check that the proof binds the claims of each pair in `equalities` to the same value,
+    /// see [`BBSplusAlgorithm::check_equal_responses`]
+    #[cfg(feature = "std")]
+    fn verify_equalities(&self) -> Result<(), CustomError> {
+        let equal_indexes = equal_indexes(
+            &self.issuer_protected_header,
+            &self.presentation_protected_header,
+            &self.payloads,
+        )?;
+        match self.presentation_protected_header.alg() {
+            PresentationProofAlgorithm::BBS_PROOF
+            | PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
+                BBSplusAlgorithm::check_equal_responses(&self.proof, &self.payloads, &equal_indexes)
+            }
+            _ if equal_indexes.is_empty() => Ok(()),
+            alg => Err(CustomError::UnsupportedAlgorithm(alg.to_string())),
+        }
+    }
+
+    /// Verify the holder signature over the presentation protected header.
+    ///
+    /// The holder key is the `presentation_key` of the issuer protected header, covered by the issuer proof:
+    /// when set, the presentation protected header must be signed with it, and its own `presentation_key`, if any,
+    /// must be the same key ([`CustomError::HolderBindingError`] otherwise).
+    /// Without it, the signature is checked against the `presentation_key` of the presentation protected header,
+    /// which proves nothing about the holder: anyone replaying the disclosed JWP can put their own key there and
+    /// sign again. Presentations without any `presentation_key` always pass.
+    #[cfg(feature = "std")]
+    pub fn verify_holder_binding(&self) -> Result<(), CustomError> {
+        let header = &self.presentation_protected_header;
+        let presentation_key = match (
+            self.issuer_protected_header.presentation_key(),
+            header.presentation_key(),
+        ) {
+            (Some(bound_key), Some(presentation_key))
+                if !presentation_key.matches_public(bound_key) =>
+            {
+                return Err(CustomError::HolderBindingError)
+            }
+            (Some(bound_key), _) => bound_key,
+            (None, Some(presentation_key)) => presentation_key,
+            (None, None) => return Ok(()),
+        };
+        let signature = header
+            .holder_signature()
+            .ok_or(CustomError::HolderBindingError)?;
+        BBSplusAlgorithm::verify_holder_binding(
+            holder_binding_algorithm(header.alg())?,
+            presentation_key,
+            &base64url_decode(signature).map_err(|_| CustomError::HolderBindingError)?,
+            &header.holder_signing_input()?,
+        )
+    }
+
     pub fn get_issuer_header(&self) -> &IssuerProtectedHeader {
         &self.issuer_protected_header
     }
@@ -275,6 +1029,7 @@ impl JwpPresentedDecoder {
         &self.payloads
     }
 
+    #[cfg(feature = "std")]
     fn verify_proof(
         alg: PresentationProofAlgorithm,
         key: &Jwk,
@@ -283,28 +1038,98 @@ impl JwpPresentedDecoder {
         issuer_header_oct: &[u8],
         payloads: &Payloads,
     ) -> Result<(), CustomError> {
-        let check = match alg {
+        match alg {
             PresentationProofAlgorithm::BBS_PROOF
             | PresentationProofAlgorithm::BBS_SHAKE256_PROOF => {
                 BBSplusAlgorithm::verify_presentation_proof(
                     alg,
-                    &key,
+                    key,
                     proof,
                     presentation_header_oct,
                     issuer_header_oct,
                     payloads,
                 )
             }
-            PresentationProofAlgorithm::SU_ES256 => todo!(),
-            PresentationProofAlgorithm::MAC_H256 => todo!(),
-            PresentationProofAlgorithm::MAC_H384 => todo!(),
-            PresentationProofAlgorithm::MAC_H512 => todo!(),
-            PresentationProofAlgorithm::MAC_K25519 => todo!(),
-            PresentationProofAlgorithm::MAC_K448 => todo!(),
-            PresentationProofAlgorithm::MAC_H256K => todo!(),
-        };
+            PresentationProofAlgorithm::SU_ES256 => SingleUseAlgorithm::verify_presentation_proof(
+                alg,
+                key,
+                proof,
+                issuer_header_oct,
+                payloads,
+            ),
+            PresentationProofAlgorithm::MAC_H256
+            | PresentationProofAlgorithm::MAC_H384
+            | PresentationProofAlgorithm::MAC_H512
+            | PresentationProofAlgorithm::MAC_K25519
+            | PresentationProofAlgorithm::MAC_K448
+            | PresentationProofAlgorithm::MAC_H256K => {
+                Err(CustomError::UnsupportedAlgorithm(alg.to_string()))
+            }
+        }
+    }
+}
 
-        check
+/// Indexes of the pairs of claims in the `equalities` of `header`. Errors with [`CustomError::InvalidPresentedJwp`]
+/// if a claim is not an undisclosed claim of the issued JWP.
+fn equal_indexes(
+    issuer_protected_header: &IssuerProtectedHeader,
+    header: &PresentationProtectedHeader,
+    payloads: &Payloads,
+) -> Result<Vec<(usize, usize)>, CustomError> {
+    let index = |claim: &String| {
+        issuer_protected_header
+            .claims()
+            .and_then(|claims| claims.0.iter().position(|c| c == claim))
+            .filter(|&index| {
+                payloads
+                    .get(index)
+                    .is_some_and(|(_, payload_type)| *payload_type == PayloadType::Undisclosed)
+            })
+            .ok_or(CustomError::InvalidPresentedJwp)
+    };
+    header
+        .equalities()
+        .into_iter()
+        .flatten()
+        .map(|(a, b)| Ok((index(a)?, index(b)?)))
+        .collect()
+}
+
+/// BBS signature algorithm used by the holder to sign the presentation protected header
+#[cfg(feature = "std")]
+fn holder_binding_algorithm(
+    alg: PresentationProofAlgorithm,
+) -> Result<ProofAlgorithm, CustomError> {
+    match alg {
+        PresentationProofAlgorithm::BBS_PROOF => Ok(ProofAlgorithm::BBS),
+        PresentationProofAlgorithm::BBS_SHAKE256_PROOF => Ok(ProofAlgorithm::BBS_SHAKE256),
+        _ => Err(CustomError::HolderBindingError),
+    }
+}
+
+/// Claims disclosed and withheld by a presented JWP, see [`JwpPresented::disclosure_report`]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DisclosureReport {
+    /// Disclosed claims with their values. Claims proven equal to a constant by a predicate are included with the constant
+    pub disclosed: Map<String, Value>,
+    /// Names of the undisclosed claims
+    pub undisclosed: Vec<String>,
+}
+
+/// Number of claims by disclosure, see [`JwpPresentedBuilder::disclosure_summary`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DisclosureSummary {
+    /// Claims whose payload is included in the presentation
+    pub disclosed: usize,
+    /// Claims whose payload is omitted
+    pub undisclosed: usize,
+    /// Claims whose payload is omitted, but proven by a predicate
+    pub predicates: usize,
+}
+
+impl DisclosureSummary {
+    pub fn total(&self) -> usize {
+        self.disclosed + self.undisclosed + self.predicates
     }
 }
 
@@ -312,32 +1137,82 @@ impl JwpPresentedDecoder {
 #[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub struct JwpPresented {
     issuer_protected_header: IssuerProtectedHeader,
+    /// Octets of the issuer protected header signed by the issuer
+    #[serde(skip)]
+    issuer_header_oct: Vec<u8>,
     presentation_protected_header: PresentationProtectedHeader,
+    /// Octets of the presentation protected header used in the presentation proof
+    #[serde(skip)]
+    presentation_header_oct: Vec<u8>,
     payloads: Payloads,
-    proof: Vec<u8>,
+    proof: PresentationProof,
 }
 
 impl JwpPresented {
     /// Encode the currently crafted JWP
     pub fn encode(&self, serialization: SerializationType) -> Result<String, CustomError> {
-        // let encoded_issuer_header = base64url_encode_serializable(&self.issuer_protected_header);
-        // let encoded_presentation_header = base64url_encode_serializable(&self.presentation_protected_header);
+        let mut jwp = String::new();
+        self.write_encoded(serialization, |segment| {
+            jwp.push_str(segment);
+            Ok(())
+        })?;
+        Ok(jwp)
+    }
 
-        let issuer_header_oct = serde_json::to_vec(&self.issuer_protected_header)
-            .map_err(|_| CustomError::SerializationError)?;
+    /// Encode the JWP with `serialization` and compress it, e.g. to fit in a QR code.
+    /// The first byte of the result identifies the compression (see [`JwpPresentedDecoder::decode_compressed`]).
+    pub fn encode_compressed(
+        &self,
+        serialization: SerializationType,
+        compression: Compression,
+    ) -> Result<Vec<u8>, CustomError> {
+        Ok(compress(
+            self.encode(serialization)?.as_bytes(),
+            compression,
+        ))
+    }
 
-        let presentation_header_oct = serde_json::to_vec(&self.presentation_protected_header)
-            .map_err(|_| CustomError::SerializationError)?;
+    /// Same as [`JwpPresented::encode`], writing the JWP to `w` one segment at a time
+    /// instead of building the whole token in memory
+    #[cfg(feature = "std")]
+    pub fn encode_to<W: std::io::Write>(
+        &self,
+        w: &mut W,
+        serialization: SerializationType,
+    ) -> Result<(), CustomError> {
+        self.write_encoded(serialization, |segment| {
+            w.write_all(segment.as_bytes())
+                .map_err(|e| CustomError::IoError(e.to_string()))
+        })
+    }
 
-        let jwp = Self::serialize(
-            serialization,
-            &presentation_header_oct,
-            &issuer_header_oct,
-            &self.payloads,
-            &self.proof,
-        );
+    /// Length in bytes of the JWP encoded with `serialization`, without building the encoded string
+    pub fn encoded_len(&self, serialization: SerializationType) -> Result<usize, CustomError> {
+        let (issuer_header_oct, presentation_header_oct) = self.header_octets()?;
 
-        Ok(jwp)
+        match serialization {
+            SerializationType::COMPACT => Ok(base64url_encoded_len(issuer_header_oct.len())
+                + 1
+                + base64url_encoded_len(presentation_header_oct.len())
+                + 1
+                + compact_payloads_len(&self.payloads)?
+                + 1
+                + base64url_encoded_len(self.proof.bytes().len())),
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
+        }
+    }
+
+    /// Octets of the issuer and presentation protected headers: the decoded ones for a decoded JWP,
+    /// the canonical ones otherwise
+    fn header_octets(&self) -> Result<(Vec<u8>, Vec<u8>), CustomError> {
+        Ok((
+            header_octets(&self.issuer_header_oct, || {
+                self.issuer_protected_header.canonical_bytes()
+            })?,
+            header_octets(&self.presentation_header_oct, || {
+                self.presentation_protected_header.canonical_bytes()
+            })?,
+        ))
     }
 
     pub fn get_issuer_protected_header(&self) -> &IssuerProtectedHeader {
@@ -357,45 +1232,90 @@ impl JwpPresented {
     }
 
     pub fn get_proof(&self) -> &[u8] {
+        self.proof.bytes()
+    }
+
+    /// The proof typed with the algorithm of the presentation protected header
+    pub fn typed_proof(&self) -> &PresentationProof {
         &self.proof
     }
 
-    fn serialize(
+    /// Whether `other` has the same headers and discloses the same payloads. Unlike `==` this ignores the proof,
+    /// which is randomized for BBS, and the values of undisclosed payloads, which are not in the encoded JWP.
+    pub fn semantically_eq(&self, other: &JwpPresented) -> bool {
+        self.issuer_protected_header == other.issuer_protected_header
+            && self.presentation_protected_header == other.presentation_protected_header
+            && self.payloads.len() == other.payloads.len()
+            && self
+                .payloads
+                .iter()
+                .zip(&other.payloads)
+                .all(|(a, b)| a.1 == b.1 && (a.1 == PayloadType::Undisclosed || a.0 == b.0))
+    }
+
+    /// Which claims the JWP discloses, with their values, and which it withholds.
+    ///
+    /// Built only from the headers and payloads: it does not need the key and does not verify the proof.
+    pub fn disclosure_report(&self) -> DisclosureReport {
+        let mut report = DisclosureReport::default();
+        let claims = self
+            .get_claims()
+            .map(|c| c.0.as_slice())
+            .unwrap_or_default();
+        for (claim, (value, payload_type)) in claims.iter().zip(&self.payloads) {
+            match payload_type {
+                PayloadType::Undisclosed => report.undisclosed.push(claim.clone()),
+                _ => {
+                    report.disclosed.insert(claim.clone(), value.clone());
+                }
+            }
+        }
+        report
+    }
+
+    /// Names of the claims whose values are in the JWP, the only ones that can still be revealed from it.
+    /// Undisclosed claims are left out, as their values are gone, and so are claims proven by a predicate,
+    /// whose values are only in the presentation protected header.
+    pub fn redisclosable_claims(&self) -> Vec<&str> {
+        self.get_claims()
+            .map(|c| c.0.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .zip(&self.payloads)
+            .filter(|(_, (_, payload_type))| *payload_type == PayloadType::Disclosed)
+            .map(|(claim, _)| claim.as_str())
+            .collect()
+    }
+
+    /// Pass the segments of the encoded JWP to `write`, in order
+    fn write_encoded(
+        &self,
         serialization: SerializationType,
-        presentation_header_oct: &[u8],
-        issuer_header_oct: &[u8],
-        payloads: &Payloads,
-        proof: &[u8],
-    ) -> String {
-        let encoded_issuer_header = base64url_encode(issuer_header_oct);
-        let encoded_presentation_header = base64url_encode(presentation_header_oct);
-        let encoded_proof = base64url_encode(proof);
+        mut write: impl FnMut(&str) -> Result<(), CustomError>,
+    ) -> Result<(), CustomError> {
+        let (issuer_header_oct, presentation_header_oct) = self.header_octets()?;
 
-        let jwp = match serialization {
+        match serialization {
             SerializationType::COMPACT => {
-                let encoded_payloads = payloads
-                    .0
-                    .iter()
-                    .map(|p| {
-                        if p.1 == PayloadType::Undisclosed {
-                            "".to_string()
-                        } else {
-                            base64url_encode_serializable(&p.0)
-                        }
-                    })
-                    .collect::<Vec<String>>()
-                    .join("~");
-                format!(
-                    "{}.{}.{}.{}",
-                    encoded_issuer_header,
-                    encoded_presentation_header,
-                    encoded_payloads,
-                    encoded_proof
-                )
+                write(&base64url_encode(issuer_header_oct))?;
+                write(".")?;
+                write(&base64url_encode(presentation_header_oct))?;
+                write(".")?;
+                for (index, (value, payload_type)) in self.payloads.iter().enumerate() {
+                    if index > 0 {
+                        write("~")?;
+                    }
+                    if !matches!(
+                        payload_type,
+                        PayloadType::Undisclosed | PayloadType::Predicate
+                    ) {
+                        write(&base64url_encode_serializable(value))?;
+                    }
+                }
+                write(".")?;
+                write(&base64url_encode(self.proof.bytes()))
             }
-            SerializationType::JSON => todo!(),
-        };
-
-        jwp
+            SerializationType::JSON => Err(CustomError::UnsupportedSerialization),
+        }
     }
 }
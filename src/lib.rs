@@ -12,9 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! Implementation of JSON Web Proof (JWP), JSON Proof Token (JPT) and JSON Proof Algorithms (JPA).
+//!
+//! With the default `std` feature disabled the crate is `no_std` (it still needs `alloc`)
+//! and only the data model is available: headers, claims, payloads, JWKs and the
+//! encoding/decoding of JWPs. Proof generation and verification (`build`, `verify`,
+//! [`jpa::bbs_plus`], [`jwk::key::Jwk::generate`]) require `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod api;
 pub mod encoding;
 pub mod errors;
 pub mod jpa;
 pub mod jpt;
 pub mod jwk;
 pub mod jwp;
+#[cfg(feature = "wasm")]
+pub mod wasm;
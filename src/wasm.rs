@@ -0,0 +1,95 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JavaScript bindings. Headers, claims and keys are passed as JSON strings,
+//! errors are thrown as JS exceptions carrying the [`CustomError`] message.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    encoding::SerializationType,
+    errors::CustomError,
+    jpt::claims::JptClaims,
+    jwk::key::Jwk,
+    jwp::{
+        header::{IssuerProtectedHeader, PresentationProtectedHeader},
+        issued::{JwpIssuedBuilder, JwpIssuedDecoder},
+        presented::{JwpPresentedBuilder, JwpPresentedDecoder},
+    },
+};
+
+fn from_json<T: serde::de::DeserializeOwned>(json: &str) -> Result<T, JsError> {
+    serde_json::from_str(json).map_err(|e| JsError::from(CustomError::from(e)))
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<JsValue, JsError> {
+    serde_json::to_string(value)
+        .map(|json| JsValue::from_str(&json))
+        .map_err(|e| JsError::from(CustomError::from(e)))
+}
+
+/// Issue a JWP signed with the issuer private key and return it in the compact serialization
+#[wasm_bindgen]
+pub fn js_issue(header_json: &str, claims_json: &str, jwk_json: &str) -> Result<String, JsError> {
+    let header: IssuerProtectedHeader = from_json(header_json)?;
+    let claims: JptClaims = from_json(claims_json)?;
+    let jwk: Jwk = from_json(jwk_json)?;
+
+    Ok(JwpIssuedBuilder::new(header, claims)?
+        .build(&jwk)?
+        .encode(SerializationType::COMPACT)?)
+}
+
+/// Decode and verify a compact issued JWP with the issuer public key, returning it as a JSON string
+#[wasm_bindgen]
+pub fn js_decode_verify_issued(token: &str, jwk_json: &str) -> Result<JsValue, JsError> {
+    let jwk: Jwk = from_json(jwk_json)?;
+    let issued_jwp = JwpIssuedDecoder::decode(token, SerializationType::COMPACT)?.verify(&jwk)?;
+
+    to_json(&issued_jwp)
+}
+
+/// Present a compact issued JWP hiding the claims listed in `undisclosed_json` (a JSON array of claim names),
+/// and return the presentation in the compact serialization. `jwk_json` is the issuer public key
+#[wasm_bindgen]
+pub fn js_present(
+    issued_token: &str,
+    presentation_header_json: &str,
+    undisclosed_json: &str,
+    jwk_json: &str,
+) -> Result<String, JsError> {
+    let presentation_header: PresentationProtectedHeader = from_json(presentation_header_json)?;
+    let undisclosed: Vec<String> = from_json(undisclosed_json)?;
+    let jwk: Jwk = from_json(jwk_json)?;
+
+    let issued_jwp =
+        JwpIssuedDecoder::decode(issued_token, SerializationType::COMPACT)?.verify(&jwk)?;
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    builder.set_presentation_protected_header(presentation_header);
+    for claim in &undisclosed {
+        builder.set_undisclosed(claim)?;
+    }
+
+    Ok(builder.build(&jwk)?.encode(SerializationType::COMPACT)?)
+}
+
+/// Decode and verify a compact presented JWP with the issuer public key, returning it as a JSON string
+#[wasm_bindgen]
+pub fn js_decode_verify_presented(token: &str, jwk_json: &str) -> Result<JsValue, JsError> {
+    let jwk: Jwk = from_json(jwk_json)?;
+    let presented_jwp =
+        JwpPresentedDecoder::decode(token, SerializationType::COMPACT)?.verify(&jwk)?;
+
+    to_json(&presented_jwp)
+}
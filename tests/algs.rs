@@ -0,0 +1,204 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "std")]
+
+use jsonprooftoken::{
+    encoding::base64url_decode,
+    errors::CustomError,
+    jpa::{
+        algs::{PresentationProofAlgorithm, ProofAlgorithm},
+        bbs_plus::BBSplusAlgorithm,
+        proof::{IssuerProof, PresentationProof},
+    },
+    jpt::claims::JptClaims,
+    jwk::{alg_parameters::JwkAlgorithmParameters, key::Jwk, types::KeyPairSubtype},
+    jwp::{
+        header::{IssuerProtectedHeader, PresentationProtectedHeader},
+        issued::JwpIssuedBuilder,
+        presented::JwpPresentedBuilder,
+    },
+};
+
+#[test]
+fn supported_algorithms() {
+    assert!(ProofAlgorithm::supported().contains(&ProofAlgorithm::BBS));
+    assert!(!ProofAlgorithm::supported().contains(&ProofAlgorithm::MAC_H256));
+
+    assert!(
+        PresentationProofAlgorithm::supported().contains(&PresentationProofAlgorithm::BBS_PROOF)
+    );
+    assert!(
+        !PresentationProofAlgorithm::supported().contains(&PresentationProofAlgorithm::MAC_H256)
+    );
+}
+
+#[test]
+fn bbs_lengths() {
+    assert_eq!(BBSplusAlgorithm::PUBLIC_KEY_LEN, 96);
+    assert_eq!(BBSplusAlgorithm::SIGNATURE_LEN, 80);
+    assert_eq!(BBSplusAlgorithm::proof_len(0), 272);
+    assert_eq!(BBSplusAlgorithm::proof_len(2), 336);
+
+    let okp_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256)
+        .unwrap()
+        .to_okp_form()
+        .unwrap();
+    let JwkAlgorithmParameters::OctetKeyPair(params) = &okp_jwk.key_params else {
+        panic!("expected an OKP key");
+    };
+    assert_eq!(
+        base64url_decode(&params.x).unwrap().len(),
+        BBSplusAlgorithm::PUBLIC_KEY_LEN
+    );
+    assert_eq!(
+        base64url_decode(params.d.as_ref().unwrap()).unwrap().len(),
+        BBSplusAlgorithm::SECRET_KEY_LEN
+    );
+}
+
+#[test]
+fn bbs_api_ids_match_ciphersuites() {
+    use zkryptium::bbsplus::ciphersuites::{BbsCiphersuite, Bls12381Sha256, Bls12381Shake256};
+
+    assert_eq!(
+        ProofAlgorithm::BBS.bbs_api_id().unwrap().as_bytes(),
+        Bls12381Sha256::API_ID
+    );
+    assert_eq!(
+        ProofAlgorithm::BBS_SHAKE256
+            .bbs_api_id()
+            .unwrap()
+            .as_bytes(),
+        Bls12381Shake256::API_ID
+    );
+    assert_eq!(ProofAlgorithm::SU_ES256.bbs_api_id(), None);
+}
+
+#[test]
+fn typed_proof_lengths() {
+    assert_eq!(
+        ProofAlgorithm::BBS.proof_len(),
+        Some(BBSplusAlgorithm::SIGNATURE_LEN)
+    );
+    assert_eq!(
+        PresentationProofAlgorithm::BBS_SHAKE256_PROOF.proof_len(2),
+        Some(BBSplusAlgorithm::proof_len(2))
+    );
+
+    assert!(IssuerProof::new(ProofAlgorithm::BBS, vec![0; 80]).is_ok());
+    assert!(matches!(
+        IssuerProof::new(ProofAlgorithm::BBS, vec![0; 79]),
+        Err(CustomError::InvalidProofLength {
+            expected: 80,
+            got: 79
+        })
+    ));
+    assert!(matches!(
+        PresentationProof::new(PresentationProofAlgorithm::BBS_PROOF, vec![0; 272], 1),
+        Err(CustomError::InvalidProofLength {
+            expected: 304,
+            got: 272
+        })
+    ));
+    // No known length to check
+    assert!(IssuerProof::new(ProofAlgorithm::SU_ES256, vec![0; 3]).is_ok());
+}
+
+#[test]
+fn deserialize_typed_proof_of_wrong_length() {
+    let proof = IssuerProof::new(ProofAlgorithm::BBS, vec![0; 80]).unwrap();
+    let mut value = serde_json::to_value(&proof).unwrap();
+    assert_eq!(
+        serde_json::from_value::<IssuerProof>(value.clone()).unwrap(),
+        proof
+    );
+    value["bytes"].as_array_mut().unwrap().pop();
+    assert!(serde_json::from_value::<IssuerProof>(value).is_err());
+
+    let proof = PresentationProof::new(
+        PresentationProofAlgorithm::BBS_PROOF,
+        vec![0; BBSplusAlgorithm::proof_len(2)],
+        2,
+    )
+    .unwrap();
+    let mut value = serde_json::to_value(&proof).unwrap();
+    assert_eq!(
+        serde_json::from_value::<PresentationProof>(value.clone()).unwrap(),
+        proof
+    );
+    value["bytes"].as_array_mut().unwrap().pop();
+    assert!(serde_json::from_value::<PresentationProof>(value).is_err());
+}
+
+#[test]
+fn typed_proof_of_other_algorithm() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    let builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+
+    let proof = IssuerProof::new(ProofAlgorithm::BBS_SHAKE256, vec![0; 80]).unwrap();
+    assert!(matches!(
+        builder.build_with_typed_proof(proof),
+        Err(CustomError::ProofAlgorithmMismatch { .. })
+    ));
+
+    let proof = IssuerProof::new(ProofAlgorithm::BBS, vec![0; 80]).unwrap();
+    let mut issued_jwp = builder.build_with_typed_proof(proof.clone()).unwrap();
+    assert_eq!(issued_jwp.typed_proof(), &proof);
+    assert!(matches!(
+        issued_jwp.set_proof(IssuerProof::new(ProofAlgorithm::BBS_SHAKE256, vec![0; 80]).unwrap()),
+        Err(CustomError::ProofAlgorithmMismatch { .. })
+    ));
+}
+
+#[test]
+fn untyped_proof_of_wrong_length() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+
+    assert!(matches!(
+        builder.build_with_proof(Vec::new()),
+        Err(CustomError::InvalidProofLength {
+            expected: 80,
+            got: 0
+        })
+    ));
+    let issued_jwp = builder.build_with_proof(vec![0; 80]).unwrap();
+    assert_eq!(issued_jwp.typed_proof().alg(), ProofAlgorithm::BBS);
+
+    let mut presentation_builder = JwpPresentedBuilder::new(&issued_jwp);
+    presentation_builder
+        .set_presentation_protected_header(PresentationProtectedHeader::new(
+            PresentationProofAlgorithm::BBS_PROOF,
+        ))
+        .set_undisclosed("name")
+        .unwrap();
+    // A proof with no undisclosed payloads, while `name` is undisclosed
+    assert!(matches!(
+        presentation_builder.build_with_proof(vec![0; BBSplusAlgorithm::proof_len(0)]),
+        Err(CustomError::InvalidProofLength { .. })
+    ));
+    let presented_jwp = presentation_builder
+        .build_with_proof(vec![0; BBSplusAlgorithm::proof_len(1)])
+        .unwrap();
+    assert_eq!(
+        presented_jwp.typed_proof().alg(),
+        PresentationProofAlgorithm::BBS_PROOF
+    );
+}
@@ -0,0 +1,284 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proofs of the crate's own BBS ProofGen (`bbs-prover` feature), checked against zkryptium's verification.
+
+#![cfg(feature = "bbs-prover")]
+
+use jsonprooftoken::{
+    encoding::SerializationType,
+    errors::CustomError,
+    jpa::{
+        algs::{PresentationProofAlgorithm, ProofAlgorithm},
+        bbs_plus::BBSplusAlgorithm,
+    },
+    jpt::{claims::JptClaims, payloads::Payloads},
+    jwk::{key::Jwk, types::KeyPairSubtype},
+    jwp::{
+        header::{IssuerProtectedHeader, PresentationProtectedHeader},
+        issued::{JwpIssued, JwpIssuedBuilder},
+        presented::{JwpPresentedBuilder, JwpPresentedDecoder},
+    },
+};
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha20Rng,
+};
+
+fn presentation_header(nonce: &str) -> PresentationProtectedHeader {
+    let mut header = PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF);
+    header.set_aud(Some("https://recipient.example.com".to_owned()));
+    header.set_nonce(Some(nonce.to_owned()));
+    header
+}
+
+fn issue_emails(jwk: &Jwk) -> JwpIssued {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    jpt_claims.set_claim(Some("email"), "john@example.com", false);
+    jpt_claims.set_claim(Some("recovery_email"), "john@example.com", false);
+    jpt_claims.set_claim(Some("work_email"), "doe@company.example", false);
+    JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+        .unwrap()
+        .build(jwk)
+        .unwrap()
+}
+
+#[test]
+fn prove_equal_withheld_claims() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue_emails(&bbs_jwk);
+
+    let compact_presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .prove_equal("email", "recovery_email")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    let decoded_jwp =
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT).unwrap();
+    assert_eq!(
+        decoded_jwp.get_presentation_header().equalities(),
+        Some(&vec![("email".to_owned(), "recovery_email".to_owned())])
+    );
+    assert_eq!(
+        decoded_jwp.get_payloads().get_undisclosed_indexes(),
+        vec![1, 2]
+    );
+    decoded_jwp.verify(&public_jwk).unwrap();
+}
+
+#[test]
+fn prove_equal_different_claims() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue_emails(&bbs_jwk);
+
+    assert!(matches!(
+        JwpPresentedBuilder::new(&issued_jwp).prove_equal("email", "work_email"),
+        Err(CustomError::EqualityNotSatisfied(a, b)) if a == "email" && b == "work_email"
+    ));
+
+    // A holder claiming the equality anyway gets a proof that does not verify
+    let mut header = serde_json::to_value(presentation_header("nonce")).unwrap();
+    header["equalities"] = serde_json::json!([["email", "work_email"]]);
+    let presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(serde_json::from_value(header).unwrap())
+        .set_undisclosed("email")
+        .unwrap()
+        .set_undisclosed("work_email")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    let result = JwpPresentedDecoder::decode(&presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk);
+    assert!(matches!(result, Err(CustomError::InvalidPresentedProof)));
+}
+
+/// Random payloads with two equal undisclosed ones, and the pair of their indexes
+fn random_payloads(rng: &mut ChaCha20Rng) -> (Payloads, (usize, usize)) {
+    let len = 2 + rng.next_u32() as usize % 7;
+    let mut values: Vec<serde_json::Value> = (0..len)
+        .map(|_| serde_json::json!(rng.next_u32() % 4))
+        .collect();
+    let a = rng.next_u32() as usize % (len - 1);
+    let b = a + 1 + rng.next_u32() as usize % (len - a - 1);
+    values[b] = values[a].clone();
+
+    let mut payloads = Payloads::new_from_values(values);
+    for index in 0..len {
+        if index == a || index == b || rng.next_u32() % 2 == 0 {
+            payloads.set_undisclosed(index);
+        }
+    }
+    (payloads, (a, b))
+}
+
+#[test]
+fn equality_proofs_verify_with_zkryptium() {
+    let mut rng = ChaCha20Rng::seed_from_u64(351);
+    for (subtype, alg) in [
+        (
+            KeyPairSubtype::BLS12381G2Sha256,
+            PresentationProofAlgorithm::BBS_PROOF,
+        ),
+        (
+            KeyPairSubtype::BLS12381G2Shake256,
+            PresentationProofAlgorithm::BBS_SHAKE256_PROOF,
+        ),
+    ] {
+        let bbs_jwk = Jwk::generate(subtype).unwrap();
+        let public_jwk = bbs_jwk.to_public().unwrap();
+        for _ in 0..8 {
+            let (payloads, pair) = random_payloads(&mut rng);
+            let signature = BBSplusAlgorithm::generate_issuer_proof(
+                subtype.proof_alg(),
+                &payloads,
+                &bbs_jwk,
+                b"issuer header",
+            )
+            .unwrap();
+
+            let proof = BBSplusAlgorithm::generate_presentation_proof_with_equalities(
+                alg,
+                &signature,
+                &payloads,
+                &public_jwk,
+                b"issuer header",
+                b"presentation header",
+                &[pair],
+            )
+            .unwrap();
+            assert_eq!(
+                proof.len(),
+                BBSplusAlgorithm::proof_len(payloads.get_undisclosed_indexes().len())
+            );
+            // Verified by zkryptium's ProofVerify
+            BBSplusAlgorithm::verify_presentation_proof(
+                alg,
+                &public_jwk,
+                &proof,
+                b"presentation header",
+                b"issuer header",
+                &payloads,
+            )
+            .unwrap();
+            BBSplusAlgorithm::check_equal_responses(&proof, &payloads, &[pair]).unwrap();
+        }
+    }
+}
+
+#[test]
+fn equal_responses_of_malformed_proof() {
+    let mut payloads = Payloads::new_from_values(vec![serde_json::json!(1), serde_json::json!(1)]);
+    payloads.set_undisclosed(0);
+    payloads.set_undisclosed(1);
+
+    // Equal responses, but not a proof zkryptium can decode
+    let proof = vec![0xff; BBSplusAlgorithm::proof_len(2)];
+    assert!(matches!(
+        BBSplusAlgorithm::check_equal_responses(&proof, &payloads, &[(0, 1)]),
+        Err(CustomError::InvalidPresentedJwp)
+    ));
+    assert!(matches!(
+        BBSplusAlgorithm::check_equal_responses(&proof[1..], &payloads, &[(0, 1)]),
+        Err(CustomError::InvalidPresentedJwp)
+    ));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn seeded_proofs_verify_with_zkryptium() {
+    let mut rng = ChaCha20Rng::seed_from_u64(303);
+    for (subtype, alg) in [
+        (
+            KeyPairSubtype::BLS12381G2Sha256,
+            PresentationProofAlgorithm::BBS_PROOF,
+        ),
+        (
+            KeyPairSubtype::BLS12381G2Shake256,
+            PresentationProofAlgorithm::BBS_SHAKE256_PROOF,
+        ),
+    ] {
+        let bbs_jwk = Jwk::generate(subtype).unwrap();
+        let public_jwk = bbs_jwk.to_public().unwrap();
+        for _ in 0..8 {
+            // Any disclosure, including none and all of the payloads
+            let len = 1 + rng.next_u32() as usize % 8;
+            let mut payloads = Payloads::new_from_values(
+                (0..len)
+                    .map(|_| serde_json::json!(rng.next_u32()))
+                    .collect(),
+            );
+            for index in 0..len {
+                if rng.next_u32() % 2 == 0 {
+                    payloads.set_undisclosed(index);
+                }
+            }
+            let signature = BBSplusAlgorithm::generate_issuer_proof(
+                subtype.proof_alg(),
+                &payloads,
+                &bbs_jwk,
+                b"issuer header",
+            )
+            .unwrap();
+
+            let seed = rng.next_u64();
+            let generate = |seed: u64| {
+                BBSplusAlgorithm::generate_presentation_proof_with_rng(
+                    alg,
+                    &signature,
+                    &payloads,
+                    &public_jwk,
+                    b"issuer header",
+                    b"presentation header",
+                    &[],
+                    &mut ChaCha20Rng::seed_from_u64(seed),
+                )
+                .unwrap()
+            };
+            let proof = generate(seed);
+            assert_eq!(proof, generate(seed));
+            // Same length as the proofs of zkryptium's prover, and verified by zkryptium's ProofVerify
+            let library_proof = BBSplusAlgorithm::generate_presentation_proof(
+                alg,
+                &signature,
+                &payloads,
+                &public_jwk,
+                b"issuer header",
+                b"presentation header",
+            )
+            .unwrap();
+            assert_eq!(proof.len(), library_proof.len());
+            for proof in [&proof, &library_proof] {
+                BBSplusAlgorithm::verify_presentation_proof(
+                    alg,
+                    &public_jwk,
+                    proof,
+                    b"presentation header",
+                    b"issuer header",
+                    &payloads,
+                )
+                .unwrap();
+            }
+        }
+    }
+}
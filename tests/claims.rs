@@ -0,0 +1,545 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonprooftoken::{
+    errors::CustomError,
+    jpa::algs::ProofAlgorithm,
+    jpt::{
+        claims::{Claims, JptClaims},
+        digest::{DigestAlgorithm, DigestDisclosure},
+        flattening::{ClaimNode, ClaimSegment, FlattenConfig},
+        payloads::{PayloadType, Payloads},
+    },
+    jwp::{
+        header::IssuerProtectedHeader, issued::JwpIssuedBuilder, presented::JwpPresentedBuilder,
+    },
+};
+use serde_json::{json, Value};
+
+fn vc() -> Value {
+    json!({
+        "degree": {
+            "type": "BachelorDegree",
+            "name": "Bachelor of Science and Arts",
+            "ciao": [
+                {"u1": "value1"},
+                {"u2": "value2"}
+            ]
+        },
+        "name": "John Doe"
+    })
+}
+
+#[test]
+fn claims_and_payloads_round_trip() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("vc"), vc(), true);
+
+    let (claims, payloads) = jpt_claims.get_claims_and_payloads().unwrap();
+    let reconstructed = JptClaims::from_claims_and_payloads(&claims, &payloads).unwrap();
+
+    let mut expected = JptClaims::new();
+    expected.set_iss("https://issuer.example".to_owned());
+    expected.set_claim(Some("vc"), vc(), false);
+
+    assert_eq!(reconstructed, expected);
+}
+
+#[test]
+fn unflatten_array_with_gap() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("vc"), vc(), true);
+
+    let (claims, payloads) = jpt_claims.get_claims_and_payloads().unwrap();
+    let (kept_claims, kept_values): (Vec<String>, Vec<Value>) = claims
+        .0
+        .into_iter()
+        .zip(payloads.get_values())
+        .filter(|(claim, _)| !claim.starts_with("vc.degree.ciao[0]"))
+        .unzip();
+
+    let reconstructed = JptClaims::from_claims_and_payloads(
+        &Claims(kept_claims),
+        &Payloads::new_from_values(kept_values),
+    )
+    .unwrap();
+
+    assert_eq!(
+        reconstructed.get_claim("vc").unwrap()["degree"]["ciao"],
+        json!([null, {"u2": "value2"}])
+    );
+}
+
+#[test]
+fn flatten_with_custom_delimiters() {
+    let custom = FlattenConfig {
+        key_separator: "/".to_owned(),
+        array_start: "<".to_owned(),
+        array_end: ">".to_owned(),
+        preserve_empty: false,
+    };
+
+    let mut expected = JptClaims::new();
+    expected.set_claim(Some("vc"), vc(), false);
+
+    for config in [FlattenConfig::default(), custom] {
+        let mut jpt_claims = JptClaims::new();
+        jpt_claims
+            .set_claim_with_config(Some("vc"), vc(), true, &config)
+            .unwrap();
+
+        let (claims, payloads) = jpt_claims.get_claims_and_payloads().unwrap();
+        let first_claim = format!(
+            "vc{sep}degree{sep}ciao{start}0{end}{sep}u1",
+            sep = config.key_separator,
+            start = config.array_start,
+            end = config.array_end
+        );
+        assert!(claims.0.contains(&first_claim));
+
+        let reconstructed =
+            JptClaims::from_claims_and_payloads_with_config(&claims, &payloads, &config).unwrap();
+        assert_eq!(reconstructed, expected);
+    }
+}
+
+#[test]
+fn duplicate_registered_claim() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("iss"), "https://other.example", false);
+
+    match jpt_claims.get_claims_and_payloads() {
+        Err(CustomError::DuplicateClaim(claim)) => assert_eq!(claim, "iss"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn registered_jti_with_custom_jti() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    jpt_claims.set_jti("urn:uuid:1".to_owned());
+
+    let (claims, _) = jpt_claims.get_claims_and_payloads().unwrap();
+    assert_eq!(claims.0, vec!["jti", "name"]);
+
+    let mut flattened = jpt_claims.clone();
+    flattened.set_claim(Some("jti"), json!({"id": "urn:uuid:2"}), true);
+    match flattened.get_claims_and_payloads() {
+        Err(CustomError::DuplicateClaim(claim)) => assert_eq!(claim, "jti.id"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    jpt_claims.set_claim(Some("jti"), "urn:uuid:2", false);
+    match jpt_claims.get_claims_and_payloads() {
+        Err(CustomError::DuplicateClaim(claim)) => assert_eq!(claim, "jti"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn sorted_claims_keep_their_payloads() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("vc"), vc(), true);
+
+    let (claims, payloads) = jpt_claims.get_claims_and_payloads().unwrap();
+    let (sorted_claims, sorted_payloads) = jpt_claims.get_claims_and_payloads_sorted().unwrap();
+
+    let mut expected_order = claims.0.clone();
+    expected_order.sort();
+    assert_eq!(sorted_claims.0, expected_order);
+
+    for (claim, value) in sorted_claims.iter().zip(sorted_payloads.get_values()) {
+        let index = claims.get_claim_index(claim.clone()).unwrap();
+        assert_eq!(payloads.get(index).unwrap().0, value);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn iat_from_system_time() {
+    use std::time::{Duration, SystemTime};
+
+    let now = SystemTime::now();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iat_time(now);
+
+    let iat = jpt_claims.iat_as_time().unwrap();
+    assert!(iat <= now);
+    assert!(now.duration_since(iat).unwrap() < Duration::from_secs(1));
+    assert_eq!(
+        serde_json::to_value(&jpt_claims).unwrap()["iat"],
+        json!(jpt_claims.iat.unwrap())
+    );
+}
+
+#[test]
+fn payload_count_of_vc_example() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("vc"), vc(), true);
+
+    let (_, payloads) = jpt_claims.get_claims_and_payloads().unwrap();
+    assert_eq!(jpt_claims.payload_count(), payloads.len());
+    assert_eq!(jpt_claims.payload_count(), 6);
+}
+
+#[test]
+fn atomic_claim_is_one_payload() {
+    let signature = json!({"alg": "ES256", "value": "MEUCIQDx"});
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims
+        .set_atomic_claim("signature", signature.clone())
+        .unwrap();
+
+    let (claims, payloads) = jpt_claims.get_claims_and_payloads().unwrap();
+    assert_eq!(claims.0, ["iss", "signature"]);
+    assert_eq!(payloads.get(1).unwrap().0, signature);
+
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build_with_proof(vec![0; ProofAlgorithm::BBS.proof_len().unwrap()])
+            .unwrap();
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    assert!(builder.set_undisclosed("signature.value").is_err());
+    builder.set_undisclosed("signature").unwrap();
+    assert_eq!(builder.get_payloads().get_undisclosed_indexes(), [1]);
+}
+
+#[test]
+fn claim_not_representable_as_json() {
+    let value: std::collections::BTreeMap<(u8, u8), &str> = [((0, 1), "a")].into_iter().collect();
+    let mut jpt_claims = JptClaims::new();
+    let result =
+        jpt_claims.set_claim_with_config(Some("pairs"), &value, true, &FlattenConfig::default());
+    assert!(matches!(result, Err(CustomError::JsonError(_))));
+    assert_eq!(jpt_claims.payload_count(), 0);
+
+    assert!(matches!(
+        jpt_claims.set_atomic_claim("pairs", value),
+        Err(CustomError::JsonError(_))
+    ));
+    assert_eq!(jpt_claims.payload_count(), 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn validate_time_at_exp_and_nbf_boundaries() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let at = |seconds: u64| UNIX_EPOCH + Duration::from_secs(seconds);
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_nbf(1_000);
+    jpt_claims.set_exp(2_000);
+
+    assert!(jpt_claims.validate_time(at(1_999), Duration::ZERO).is_ok());
+    assert!(matches!(
+        jpt_claims.validate_time(at(2_000), Duration::ZERO),
+        Err(CustomError::Expired)
+    ));
+    assert!(jpt_claims
+        .validate_time(at(2_000), Duration::from_secs(1))
+        .is_ok());
+    assert!(matches!(
+        jpt_claims.validate_time(at(2_001), Duration::from_secs(1)),
+        Err(CustomError::Expired)
+    ));
+
+    assert!(jpt_claims.validate_time(at(1_000), Duration::ZERO).is_ok());
+    assert!(matches!(
+        jpt_claims.validate_time(at(999), Duration::ZERO),
+        Err(CustomError::NotYetValid)
+    ));
+    assert!(jpt_claims
+        .validate_time(at(999), Duration::from_secs(1))
+        .is_ok());
+}
+
+#[test]
+fn temporal_consistency_of_nbf_and_iat() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_nbf(2_000);
+    jpt_claims.set_iat(3_000);
+    assert!(jpt_claims.check_temporal_consistency().is_ok());
+
+    jpt_claims.set_exp(2_000);
+    assert!(matches!(
+        jpt_claims.check_temporal_consistency(),
+        Err(CustomError::InconsistentTemporalClaims(claim)) if claim == "iat"
+    ));
+
+    jpt_claims.set_iat(1_000);
+    assert!(jpt_claims.check_temporal_consistency().is_ok());
+
+    jpt_claims.set_nbf(2_001);
+    assert!(matches!(
+        jpt_claims.check_temporal_consistency(),
+        Err(CustomError::InconsistentTemporalClaims(claim)) if claim == "nbf"
+    ));
+}
+
+#[test]
+fn digest_claim_with_tampered_preimage() {
+    let photo = json!({"format": "jpeg", "data": "/9j/4AAQSkZJRgABAQ"});
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let disclosure = jpt_claims
+        .set_digest_claim("photo", photo.clone(), DigestAlgorithm::Sha256)
+        .unwrap();
+
+    let (claims, payloads) = jpt_claims.get_claims_and_payloads().unwrap();
+    let index = claims.get_claim_index("photo".to_owned()).unwrap();
+    let digest_claim = &payloads.get(index).unwrap().0;
+    assert_eq!(digest_claim["_sd_alg"], "sha-256");
+    assert_eq!(disclosure.verify(digest_claim).unwrap(), &photo);
+
+    let tampered = DigestDisclosure::new("photo", json!({"format": "jpeg", "data": "AAAA"}));
+    assert!(matches!(
+        tampered.verify(digest_claim),
+        Err(CustomError::DigestMismatch(claim)) if claim == "photo"
+    ));
+}
+
+#[test]
+fn merge_claims_from_two_sources() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("vc"), vc(), true);
+
+    let mut registry = JptClaims::new();
+    registry.set_iss("https://issuer.example".to_owned());
+    registry.set_sub("did:example:123".to_owned());
+    registry.set_claim(Some("email"), "john@example.com", false);
+
+    let count = jpt_claims.payload_count();
+    jpt_claims.merge(registry).unwrap();
+    assert_eq!(jpt_claims.sub.as_deref(), Some("did:example:123"));
+    assert_eq!(
+        jpt_claims.get_claim("email"),
+        Some(&json!("john@example.com"))
+    );
+    // iss is set in both with the same value
+    assert_eq!(jpt_claims.payload_count(), count + 2);
+
+    let mut conflicting = JptClaims::new();
+    conflicting.set_claim(Some("email"), "jane@example.com", false);
+    let before = jpt_claims.clone();
+    assert!(matches!(
+        jpt_claims.merge(conflicting),
+        Err(CustomError::DuplicateClaim(claim)) if claim == "email"
+    ));
+    assert_eq!(jpt_claims, before);
+
+    let mut nested = JptClaims::new();
+    nested.set_claim(Some("vc"), json!({"name": "John Doe"}), false);
+    assert!(matches!(
+        jpt_claims.merge(nested),
+        Err(CustomError::DuplicateClaim(claim)) if claim == "vc"
+    ));
+}
+
+#[test]
+fn subtree_and_duplicates_with_custom_delimiters() {
+    let config = FlattenConfig {
+        key_separator: "/".to_owned(),
+        array_start: "<".to_owned(),
+        array_end: ">".to_owned(),
+        preserve_empty: false,
+    };
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims
+        .set_claim_with_config(Some("vc"), vc(), true, &config)
+        .unwrap();
+
+    let mut nested = JptClaims::new();
+    nested.set_claim(Some("vc"), json!({"name": "John Doe"}), false);
+    assert!(matches!(
+        jpt_claims.clone().merge_with_config(nested, &config),
+        Err(CustomError::DuplicateClaim(claim)) if claim == "vc"
+    ));
+
+    let mut with_jti = jpt_claims.clone();
+    with_jti.set_jti("urn:uuid:1".to_owned());
+    with_jti
+        .set_claim_with_config(Some("jti"), json!({"id": "urn:uuid:2"}), true, &config)
+        .unwrap();
+    assert!(with_jti.get_claims_and_payloads().is_ok());
+    assert!(matches!(
+        with_jti.get_claims_and_payloads_with_config(&config),
+        Err(CustomError::DuplicateClaim(claim)) if claim == "jti/id"
+    ));
+
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build_with_proof(vec![0; ProofAlgorithm::BBS.proof_len().unwrap()])
+            .unwrap();
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    assert!(builder.set_undisclosed_subtree("vc/degree").is_err());
+    assert_eq!(
+        builder
+            .set_undisclosed_subtree_with_config("vc/degree", &config)
+            .unwrap(),
+        4
+    );
+}
+
+#[test]
+fn disclosable_tree_of_nested_vc() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("vc"), vc(), true);
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build_with_proof(vec![0; ProofAlgorithm::BBS.proof_len().unwrap()])
+            .unwrap();
+
+    assert_eq!(issued_jwp.disclosable_paths().len(), 6);
+    let tree = issued_jwp.disclosable_tree().unwrap();
+    assert_eq!(tree.len(), 2);
+    assert_eq!(tree[0].segment, ClaimSegment::Key("iss".to_owned()));
+    assert_eq!(tree[0].claim.as_deref(), Some("iss"));
+
+    // vc -> degree -> ciao -> [0] -> u1
+    let vc = &tree[1];
+    assert_eq!(vc.depth(), 5);
+    assert_eq!(vc.claim, None);
+    let degree = &vc.children[0];
+    assert_eq!(degree.segment, ClaimSegment::Key("degree".to_owned()));
+    let ciao = &degree.children[2];
+    assert_eq!(ciao.segment, ClaimSegment::Key("ciao".to_owned()));
+    assert_eq!(ciao.children[1].segment, ClaimSegment::Index(1));
+    assert_eq!(
+        ciao.children[1].children[0].claim.as_deref(),
+        Some("vc.degree.ciao[1].u2")
+    );
+
+    // Every leaf is a claim, so the tree holds all of them
+    fn leaves(nodes: &[ClaimNode]) -> Vec<String> {
+        nodes
+            .iter()
+            .flat_map(|node| node.claim.clone().into_iter().chain(leaves(&node.children)))
+            .collect()
+    }
+    assert_eq!(leaves(&tree), issued_jwp.disclosable_paths());
+}
+
+#[test]
+fn payloads_json_serialization() {
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Jwp {
+        #[serde(with = "jsonprooftoken::jpt::payloads::json_serialization")]
+        payloads: Payloads,
+    }
+
+    let jwp: Jwp =
+        serde_json::from_value(json!({ "payloads": ["IkpvaG4gRG9lIg", null, "NDI", ""] })).unwrap();
+    assert_eq!(
+        jwp.payloads,
+        Payloads(vec![
+            (json!("John Doe"), PayloadType::Disclosed),
+            (Value::Null, PayloadType::Undisclosed),
+            (json!(42), PayloadType::Disclosed),
+            (Value::Null, PayloadType::Undisclosed),
+        ])
+    );
+
+    assert_eq!(
+        serde_json::to_value(&jwp).unwrap(),
+        json!({ "payloads": ["IkpvaG4gRG9lIg", null, "NDI", null] })
+    );
+
+    assert!(serde_json::from_value::<Jwp>(json!({ "payloads": ["not base64!"] })).is_err());
+}
+
+#[test]
+fn payloads_bytes_without_copies() {
+    let mut payloads = Payloads::new_from_values(vec![
+        json!({"name": "John Doe", "degree": {"type": "BachelorDegree", "name": "Bachelor"}}),
+        json!([{"b": 1, "a": 2}]),
+        json!(42),
+    ]);
+    payloads.set_undisclosed(2);
+
+    assert_eq!(
+        payloads.to_bytes().unwrap(),
+        vec![
+            br#"{"degree":{"name":"Bachelor","type":"BachelorDegree"},"name":"John Doe"}"#.to_vec(),
+            br#"[{"a":2,"b":1}]"#.to_vec(),
+            b"42".to_vec(),
+        ]
+    );
+    assert_eq!(
+        payloads.disclosed_to_bytes().unwrap(),
+        payloads.get_disclosed_payloads().to_bytes().unwrap()
+    );
+    assert_eq!(payloads.disclosed_to_bytes().unwrap().len(), 2);
+}
+
+#[test]
+fn payload_sizes_sum_to_total() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("vc"), vc(), true);
+    jpt_claims
+        .set_atomic_claim("photo", "/9j/4AAQSkZJRgABAQ".repeat(100))
+        .unwrap();
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build_with_proof(vec![0; ProofAlgorithm::BBS.proof_len().unwrap()])
+            .unwrap();
+
+    let sizes = issued_jwp.get_payloads().byte_sizes().unwrap();
+    let report = issued_jwp.payload_size_report().unwrap();
+    assert_eq!(report.total, sizes.iter().sum::<usize>());
+    assert_eq!(
+        report.total,
+        report.claims.iter().map(|(_, size)| size).sum::<usize>()
+    );
+    assert_eq!(
+        report.claims[0],
+        ("iss".to_owned(), r#""https://issuer.example""#.len())
+    );
+
+    let largest = report.claims.iter().max_by_key(|(_, size)| *size).unwrap();
+    assert_eq!(largest.0, "photo");
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn jti_as_uuid_round_trip() {
+    let id = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+    let mut jpt_claims = JptClaims::new();
+    assert!(jpt_claims.jti_as_uuid().is_none());
+
+    jpt_claims.set_jti_uuid(id);
+    assert_eq!(
+        jpt_claims.jti.as_deref(),
+        Some("67e55044-10b1-426f-9247-bb680e5fe0c8")
+    );
+    assert_eq!(jpt_claims.jti_as_uuid().unwrap().unwrap(), id);
+
+    jpt_claims.set_jti("not-a-uuid".to_owned());
+    assert!(matches!(
+        jpt_claims.jti_as_uuid(),
+        Some(Err(CustomError::InvalidUuid(_)))
+    ));
+}
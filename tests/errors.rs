@@ -0,0 +1,84 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use data_encoding::BASE64URL_NOPAD;
+use jsonprooftoken::{errors::CustomError, jwp::header::IssuerProtectedHeader};
+
+fn decode_header(encoded: &str) -> Result<IssuerProtectedHeader, CustomError> {
+    let header_oct = BASE64URL_NOPAD.decode(encoded.as_bytes())?;
+    Ok(serde_json::from_slice(&header_oct)?)
+}
+
+#[test]
+fn question_mark_converts_errors() {
+    let header = decode_header("eyJhbGciOiJCQlMifQ").unwrap();
+    assert_eq!(header.alg().to_string(), "BBS");
+
+    assert!(matches!(
+        decode_header("not base64!"),
+        Err(CustomError::Base64Error(_))
+    ));
+    // {"alg":
+    assert!(matches!(
+        decode_header("eyJhbGciOg"),
+        Err(CustomError::JsonError(_))
+    ));
+}
+
+#[test]
+fn json_error_keeps_its_source() {
+    use std::error::Error;
+
+    // {"alg":
+    let error = decode_header("eyJhbGciOg").unwrap_err();
+    assert!(error.to_string().contains("EOF while parsing"));
+    let source = error
+        .source()
+        .and_then(|source| source.downcast_ref::<serde_json::Error>())
+        .unwrap();
+    assert!(source.is_eof());
+}
+
+#[test]
+fn incomplete_build_names_the_missing_piece() {
+    use jsonprooftoken::{
+        errors::IncompleteJwpBuild,
+        jpa::algs::ProofAlgorithm,
+        jpt::claims::JptClaims,
+        jwp::{issued::JwpIssuedBuilder, presented::JwpPresentedBuilder},
+    };
+    use std::error::Error;
+
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build_with_proof(vec![0; ProofAlgorithm::BBS.proof_len().unwrap()])
+            .unwrap();
+
+    let error = JwpPresentedBuilder::new(&issued_jwp)
+        .build_with_proof(Vec::new())
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Incomplete Jwp build: Presentation Header Not set!"
+    );
+    assert!(matches!(
+        error
+            .source()
+            .and_then(|source| source.downcast_ref::<IncompleteJwpBuild>()),
+        Some(IncompleteJwpBuild::NoPresentationHeader)
+    ));
+}
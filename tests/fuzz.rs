@@ -0,0 +1,77 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonprooftoken::{encoding::base64url_encode, jwp::fuzz_decode};
+
+/// xorshift64, good enough to generate inputs deterministically
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[test]
+fn fuzz_decode_never_panics() {
+    let seed = format!(
+        "{}.{}.{}~~{}.{}",
+        base64url_encode(br#"{"typ":"JPT","alg":"BBS","claims":["name","age","email"]}"#),
+        base64url_encode(br#"{"alg":"BBS-PROOF","predicates":{"age":{"eq":42}}}"#),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode(br#""john@example.com""#),
+        base64url_encode([7u8; 304])
+    );
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_.~=";
+    let mut rng = Rng(0x5eed_1234_abcd_ef01);
+
+    for i in 0..1000 {
+        let data: Vec<u8> = match i % 3 {
+            // Arbitrary bytes
+            0 => (0..rng.below(256)).map(|_| rng.next() as u8).collect(),
+            // Arbitrary base64url segments
+            1 => (0..rng.below(256))
+                .map(|_| alphabet[rng.below(alphabet.len())])
+                .collect(),
+            // Mutations of a well formed token
+            _ => {
+                let mut data = seed.clone().into_bytes();
+                for _ in 0..=rng.below(4) {
+                    let index = rng.below(data.len());
+                    match rng.below(3) {
+                        0 => data[index] = alphabet[rng.below(alphabet.len())],
+                        1 => {
+                            data.remove(index);
+                        }
+                        _ => data.truncate(index),
+                    }
+                    if data.is_empty() {
+                        break;
+                    }
+                }
+                data
+            }
+        };
+        let _ = fuzz_decode(&data);
+    }
+
+    assert!(fuzz_decode(seed.as_bytes()).is_ok());
+}
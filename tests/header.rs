@@ -0,0 +1,145 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use jsonprooftoken::{
+    encoding::{base64url_encode, SerializationType},
+    errors::CustomError,
+    jpa::algs::{PresentationProofAlgorithm, ProofAlgorithm},
+    jwp::{
+        header::{IssuerProtectedHeader, PresentationProtectedHeader},
+        issued::JwpIssuedDecoder,
+        presented::JwpPresentedDecoder,
+    },
+};
+
+#[test]
+fn issuer_canonical_bytes_are_stable() {
+    let mut first = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+    first.set_kid(Some("key-1".to_owned()));
+    first.set_cid(Some("cid-1".to_owned()));
+
+    let mut second = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+    second.set_cid(Some("cid-1".to_owned()));
+    second.set_kid(Some("key-1".to_owned()));
+
+    let third: IssuerProtectedHeader =
+        serde_json::from_str(r#"{ "cid": "cid-1", "kid": "key-1", "alg": "BBS", "typ": "JPT" }"#)
+            .unwrap();
+
+    let canonical = br#"{"typ":"JPT","alg":"BBS","kid":"key-1","cid":"cid-1"}"#;
+    assert_eq!(first.canonical_bytes().unwrap(), canonical);
+    assert_eq!(second.canonical_bytes().unwrap(), canonical);
+    assert_eq!(third.canonical_bytes().unwrap(), canonical);
+}
+
+#[test]
+fn presentation_canonical_bytes_are_stable() {
+    let mut first = PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF);
+    first.set_aud(Some("https://recipient.example.com".to_owned()));
+    first.set_nonce(Some("nonce".to_owned()));
+
+    let mut second = PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF);
+    second.set_nonce(Some("nonce".to_owned()));
+    second.set_aud(Some("https://recipient.example.com".to_owned()));
+
+    let third: PresentationProtectedHeader = serde_json::from_str(
+        r#"{"nonce": "nonce", "aud": "https://recipient.example.com", "alg": "BBS-PROOF"}"#,
+    )
+    .unwrap();
+
+    let canonical = br#"{"alg":"BBS-PROOF","aud":"https://recipient.example.com","nonce":"nonce"}"#;
+    assert_eq!(first.canonical_bytes().unwrap(), canonical);
+    assert_eq!(second.canonical_bytes().unwrap(), canonical);
+    assert_eq!(third.canonical_bytes().unwrap(), canonical);
+}
+
+#[test]
+fn decode_strict_with_duplicate_alg() {
+    let issuer_header = br#"{"alg":"BBS","claims":["name"],"alg":"BBS-SHAKE256"}"#;
+    let compact_issued_jwp = format!(
+        "{}.{}.{}",
+        base64url_encode(issuer_header),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode([0u8; 80])
+    );
+
+    assert!(matches!(
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT),
+        Err(CustomError::JsonError(_))
+    ));
+
+    match JwpIssuedDecoder::decode_strict(&compact_issued_jwp, SerializationType::COMPACT) {
+        Err(CustomError::DuplicateHeaderParameter(parameter)) => assert_eq!(parameter, "alg"),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    let compact_presented_jwp = format!(
+        "{}.{}.{}.{}",
+        base64url_encode(br#"{"alg":"BBS","claims":["name"]}"#),
+        base64url_encode(br#"{"alg":"BBS-PROOF","ext":"a","ext":"b"}"#),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode([0u8; 272])
+    );
+    match JwpPresentedDecoder::decode_strict(&compact_presented_jwp, SerializationType::COMPACT) {
+        Err(CustomError::DuplicateHeaderParameter(parameter)) => assert_eq!(parameter, "ext"),
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn blinded_kid_matches_candidate() {
+    let mut header = PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF);
+    assert!(!header.matches_blinded_kid("issuer-key-1"));
+
+    header.set_blinded_kid("issuer-key-1", b"per-presentation salt");
+    assert!(header.kid().is_none());
+    assert!(header.matches_blinded_kid("issuer-key-1"));
+    assert!(!header.matches_blinded_kid("issuer-key-2"));
+
+    let json = serde_json::to_string(&header).unwrap();
+    assert!(!json.contains("issuer-key-1"));
+    let decoded: PresentationProtectedHeader = serde_json::from_str(&json).unwrap();
+    assert!(decoded.matches_blinded_kid("issuer-key-1"));
+
+    let mut other = PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF);
+    other.set_blinded_kid("issuer-key-1", b"another salt");
+    assert_ne!(other.blinded_kid(), header.blinded_kid());
+}
+
+#[test]
+fn strict_header_with_unknown_parameter() {
+    let header = br#"{"typ":"JPT","alg":"BBS","claims":["iss"],"ext":"value"}"#;
+
+    let lenient: IssuerProtectedHeader = serde_json::from_slice(header).unwrap();
+    assert_eq!(lenient.alg(), ProofAlgorithm::BBS);
+    assert!(matches!(
+        IssuerProtectedHeader::from_slice_strict(header),
+        Err(CustomError::UnknownHeaderParameter(parameter)) if parameter == "ext"
+    ));
+
+    let critical = br#"{"typ":"JPT","alg":"BBS","claims":["iss"],"ext":"value","crit":["ext"]}"#;
+    assert_eq!(
+        IssuerProtectedHeader::from_slice_strict(critical).unwrap(),
+        serde_json::from_slice::<IssuerProtectedHeader>(critical).unwrap()
+    );
+    assert_eq!(
+        lenient.extra().get("ext"),
+        Some(&serde_json::json!("value"))
+    );
+
+    assert!(matches!(
+        PresentationProtectedHeader::from_slice_strict(br#"{"alg":"BBS-PROOF","typ":"JPT"}"#),
+        Err(CustomError::UnknownHeaderParameter(parameter)) if parameter == "typ"
+    ));
+}
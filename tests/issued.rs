@@ -0,0 +1,1250 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "std")]
+
+use jsonprooftoken::{
+    encoding::{
+        base64url_decode, base64url_decode_lenient, base64url_encode, Compression,
+        SerializationType,
+    },
+    errors::CustomError,
+    jpa::{
+        algs::{PresentationProofAlgorithm, ProofAlgorithm},
+        bbs_plus::BBSplusAlgorithm,
+    },
+    jpt::{
+        claims::{Claims, ClaimsResolver, JptClaims},
+        payloads::{PayloadType, Payloads},
+    },
+    jwk::{
+        alg_parameters::{Algorithm, JwkAlgorithmParameters, JwkEllipticCurveKeyParameters},
+        curves::EllipticCurveTypes,
+        key::{Jwk, KeyOps, PKUse},
+        types::KeyPairSubtype,
+    },
+    jwp::{
+        header::{IssuerProtectedHeader, PresentationProtectedHeader},
+        issued::{JwpIssued, JwpIssuedBuilder, JwpIssuedDecoder, ProofInput},
+        presented::{JwpPresentedBuilder, JwpPresentedDecoder},
+    },
+};
+
+fn issue(jwk: &Jwk) -> JwpIssued {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+
+    JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+        .unwrap()
+        .build(jwk)
+        .unwrap()
+}
+
+#[test]
+fn verify_with_incompatible_curve() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let compact_issued_jwp = issue(&bbs_jwk).encode(SerializationType::COMPACT).unwrap();
+
+    let p256_jwk = Jwk::from_key_params(JwkAlgorithmParameters::EllipticCurve(
+        JwkEllipticCurveKeyParameters::new(EllipticCurveTypes::P256, &[0; 32], &[0; 32], None),
+    ));
+
+    let result = JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&p256_jwk);
+
+    match result {
+        Err(CustomError::IncompatibleKeyAlgorithm { alg, crv }) => {
+            assert_eq!(alg, "BBS");
+            assert_eq!(crv, "P-256");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn encoded_len_matches_encode() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    assert_eq!(
+        issued_jwp.encoded_len(SerializationType::COMPACT).unwrap(),
+        issued_jwp.encode(SerializationType::COMPACT).unwrap().len()
+    );
+}
+
+#[test]
+fn encoded_len_matches_encode_with_predicate() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut issued_jwp = issue(&bbs_jwk);
+    let mut payloads = issued_jwp.get_payloads().clone();
+    payloads.set_predicate(1);
+    issued_jwp.set_payloads(payloads);
+
+    let compact_issued_jwp = issued_jwp.encode(SerializationType::COMPACT).unwrap();
+    assert_eq!(
+        issued_jwp.encoded_len(SerializationType::COMPACT).unwrap(),
+        compact_issued_jwp.len()
+    );
+    // Predicate payloads are omitted, as in the presented JWP
+    assert_eq!(
+        compact_issued_jwp
+            .split('.')
+            .nth(1)
+            .unwrap()
+            .split('~')
+            .nth(1),
+        Some("")
+    );
+}
+
+#[test]
+fn verify_truncated_proof() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let compact_issued_jwp = issue(&bbs_jwk).encode(SerializationType::COMPACT).unwrap();
+
+    let (rest, encoded_proof) = compact_issued_jwp.rsplit_once('.').unwrap();
+    let proof = base64url_decode(encoded_proof).unwrap();
+    let truncated = format!("{}.{}", rest, base64url_encode(&proof[..proof.len() - 1]));
+
+    let result = JwpIssuedDecoder::decode(&truncated, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&bbs_jwk.to_public().unwrap());
+
+    match result {
+        Err(CustomError::InvalidProofLength { expected, got }) => {
+            assert_eq!(expected, proof.len());
+            assert_eq!(got, proof.len() - 1);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn replace_claim_value_before_build() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(
+        Some("vc"),
+        serde_json::json!({"name": "John Doe", "age": 42}),
+        true,
+    );
+
+    let mut builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+    builder
+        .replace_claim_value("vc.name", serde_json::json!("6cc2e1b3"))
+        .unwrap();
+    assert!(matches!(
+        builder.replace_claim_value("vc.surname", serde_json::json!("Doe")),
+        Err(CustomError::SelectiveDisclosureError)
+    ));
+
+    let compact_issued_jwp = builder
+        .build(&bbs_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    let issued_jwp = JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&bbs_jwk.to_public().unwrap())
+        .unwrap();
+
+    let index = issued_jwp
+        .get_claims()
+        .unwrap()
+        .0
+        .iter()
+        .position(|c| c == "vc.name")
+        .unwrap();
+    assert_eq!(
+        issued_jwp.get_payloads().get_values()[index],
+        serde_json::json!("6cc2e1b3")
+    );
+}
+
+#[test]
+fn salted_tokens_have_different_payloads() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issue_salted = || {
+        let mut jpt_claims = JptClaims::new();
+        jpt_claims.set_iss("https://issuer.example".to_owned());
+        jpt_claims.set_claim(Some("name"), "John Doe", false);
+
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .set_salted()
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap()
+            .encode(SerializationType::COMPACT)
+            .unwrap()
+    };
+
+    let first = issue_salted();
+    let second = issue_salted();
+    let payload_segments = |jpt: &str| jpt.split('.').nth(1).unwrap().to_owned();
+    assert_ne!(payload_segments(&first), payload_segments(&second));
+
+    let issued_jwp = JwpIssuedDecoder::decode(&first, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+    assert!(issued_jwp.get_issuer_protected_header().salted());
+    assert_eq!(
+        issued_jwp.get_payloads().unsalted().unwrap().get_values(),
+        vec![
+            serde_json::json!("https://issuer.example"),
+            serde_json::json!("John Doe")
+        ]
+    );
+}
+
+#[test]
+fn decode_malformed_header_reports_json_error() {
+    let compact_issued_jwp = format!(
+        "{}.{}.{}",
+        base64url_encode(br#"{"alg": "BBS", "typ": }"#),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode([0u8; 80])
+    );
+
+    let error = JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .err()
+        .unwrap();
+
+    assert!(matches!(error, CustomError::JsonError(_)));
+    assert!(error.to_string().contains("expected value"));
+}
+
+#[test]
+fn read_kid_before_verification() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut header = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+    header.set_kid(Some("issuer-key-1".to_owned()));
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let compact_issued_jwp = JwpIssuedBuilder::new(header, jpt_claims)
+        .unwrap()
+        .build(&bbs_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    let decoder =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert_eq!(
+        decoder.get_header().kid().map(String::as_str),
+        Some("issuer-key-1")
+    );
+    assert_eq!(decoder.get_claims().unwrap().0, vec!["name".to_owned()]);
+
+    let (header, payloads, proof) = decoder.clone().into_unverified_parts();
+    assert_eq!(header.kid(), decoder.get_header().kid());
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(proof, decoder.get_proof());
+}
+
+#[test]
+fn issue_with_sorted_claims() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    jpt_claims.set_claim(Some("age"), 42, false);
+
+    let compact_issued_jwp =
+        JwpIssuedBuilder::new_sorted(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap()
+            .encode(SerializationType::COMPACT)
+            .unwrap();
+    let issued_jwp = JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&bbs_jwk.to_public().unwrap())
+        .unwrap();
+
+    assert_eq!(
+        issued_jwp.get_claims().unwrap().0,
+        vec!["age", "iss", "name"]
+    );
+    assert_eq!(
+        issued_jwp.get_payloads().get_values(),
+        vec![
+            serde_json::json!(42),
+            serde_json::json!("https://issuer.example"),
+            serde_json::json!("John Doe")
+        ]
+    );
+}
+
+#[test]
+fn payloads_with_different_key_order_verify() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(
+        Some("address"),
+        serde_json::json!({"street": "Via Pier Carlo Boggio 61", "city": "Torino"}),
+        false,
+    );
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap();
+
+    let mut address = serde_json::Map::new();
+    address.insert("city".to_owned(), "Torino".into());
+    address.insert("street".to_owned(), "Via Pier Carlo Boggio 61".into());
+    let reordered = Payloads::new_from_values(vec![serde_json::Value::Object(address)]);
+    assert_ne!(
+        serde_json::to_string(&reordered.get_values()).unwrap(),
+        serde_json::to_string(&issued_jwp.get_payloads().get_values()).unwrap()
+    );
+
+    BBSplusAlgorithm::verify_issuer_proof(
+        ProofAlgorithm::BBS,
+        &bbs_jwk.to_public().unwrap(),
+        issued_jwp.get_proof(),
+        &issued_jwp
+            .get_issuer_protected_header()
+            .canonical_bytes()
+            .unwrap(),
+        &reordered,
+    )
+    .unwrap();
+}
+
+#[test]
+fn verify_self_contained_with_proof_key() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut header = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+    header.set_proof_key(Some(bbs_jwk.to_public().unwrap()));
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let compact_issued_jwp = JwpIssuedBuilder::new(header, jpt_claims)
+        .unwrap()
+        .build(&bbs_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify_self_contained()
+        .unwrap();
+
+    let compact_without_key = issue(&bbs_jwk).encode(SerializationType::COMPACT).unwrap();
+    let result = JwpIssuedDecoder::decode(&compact_without_key, SerializationType::COMPACT)
+        .unwrap()
+        .verify_self_contained();
+    assert!(matches!(result, Err(CustomError::MissingProofKey)));
+}
+
+#[test]
+fn disclosed_null_payload_round_trip() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("middle_name"), serde_json::Value::Null, false);
+    let compact_issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap()
+            .encode(SerializationType::COMPACT)
+            .unwrap();
+
+    let decoder =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert_eq!(
+        decoder.get_payloads().get(0),
+        Some(&(serde_json::Value::Null, PayloadType::Disclosed))
+    );
+    decoder.verify(&bbs_jwk.to_public().unwrap()).unwrap();
+}
+
+#[test]
+fn storage_bytes_round_trip() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let bytes = issued_jwp.to_bytes().unwrap();
+    assert_eq!(JwpIssued::from_bytes(&bytes).unwrap(), issued_jwp);
+
+    assert!(matches!(
+        JwpIssued::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(CustomError::SerializationError(message)) if message.contains("truncated")
+    ));
+}
+
+#[test]
+fn read_registered_claims_of_verified_jwp() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let compact_issued_jwp = issue(&bbs_jwk).encode(SerializationType::COMPACT).unwrap();
+
+    let issued_jwp = JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&bbs_jwk.to_public().unwrap())
+        .unwrap();
+
+    assert_eq!(issued_jwp.iss(), Some("https://issuer.example"));
+    assert_eq!(issued_jwp.sub(), None);
+    assert_eq!(issued_jwp.exp(), None);
+    assert_eq!(
+        issued_jwp.registered_claim("name"),
+        Some(&serde_json::json!("John Doe"))
+    );
+}
+
+#[test]
+fn proof_generation_error_with_context() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+
+    // Issuing requires the private key
+    let result = JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+        .unwrap()
+        .build(&bbs_jwk.to_public().unwrap());
+
+    match result {
+        Err(error @ CustomError::ProofGenerationError { .. }) => assert_eq!(
+            error.to_string(),
+            "Error during generation of a BBS proof over 2 payloads: key is not compatible"
+        ),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn verify_with_private_key() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let compact_issued_jwp = issue(&bbs_jwk).encode(SerializationType::COMPACT).unwrap();
+
+    // Verifying requires the public key, and fails as a verification, not a generation
+    let result = JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&bbs_jwk);
+
+    assert!(matches!(
+        result,
+        Err(CustomError::ProofVerificationError(message)) if message == "key is not compatible"
+    ));
+}
+
+#[test]
+fn signing_input_matches_header_and_payloads() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+
+    let (header_oct, messages) = builder.signing_input().unwrap();
+    assert_eq!(
+        header_oct,
+        serde_json::to_vec(builder.get_issuer_protected_header().unwrap()).unwrap()
+    );
+    assert_eq!(
+        messages,
+        vec![
+            b"\"https://issuer.example\"".to_vec(),
+            b"\"John Doe\"".to_vec()
+        ]
+    );
+}
+
+#[test]
+fn verify_header_with_external_member_order() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+
+    // Signed as produced by another implementation, not in the canonical order
+    let header_oct = br#"{"claims":["name","iss"],"typ":"JPT","alg":"BBS"}"#;
+    let payloads = Payloads::new_from_values(vec![
+        serde_json::json!("John Doe"),
+        serde_json::json!("https://issuer.example"),
+    ]);
+    let proof = BBSplusAlgorithm::generate_issuer_proof(
+        ProofAlgorithm::BBS,
+        &payloads,
+        &bbs_jwk,
+        header_oct,
+    )
+    .unwrap();
+    let compact_issued_jwp = format!(
+        "{}.{}~{}.{}",
+        base64url_encode(header_oct),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode(br#""https://issuer.example""#),
+        base64url_encode(&proof)
+    );
+
+    let issued_jwp = JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+    assert_eq!(
+        issued_jwp.encode(SerializationType::COMPACT).unwrap(),
+        compact_issued_jwp
+    );
+
+    let compact_presented_jwp = issued_jwp
+        .present_all(
+            PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF),
+            &public_jwk,
+        )
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+}
+
+struct TestResolver;
+
+impl ClaimsResolver for TestResolver {
+    fn resolve(&self, cid: &str) -> Option<Claims> {
+        (cid == "urn:example:claims:person")
+            .then(|| Claims(vec!["name".to_owned(), "age".to_owned()]))
+    }
+}
+
+#[test]
+fn decode_with_cid_instead_of_claims() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+
+    let header_oct = br#"{"typ":"JPT","alg":"BBS","cid":"urn:example:claims:person"}"#;
+    let payloads =
+        Payloads::new_from_values(vec![serde_json::json!("John Doe"), serde_json::json!(42)]);
+    let proof = BBSplusAlgorithm::generate_issuer_proof(
+        ProofAlgorithm::BBS,
+        &payloads,
+        &bbs_jwk,
+        header_oct,
+    )
+    .unwrap();
+    let compact_issued_jwp = format!(
+        "{}.{}~{}.{}",
+        base64url_encode(header_oct),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode(b"42"),
+        base64url_encode(&proof)
+    );
+
+    assert!(matches!(
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT),
+        Err(CustomError::UnresolvedClaims(cid)) if cid == "urn:example:claims:person"
+    ));
+
+    let issued_jwp = JwpIssuedDecoder::decode_with_resolver(
+        &compact_issued_jwp,
+        SerializationType::COMPACT,
+        &TestResolver,
+    )
+    .unwrap()
+    .verify(&public_jwk)
+    .unwrap();
+    assert_eq!(issued_jwp.get_claims().unwrap().0, vec!["name", "age"]);
+    assert_eq!(issued_jwp.get_payloads(), &payloads);
+
+    let mut presentation_builder = JwpPresentedBuilder::new(&issued_jwp);
+    let compact_presented_jwp = presentation_builder
+        .set_presentation_protected_header(PresentationProtectedHeader::new(
+            PresentationProofAlgorithm::BBS_PROOF,
+        ))
+        .set_undisclosed("age")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    assert!(matches!(
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT),
+        Err(CustomError::UnresolvedClaims(_))
+    ));
+    let presented_jwp = JwpPresentedDecoder::decode_with_resolver(
+        &compact_presented_jwp,
+        SerializationType::COMPACT,
+        &TestResolver,
+    )
+    .unwrap()
+    .verify(&public_jwk)
+    .unwrap();
+    assert_eq!(
+        presented_jwp.get_payloads().get(0),
+        Some(&(serde_json::json!("John Doe"), PayloadType::Disclosed))
+    );
+}
+
+#[test]
+fn verify_unsupported_algorithm() {
+    let public_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256)
+        .unwrap()
+        .to_public()
+        .unwrap();
+    let compact_issued_jwp = format!(
+        "{}.{}.{}",
+        base64url_encode(br#"{"typ":"JPT","alg":"MAC-H256","claims":["name"]}"#),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode([0u8; 32])
+    );
+
+    let decoded_issued_jwp =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert!(matches!(
+        decoded_issued_jwp.verify(&public_jwk),
+        Err(CustomError::UnsupportedAlgorithm(alg)) if alg == "MAC-H256"
+    ));
+}
+
+#[test]
+fn verify_with_expected_typ() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+
+    let mut header = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+    header.set_typ(Some("vc+jpt".to_owned()));
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let compact_issued_jwp = JwpIssuedBuilder::new(header, jpt_claims)
+        .unwrap()
+        .build(&bbs_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    let decoded_issued_jwp =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert!(decoded_issued_jwp
+        .verify_with_typ(&public_jwk, "vc+jpt")
+        .is_ok());
+    assert!(decoded_issued_jwp
+        .verify_with_typ(&public_jwk, "VC+JPT")
+        .is_ok());
+    match decoded_issued_jwp.verify_with_typ(&public_jwk, "JPT") {
+        Err(CustomError::TypeMismatch { expected, found }) => {
+            assert_eq!(expected, "JPT");
+            assert_eq!(found.as_deref(), Some("vc+jpt"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    // The default verification does not look at typ
+    assert!(decoded_issued_jwp.verify(&public_jwk).is_ok());
+}
+
+/// Stand-in for an HSM that only signs bytes, using the BBS implementation directly
+fn hsm_sign(jwk: &Jwk, proof_input: ProofInput) -> Vec<u8> {
+    use zkryptium::{
+        bbsplus::keys::{BBSplusPublicKey, BBSplusSecretKey},
+        schemes::{algorithms::BbsBls12381Sha256, generics::Signature},
+    };
+
+    assert_eq!(proof_input.alg, ProofAlgorithm::BBS);
+    let JwkAlgorithmParameters::EllipticCurve(params) = &jwk.key_params else {
+        panic!("expected an EC key");
+    };
+    let sk = BBSplusSecretKey::from_bytes(&base64url_decode(params.d.as_ref().unwrap()).unwrap())
+        .unwrap();
+    let pk = BBSplusPublicKey::from_coordinates(
+        &base64url_decode(&params.x).unwrap().try_into().unwrap(),
+        &base64url_decode(&params.y).unwrap().try_into().unwrap(),
+    )
+    .unwrap();
+    Signature::<BbsBls12381Sha256>::sign(
+        Some(&proof_input.messages),
+        &sk,
+        &pk,
+        Some(&proof_input.header),
+    )
+    .unwrap()
+    .to_bytes()
+    .to_vec()
+}
+
+#[test]
+fn build_with_externally_signed_proof() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+
+    let proof = hsm_sign(&bbs_jwk, builder.proof_input().unwrap());
+    let compact_issued_jwp = builder
+        .build_with_proof(proof)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+}
+
+#[test]
+fn issue_with_verify_only_key() {
+    let mut bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    bbs_jwk.set_key_ops(vec![KeyOps::ProofVerification]);
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+
+    assert!(matches!(
+        builder.build(&bbs_jwk),
+        Err(CustomError::KeyOpsNotPermitted(KeyOps::ProofGeneration))
+    ));
+
+    // The public form of a key allowed to generate proofs is allowed to verify them
+    bbs_jwk.set_key_ops(vec![KeyOps::ProofGeneration]);
+    let compact_issued_jwp = builder
+        .build(&bbs_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    let decoded_issued_jwp =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert!(decoded_issued_jwp
+        .verify(&bbs_jwk.to_public().unwrap())
+        .is_ok());
+    let mut encryption_jwk = bbs_jwk.to_public().unwrap();
+    encryption_jwk.set_pk_use(PKUse::Encryption);
+    assert!(matches!(
+        decoded_issued_jwp.verify(&encryption_jwk),
+        Err(CustomError::KeyOpsNotPermitted(KeyOps::ProofVerification))
+    ));
+}
+
+#[test]
+fn decode_bytes_matches_decode() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+    let compact_issued_jwp = issued_jwp.encode(SerializationType::COMPACT).unwrap();
+
+    let from_bytes =
+        JwpIssuedDecoder::decode_bytes(compact_issued_jwp.as_bytes(), SerializationType::COMPACT)
+            .unwrap();
+    assert_eq!(
+        from_bytes,
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap()
+    );
+    assert!(from_bytes.verify(&public_jwk).is_ok());
+
+    let compact_presented_jwp = issued_jwp
+        .present_all(
+            PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF),
+            &public_jwk,
+        )
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    assert_eq!(
+        JwpPresentedDecoder::decode_bytes(
+            compact_presented_jwp.as_bytes(),
+            SerializationType::COMPACT
+        )
+        .unwrap(),
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT).unwrap()
+    );
+
+    let mut corrupted = compact_issued_jwp.into_bytes();
+    corrupted[2] = 0xff;
+    assert!(matches!(
+        JwpIssuedDecoder::decode_bytes(&corrupted, SerializationType::COMPACT),
+        Err(CustomError::Base64Error(_))
+    ));
+}
+
+#[test]
+fn issuer_from_header_or_claims() {
+    let build = |header_iss: Option<&str>, claim_iss: Option<&str>| {
+        let mut issuer_header = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+        issuer_header.set_iss(header_iss.map(String::from));
+        let mut jpt_claims = JptClaims::new();
+        if let Some(iss) = claim_iss {
+            jpt_claims.set_iss(iss.to_owned());
+        }
+        jpt_claims.set_claim(Some("name"), "John Doe", false);
+        JwpIssuedBuilder::new(issuer_header, jpt_claims)
+            .unwrap()
+            .build_with_proof(vec![0; ProofAlgorithm::BBS.proof_len().unwrap()])
+            .unwrap()
+    };
+
+    let agree = build(
+        Some("https://issuer.example"),
+        Some("https://issuer.example"),
+    );
+    assert_eq!(agree.issuer().as_deref(), Some("https://issuer.example"));
+    assert!(agree.verify_issuer_consistency().is_ok());
+
+    let disagree = build(
+        Some("https://issuer.example"),
+        Some("https://other.example"),
+    );
+    assert_eq!(disagree.issuer().as_deref(), Some("https://issuer.example"));
+    assert!(matches!(
+        disagree.verify_issuer_consistency(),
+        Err(CustomError::IssuerMismatch { header, claim })
+            if header == "https://issuer.example" && claim == "https://other.example"
+    ));
+
+    let header_only = build(Some("https://issuer.example"), None);
+    assert_eq!(
+        header_only.issuer().as_deref(),
+        Some("https://issuer.example")
+    );
+    assert!(header_only.verify_issuer_consistency().is_ok());
+
+    let claims_only = build(None, Some("https://other.example"));
+    assert_eq!(
+        claims_only.issuer().as_deref(),
+        Some("https://other.example")
+    );
+    assert_eq!(build(None, None).issuer(), None);
+}
+
+#[test]
+fn compressed_round_trip() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let compressed = issued_jwp
+        .encode_compressed(SerializationType::COMPACT, Compression::Deflate)
+        .unwrap();
+    let decoder =
+        JwpIssuedDecoder::decode_compressed(&compressed, SerializationType::COMPACT).unwrap();
+    assert_eq!(
+        decoder,
+        JwpIssuedDecoder::decode(
+            &issued_jwp.encode(SerializationType::COMPACT).unwrap(),
+            SerializationType::COMPACT
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        decoder.verify(&bbs_jwk.to_public().unwrap()).unwrap(),
+        issued_jwp
+    );
+
+    let mut unknown = compressed.clone();
+    unknown[0] = 0xff;
+    assert!(matches!(
+        JwpIssuedDecoder::decode_compressed(&unknown, SerializationType::COMPACT),
+        Err(CustomError::DecompressionError(_))
+    ));
+}
+
+#[test]
+fn build_with_mismatched_claims_and_payloads() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+    assert!(builder.validate().is_ok());
+
+    let mut value = serde_json::to_value(&builder).unwrap();
+    value["payloads"].as_array_mut().unwrap().pop();
+    let mismatched: JwpIssuedBuilder = serde_json::from_value(value).unwrap();
+
+    assert!(matches!(
+        mismatched.validate(),
+        Err(CustomError::InvalidIssuedJwp)
+    ));
+    assert!(matches!(
+        mismatched.build(&bbs_jwk),
+        Err(CustomError::InvalidIssuedJwp)
+    ));
+    assert!(matches!(
+        mismatched.build_with_proof(Vec::new()),
+        Err(CustomError::InvalidIssuedJwp)
+    ));
+}
+
+#[test]
+fn verify_only_without_building() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let compact_issued_jwp = issue(&bbs_jwk).encode(SerializationType::COMPACT).unwrap();
+
+    let decoder =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert!(decoder.verify_only(&public_jwk).is_ok());
+
+    let mut parts: Vec<String> = compact_issued_jwp.split('.').map(String::from).collect();
+    parts[1] = ["\"https://other.example\"", "\"John Doe\""]
+        .iter()
+        .map(base64url_encode)
+        .collect::<Vec<_>>()
+        .join("~");
+    let tampered = JwpIssuedDecoder::decode(&parts.join("."), SerializationType::COMPACT).unwrap();
+    assert!(matches!(
+        tampered.verify_only(&public_jwk),
+        Err(CustomError::InvalidIssuedProof)
+    ));
+}
+
+#[test]
+fn explicit_ciphersuite_api_id() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let build = |api_id: &str| {
+        let mut issuer_header = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+        issuer_header.set_api_id(Some(api_id.to_owned()));
+        let mut jpt_claims = JptClaims::new();
+        jpt_claims.set_iss("https://issuer.example".to_owned());
+        JwpIssuedBuilder::new(issuer_header, jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+    };
+
+    let compact_issued_jwp = build("BBS_BLS12381G1_XMD:SHA-256_SSWU_RO_H2G_HM2S_")
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    let decoder =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert!(decoder.verify(&bbs_jwk.to_public().unwrap()).is_ok());
+
+    assert!(matches!(
+        build("BBS_BLS12381G1_XOF:SHAKE-256_SSWU_RO_H2G_HM2S_"),
+        Err(CustomError::CiphersuiteMismatch { alg, .. }) if alg == "BBS"
+    ));
+}
+
+#[test]
+fn build_with_key_of_other_ciphersuite() {
+    assert_eq!(
+        KeyPairSubtype::for_proof_alg(ProofAlgorithm::BBS),
+        Some(KeyPairSubtype::BLS12381G2Sha256)
+    );
+    assert_eq!(
+        KeyPairSubtype::for_proof_alg(ProofAlgorithm::BBS_SHAKE256),
+        Some(KeyPairSubtype::BLS12381G2Shake256)
+    );
+    assert_eq!(
+        KeyPairSubtype::for_proof_alg(ProofAlgorithm::MAC_H256),
+        None
+    );
+
+    // Generated keys are not tied to a ciphersuite until their `alg` is set
+    let mut shake_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Shake256).unwrap();
+    assert_eq!(shake_jwk.alg, None);
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    let builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+    assert!(builder.build(&shake_jwk).is_ok());
+
+    shake_jwk.set_alg(Algorithm::Proof(ProofAlgorithm::BBS_SHAKE256));
+    assert!(matches!(
+        builder.build(&shake_jwk),
+        Err(CustomError::IncompatibleKeyAlgorithm { alg, .. }) if alg == "BBS"
+    ));
+}
+
+#[test]
+fn decode_with_illegal_payload_character() {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let compact_issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build_with_proof(vec![0; ProofAlgorithm::BBS.proof_len().unwrap()])
+            .unwrap()
+            .encode(SerializationType::COMPACT)
+            .unwrap();
+    assert!(JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).is_ok());
+
+    let parts: Vec<&str> = compact_issued_jwp.split('.').collect();
+    for payloads in [
+        format!("{}!", parts[1]),
+        format!("{}~", parts[1]),
+        parts[1].replacen('~', "~~", 1),
+    ] {
+        let jpt = [parts[0], &payloads, parts[2]].join(".");
+        assert!(matches!(
+            JwpIssuedDecoder::decode(&jpt, SerializationType::COMPACT),
+            Err(CustomError::InvalidIssuedJwp)
+        ));
+    }
+}
+
+#[test]
+fn verify_header_with_unknown_member() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+
+    // The issuer signed a member the header type does not know: it is kept in the decoded header
+    let header_oct = br#"{"alg":"BBS","claims":["name"],"ext":"value"}"#;
+    let payloads = Payloads::new_from_values(vec![serde_json::json!("John Doe")]);
+    let proof = BBSplusAlgorithm::generate_issuer_proof(
+        ProofAlgorithm::BBS,
+        &payloads,
+        &bbs_jwk,
+        header_oct,
+    )
+    .unwrap();
+    let compact_issued_jwp = format!(
+        "{}.{}.{}",
+        base64url_encode(header_oct),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode(&proof)
+    );
+
+    let decoder =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    let issued_jwp = decoder.verify(&public_jwk).unwrap();
+    assert_eq!(
+        issued_jwp.get_issuer_protected_header().extra().get("ext"),
+        Some(&serde_json::json!("value"))
+    );
+    assert_eq!(
+        issued_jwp
+            .get_issuer_protected_header()
+            .canonical_bytes()
+            .unwrap(),
+        header_oct
+    );
+
+    let mut presentation_header =
+        PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF);
+    presentation_header.set_nonce(Some("nonce".to_owned()));
+    let compact_presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(presentation_header)
+        .build(&public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+}
+
+#[test]
+fn verify_header_with_critical_parameter() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let header = IssuerProtectedHeader::from_slice_strict(
+        br#"{"typ":"JPT","alg":"BBS","crit":["ext"],"ext":"value"}"#,
+    )
+    .unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let compact_issued_jwp = JwpIssuedBuilder::new(header, jpt_claims)
+        .unwrap()
+        .build(&bbs_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    // The strict decoding of the signed header keeps the critical parameter
+    let header_oct = base64url_decode(compact_issued_jwp.split('.').next().unwrap()).unwrap();
+    let decoded_header = IssuerProtectedHeader::from_slice_strict(&header_oct).unwrap();
+    assert_eq!(
+        decoded_header.extra().get("crit"),
+        Some(&serde_json::json!(["ext"]))
+    );
+    assert_eq!(
+        decoded_header.extra().get("ext"),
+        Some(&serde_json::json!("value"))
+    );
+
+    let issued_jwp = JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+    assert_eq!(issued_jwp.get_issuer_protected_header(), &decoded_header);
+}
+
+#[test]
+fn verify_header_with_unknown_proof_key_member() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+
+    // Members unknown to the JWK of the `proof_key` are dropped by its re-serialization
+    let mut proof_key = serde_json::to_value(&public_jwk).unwrap();
+    proof_key["ext"] = serde_json::json!("value");
+    let header_oct = serde_json::to_vec(&serde_json::json!({
+        "alg": "BBS",
+        "claims": ["name"],
+        "proof_key": proof_key,
+    }))
+    .unwrap();
+    let payloads = Payloads::new_from_values(vec![serde_json::json!("John Doe")]);
+    let proof = BBSplusAlgorithm::generate_issuer_proof(
+        ProofAlgorithm::BBS,
+        &payloads,
+        &bbs_jwk,
+        &header_oct,
+    )
+    .unwrap();
+    let compact_issued_jwp = format!(
+        "{}.{}.{}",
+        base64url_encode(&header_oct),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode(&proof)
+    );
+
+    let decoder =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert!(matches!(
+        decoder.verify(&public_jwk),
+        Err(CustomError::HeaderReserializationMismatch(member)) if member == "proof_key"
+    ));
+}
+
+#[test]
+fn redacted_for_display_does_not_verify() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let redacted = issued_jwp.redact_for_display(&["name", "unknown"]);
+    let name_index = redacted
+        .disclosable_paths()
+        .iter()
+        .position(|c| c == "name")
+        .unwrap();
+    assert_eq!(
+        redacted.get_payloads().get(name_index).unwrap().0,
+        JwpIssued::REDACTED_PLACEHOLDER
+    );
+    assert_eq!(redacted.get_claims(), issued_jwp.get_claims());
+    assert_eq!(redacted.get_proof(), issued_jwp.get_proof());
+
+    let compact_redacted = redacted.encode(SerializationType::COMPACT).unwrap();
+    assert!(matches!(
+        JwpIssuedDecoder::decode(&compact_redacted, SerializationType::COMPACT)
+            .unwrap()
+            .verify(&public_jwk),
+        Err(CustomError::InvalidIssuedProof)
+    ));
+}
+
+#[test]
+fn decode_padded_segments() {
+    assert_eq!(base64url_decode_lenient("TWE=").unwrap(), b"Ma");
+    assert_eq!(base64url_decode_lenient("TWE").unwrap(), b"Ma");
+    assert!(base64url_decode("TWE=").is_err());
+    assert!(base64url_decode_lenient("TWE==").is_err());
+
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let compact_issued_jwp = issue(&bbs_jwk).encode(SerializationType::COMPACT).unwrap();
+
+    let pad = |segment: &str| format!("{segment}{}", "=".repeat((4 - segment.len() % 4) % 4));
+    let parts: Vec<&str> = compact_issued_jwp.split('.').collect();
+    let payloads: Vec<String> = parts[1].split('~').map(pad).collect();
+    let padded = [pad(parts[0]), payloads.join("~"), pad(parts[2])].join(".");
+    assert_ne!(padded, compact_issued_jwp);
+
+    let issued_jwp = JwpIssuedDecoder::decode(&padded, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+    assert_eq!(
+        issued_jwp.encode(SerializationType::COMPACT).unwrap(),
+        compact_issued_jwp
+    );
+}
+
+#[test]
+fn issue_with_explicit_claim_order() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    jpt_claims.set_claim(Some("age"), 42, false);
+    let mut builder =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims).unwrap();
+
+    for order in [
+        &["age", "name"][..],
+        &["age", "name", "email"],
+        &["age", "name", "name"],
+    ] {
+        assert!(matches!(
+            builder.with_claim_order(order),
+            Err(CustomError::ClaimOrderMismatch)
+        ));
+    }
+
+    let issued_jwp = builder
+        .with_claim_order(&["age", "name", "iss"])
+        .unwrap()
+        .build(&bbs_jwk)
+        .unwrap();
+    assert_eq!(issued_jwp.disclosable_paths(), ["age", "name", "iss"]);
+    assert_eq!(
+        issued_jwp.get_payloads().get_values(),
+        [
+            serde_json::json!(42),
+            serde_json::json!("John Doe"),
+            serde_json::json!("https://issuer.example")
+        ]
+    );
+
+    let compact_issued_jwp = issued_jwp.encode(SerializationType::COMPACT).unwrap();
+    JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+}
+
+#[test]
+fn build_without_claims_when_required() {
+    use jsonprooftoken::errors::IncompleteJwpBuild;
+
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let mut builder = JwpIssuedBuilder::new(
+        IssuerProtectedHeader::new(ProofAlgorithm::BBS),
+        JptClaims::new(),
+    )
+    .unwrap();
+    assert!(builder.get_payloads().unwrap().is_empty());
+    assert!(builder.build(&bbs_jwk).is_ok());
+
+    builder.require_claims();
+    assert!(matches!(
+        builder.build(&bbs_jwk),
+        Err(CustomError::IncompleteJwpBuild(
+            IncompleteJwpBuild::NoClaimsAndPayloads
+        ))
+    ));
+    assert!(matches!(
+        builder.build_with_proof(Vec::new()),
+        Err(CustomError::IncompleteJwpBuild(
+            IncompleteJwpBuild::NoClaimsAndPayloads
+        ))
+    ));
+}
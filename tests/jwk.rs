@@ -0,0 +1,315 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(feature = "std")]
+use jsonprooftoken::encoding::base64url_decode;
+use jsonprooftoken::{
+    errors::CustomError,
+    jpa::algs::{PresentationProofAlgorithm, ProofAlgorithm},
+    jwk::{
+        alg_parameters::{Algorithm, JwkAlgorithmParameters, JwkOctetKeyPairParameters},
+        curves::EllipticCurveTypes,
+        key::Jwk,
+        set::JwkSet,
+        types::AlgorithmFamily,
+    },
+};
+
+const PRIVATE_KEY_PEM: &str = "-----BEGIN BLS12381G2 PRIVATE KEY-----
+MIHsAgEBBCApqidcLLYeTXWplch1kPNlcdCQs1Gj6JHq+K5yTTwoX6GBxAOBwQAD
+yM0EMbZNETBTpXvemonEgkLkiCVQvsJe9FBAow0crXhiI5faeUiQRTPfVpMGZ0wH
+MnEEYLROnxcX1wiNfaSYs3QHPwaxlI7MsghMw3uMbspZFcXH+db/P8E52kN7GS0N
+DHGKOMYLO4sJPY2xC0lbccsERcvFXC6KS4vybtmDPQalj7t+Nyue8GA5xLET0o0N
+xHIfFnbK9irJsbvyTzX+m/WFYAfggcGK3U4J9nFv3g4O+c/myDP3Qa12Chsg2Rs=
+-----END BLS12381G2 PRIVATE KEY-----
+";
+
+const PUBLIC_KEY_PEM: &str = "-----BEGIN BLS12381G2 PUBLIC KEY-----
+MIHEA4HBAAPIzQQxtk0RMFOle96aicSCQuSIJVC+wl70UECjDRyteGIjl9p5SJBF
+M99WkwZnTAcycQRgtE6fFxfXCI19pJizdAc/BrGUjsyyCEzDe4xuylkVxcf51v8/
+wTnaQ3sZLQ0McYo4xgs7iwk9jbELSVtxywRFy8VcLopLi/Ju2YM9BqWPu343K57w
+YDnEsRPSjQ3Ech8Wdsr2Ksmxu/JPNf6b9YVgB+CBwYrdTgn2cW/eDg75z+bIM/dB
+rXYKGyDZGw==
+-----END BLS12381G2 PUBLIC KEY-----
+";
+
+#[test]
+fn pem_round_trip() {
+    let vector: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/bbs_sha256.json")).unwrap();
+    let expected: Jwk = serde_json::from_value(vector["jwk"].clone()).unwrap();
+
+    let jwk = Jwk::from_pem(PRIVATE_KEY_PEM).unwrap();
+    assert_eq!(jwk, expected);
+    assert_eq!(jwk.to_pem().unwrap(), PRIVATE_KEY_PEM);
+
+    let public_jwk = Jwk::from_pem(PUBLIC_KEY_PEM).unwrap();
+    assert!(public_jwk.is_public());
+    assert_eq!(public_jwk.to_pem().unwrap(), PUBLIC_KEY_PEM);
+    assert_eq!(jwk.to_public().unwrap().to_pem().unwrap(), PUBLIC_KEY_PEM);
+}
+
+#[test]
+fn from_pem_with_unknown_label() {
+    let pem = PUBLIC_KEY_PEM.replace("BLS12381G2 PUBLIC KEY", "PUBLIC KEY");
+
+    assert!(matches!(Jwk::from_pem(&pem), Err(CustomError::InvalidPem)));
+}
+
+#[test]
+fn algorithm_family_of_bls12381g2_okp_key() {
+    let jwk = Jwk::from_key_params(JwkAlgorithmParameters::OctetKeyPair(
+        JwkOctetKeyPairParameters::new(EllipticCurveTypes::BLS12381G2, &[0; 96], None),
+    ));
+
+    assert_eq!(jwk.algorithm_family(), Some(AlgorithmFamily::Bls12381));
+    assert!(jwk.supports_proof_alg(ProofAlgorithm::BBS));
+    assert!(jwk.supports_presentation_alg(PresentationProofAlgorithm::BBS_SHAKE256_PROOF));
+    assert!(!jwk.supports_proof_alg(ProofAlgorithm::SU_ES256));
+}
+
+#[test]
+fn algorithm_family_of_inconsistent_key() {
+    let jwk = Jwk::from_key_params(JwkAlgorithmParameters::OctetKeyPair(
+        JwkOctetKeyPairParameters::new(EllipticCurveTypes::P256, &[0; 32], None),
+    ));
+
+    assert_eq!(jwk.algorithm_family(), None);
+    assert!(!jwk.supports_proof_alg(ProofAlgorithm::BBS));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn okp_ec_round_trip() {
+    let vector: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/bbs_sha256.json")).unwrap();
+    let ec_jwk: Jwk = serde_json::from_value(vector["jwk"].clone()).unwrap();
+
+    let okp_jwk = ec_jwk.to_public().unwrap().to_okp_form().unwrap();
+    let JwkAlgorithmParameters::OctetKeyPair(okp_params) = &okp_jwk.key_params else {
+        panic!("expected an OKP key");
+    };
+    assert_eq!(base64url_decode(&okp_params.x).unwrap().len(), 96);
+
+    let round_trip = okp_jwk.to_ec_form().unwrap().to_okp_form().unwrap();
+    assert_eq!(round_trip, okp_jwk);
+
+    let ec_round_trip = ec_jwk.to_okp_form().unwrap().to_ec_form().unwrap();
+    assert_eq!(ec_round_trip, ec_jwk);
+}
+
+#[test]
+fn same_key_ignores_metadata() {
+    let private_jwk = Jwk::from_pem(PRIVATE_KEY_PEM).unwrap();
+    let public_jwk = Jwk::from_pem(PUBLIC_KEY_PEM).unwrap();
+
+    let mut rotated = public_jwk.clone();
+    rotated.set_kid("key-2");
+    rotated.set_alg(Algorithm::Proof(ProofAlgorithm::BBS));
+    assert_ne!(rotated, public_jwk);
+    assert!(rotated.same_key(&public_jwk));
+    assert!(rotated.matches_public(&public_jwk));
+
+    assert!(!private_jwk.same_key(&public_jwk));
+    assert!(private_jwk.matches_public(&public_jwk));
+    assert!(public_jwk.matches_public(&private_jwk));
+    assert!(private_jwk.same_key(&private_jwk.clone()));
+
+    let other_jwk = Jwk::from_key_params(JwkAlgorithmParameters::OctetKeyPair(
+        JwkOctetKeyPairParameters::new(EllipticCurveTypes::BLS12381G2, [0u8; 96], None),
+    ));
+    assert!(!other_jwk.same_key(&public_jwk));
+    assert!(!other_jwk.matches_public(&private_jwk));
+}
+
+#[test]
+fn lenient_jwk_set_skips_unsupported_keys() {
+    let vector: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/bbs_sha256.json")).unwrap();
+    let mut bls_key = vector["jwk"].clone();
+    bls_key["kid"] = "bls-key".into();
+    let rsa_key = serde_json::json!({
+        "kty": "RSA",
+        "kid": "rsa-key",
+        "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+        "e": "AQAB"
+    });
+    let json = serde_json::json!({ "keys": [rsa_key, bls_key] }).to_string();
+
+    assert!(matches!(
+        JwkSet::from_json(&json),
+        Err(CustomError::JsonError(_))
+    ));
+
+    let (set, skipped) = JwkSet::from_json_lenient(&json);
+    assert_eq!(set.keys.len(), 1);
+    assert!(set.find("bls-key").is_some());
+    assert_eq!(skipped.len(), 1);
+    assert!(skipped[0].contains("rsa-key"));
+}
+
+#[test]
+fn jwk_set_json_round_trip() {
+    let vector: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/bbs_sha256.json")).unwrap();
+    let mut bls_key = vector["jwk"].clone();
+    bls_key["kid"] = "bls-key".into();
+    let json = serde_json::json!({ "keys": [bls_key] }).to_string();
+
+    let set = JwkSet::from_json(&json).unwrap();
+    assert!(set.find("bls-key").is_some());
+    assert_eq!(JwkSet::from_json(&set.to_json().unwrap()).unwrap(), set);
+
+    assert!(matches!(
+        JwkSet::from_json(r#"{"keys": "#),
+        Err(CustomError::JsonError(_))
+    ));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn from_raw_bls_key_bytes() {
+    use jsonprooftoken::{
+        encoding::SerializationType,
+        jpt::claims::JptClaims,
+        jwp::{
+            header::IssuerProtectedHeader,
+            issued::{JwpIssuedBuilder, JwpIssuedDecoder},
+        },
+    };
+    use zkryptium::{keys::pair::KeyPair, schemes::algorithms::BbsBls12381Sha256};
+
+    let keypair = KeyPair::<BbsBls12381Sha256>::random().unwrap();
+    let sk = keypair.private_key().to_bytes();
+    let pk = keypair.public_key().to_bytes();
+
+    let jwk = Jwk::from_bls_keypair_bytes(&sk, &pk).unwrap();
+    let public_jwk = Jwk::from_bls_public_bytes(&pk).unwrap();
+    assert!(jwk.is_private());
+    assert!(public_jwk.is_public());
+    assert!(jwk.matches_public(&public_jwk));
+
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    let compact_issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&jwk)
+            .unwrap()
+            .encode(SerializationType::COMPACT)
+            .unwrap();
+    assert!(
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT)
+            .unwrap()
+            .verify(&public_jwk)
+            .is_ok()
+    );
+
+    let other_pk = KeyPair::<BbsBls12381Sha256>::random()
+        .unwrap()
+        .public_key()
+        .to_bytes();
+    assert!(matches!(
+        Jwk::from_bls_keypair_bytes(&sk, &other_pk),
+        Err(CustomError::InvalidJwk)
+    ));
+    assert!(matches!(
+        Jwk::from_bls_public_bytes(&pk[1..]),
+        Err(CustomError::InvalidJwk)
+    ));
+}
+
+/// DER element with a length of up to 65535 bytes
+#[cfg(feature = "std")]
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    match content.len() {
+        len @ 0..=0x7f => element.push(len as u8),
+        len @ 0x80..=0xff => element.extend([0x81, len as u8]),
+        len => element.extend([0x82, (len >> 8) as u8, len as u8]),
+    }
+    element.extend_from_slice(content);
+    element
+}
+
+/// Base64 of a minimal (unsigned) X.509 certificate for `public_key`
+#[cfg(feature = "std")]
+fn certificate(public_key: &[u8]) -> String {
+    let algorithm = der(0x30, &der(0x06, &[0x2b, 0x06, 0x01, 0x04, 0x01]));
+    let name = der(0x30, &der(0x31, &der(0x30, &[])));
+    let mut subject_public_key = vec![0];
+    subject_public_key.extend_from_slice(public_key);
+
+    let tbs_certificate = der(
+        0x30,
+        &[
+            der(0xa0, &der(0x02, &[2])),
+            der(0x02, &[1]),
+            algorithm.clone(),
+            name.clone(),
+            der(0x30, &[]),
+            name,
+            der(
+                0x30,
+                &[algorithm.clone(), der(0x03, &subject_public_key)].concat(),
+            ),
+        ]
+        .concat(),
+    );
+    data_encoding::BASE64.encode(&der(
+        0x30,
+        &[tbs_certificate, algorithm, der(0x03, &[0])].concat(),
+    ))
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn x5c_binding() {
+    use jsonprooftoken::jwk::types::KeyPairSubtype;
+
+    let mut jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256)
+        .unwrap()
+        .to_public()
+        .unwrap();
+    let JwkAlgorithmParameters::OctetKeyPair(okp_params) = &jwk.to_okp_form().unwrap().key_params
+    else {
+        panic!("expected an OKP key");
+    };
+    let compressed = base64url_decode(&okp_params.x).unwrap();
+    assert!(jwk.x5c_leaf_der().is_none());
+
+    let leaf = certificate(&compressed);
+    jwk.set_x5c(vec![&leaf]);
+    assert_eq!(
+        jwk.x5c_leaf_der().unwrap(),
+        data_encoding::BASE64.decode(leaf.as_bytes()).unwrap()
+    );
+    assert!(jwk.verify_x5c_binding().is_ok());
+    assert!(jwk.to_okp_form().unwrap().verify_x5c_binding().is_ok());
+
+    let other_key = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256)
+        .unwrap()
+        .to_okp_form()
+        .unwrap();
+    let JwkAlgorithmParameters::OctetKeyPair(other_params) = &other_key.key_params else {
+        panic!("expected an OKP key");
+    };
+    jwk.set_x5c(vec![&certificate(
+        &base64url_decode(&other_params.x).unwrap(),
+    )]);
+    assert!(matches!(
+        jwk.verify_x5c_binding(),
+        Err(CustomError::X5cKeyMismatch)
+    ));
+}
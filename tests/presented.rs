@@ -0,0 +1,1114 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "std")]
+
+use jsonprooftoken::{
+    encoding::{
+        base64url_decode, base64url_encode, base64url_encode_serializable, SerializationType,
+    },
+    errors::CustomError,
+    jpa::{
+        algs::{PresentationProofAlgorithm, ProofAlgorithm},
+        bbs_plus::BBSplusAlgorithm,
+    },
+    jpt::claims::JptClaims,
+    jwk::{
+        key::{Jwk, KeyOps},
+        types::KeyPairSubtype,
+    },
+    jwp::{
+        claims_withheld,
+        combined::JwpCombinedPresentedDecoder,
+        header::{IssuerProtectedHeader, Predicate, PresentationProtectedHeader},
+        issued::{JwpIssued, JwpIssuedBuilder, JwpIssuedDecoder},
+        presented::{DisclosureSummary, JwpPresentedBuilder, JwpPresentedDecoder},
+    },
+};
+
+fn issue(jwk: &Jwk) -> JwpIssued {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(
+        Some("vc"),
+        serde_json::json!({"name": "John Doe", "age": 42}),
+        true,
+    );
+
+    JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+        .unwrap()
+        .build(jwk)
+        .unwrap()
+}
+
+fn presentation_header(nonce: &str) -> PresentationProtectedHeader {
+    let mut header = PresentationProtectedHeader::new(PresentationProofAlgorithm::BBS_PROOF);
+    header.set_aud(Some("https://recipient.example.com".to_owned()));
+    header.set_nonce(Some(nonce.to_owned()));
+    header
+}
+
+fn present(issued_jwp: &JwpIssued, jwk: &Jwk, nonce: &str) -> String {
+    JwpPresentedBuilder::new(issued_jwp)
+        .set_presentation_protected_header(presentation_header(nonce))
+        .set_undisclosed("vc.age")
+        .unwrap()
+        .build(jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap()
+}
+
+#[test]
+fn verify_batch_with_tampered_jwp() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let valid = present(&issued_jwp, &public_jwk, "nonce-1");
+    let mut parts: Vec<String> = present(&issued_jwp, &public_jwk, "nonce-2")
+        .split('.')
+        .map(str::to_owned)
+        .collect();
+    parts[1] = base64url_encode_serializable(presentation_header("nonce-3"));
+    let tampered = parts.join(".");
+
+    let items: Vec<JwpPresentedDecoder> = [&valid, &tampered, &valid]
+        .iter()
+        .map(|jpt| JwpPresentedDecoder::decode(jpt, SerializationType::COMPACT).unwrap())
+        .collect();
+
+    let results = JwpPresentedDecoder::verify_batch(&items, &public_jwk);
+
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+}
+
+#[test]
+fn encoded_len_matches_encode() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .set_undisclosed("vc.age")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap();
+
+    assert_eq!(
+        presented_jwp
+            .encoded_len(SerializationType::COMPACT)
+            .unwrap(),
+        presented_jwp
+            .encode(SerializationType::COMPACT)
+            .unwrap()
+            .len()
+    );
+}
+
+#[test]
+fn disclosed_indexes_after_set_undisclosed() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    assert_eq!(builder.disclosed_indexes(), vec![0, 1, 2]);
+
+    builder
+        .set_undisclosed("iss")
+        .unwrap()
+        .set_undisclosed("vc.age")
+        .unwrap();
+    assert_eq!(builder.disclosed_indexes(), vec![1]);
+}
+
+#[test]
+fn present_all_discloses_every_claim() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let presented_jwp = issued_jwp
+        .present_all(presentation_header("nonce"), &public_jwk)
+        .unwrap();
+
+    assert_eq!(presented_jwp.get_claims(), issued_jwp.get_claims());
+    assert_eq!(presented_jwp.get_payloads(), issued_jwp.get_payloads());
+
+    let compact_presented_jwp = presented_jwp.encode(SerializationType::COMPACT).unwrap();
+    JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+}
+
+#[test]
+fn verify_truncated_proof() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let compact_presented_jwp = present(&issue(&bbs_jwk), &public_jwk, "nonce");
+
+    let (rest, encoded_proof) = compact_presented_jwp.rsplit_once('.').unwrap();
+    let proof = base64url_decode(encoded_proof).unwrap();
+    let truncated = format!("{}.{}", rest, base64url_encode(&proof[..100]));
+
+    let result = JwpPresentedDecoder::decode(&truncated, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk);
+
+    match result {
+        Err(CustomError::InvalidProofLength { expected, got }) => {
+            assert_eq!(expected, proof.len());
+            assert_eq!(got, 100);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn holder_binding_with_tampered_presentation_header() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let holder_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let compact_presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .set_holder_key(holder_jwk.clone())
+        .build(&public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    let decoded_jwp =
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT).unwrap();
+    assert_eq!(
+        decoded_jwp.get_presentation_header().presentation_key(),
+        holder_jwk.to_public().as_ref()
+    );
+    decoded_jwp.verify(&public_jwk).unwrap();
+
+    let mut parts: Vec<String> = compact_presented_jwp
+        .split('.')
+        .map(str::to_owned)
+        .collect();
+    let mut header: serde_json::Value =
+        serde_json::from_slice(&base64url_decode(&parts[1]).unwrap()).unwrap();
+    header["nonce"] = "another-nonce".into();
+    parts[1] = base64url_encode_serializable(&header);
+    let tampered = parts.join(".");
+
+    let result = JwpPresentedDecoder::decode(&tampered, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk);
+
+    assert!(matches!(result, Err(CustomError::HolderBindingError)));
+}
+
+#[test]
+fn disclosure_report_of_selective_disclosure_example() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(
+        Some("vc"),
+        serde_json::json!({
+            "degree": {
+                "type": "BachelorDegree",
+                "name": "Bachelor of Science and Arts",
+                "ciao": [
+                    {"u1": "value1"},
+                    {"u2": "value2"}
+                ]
+            },
+            "name": "John Doe"
+        }),
+        true,
+    );
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap();
+
+    let presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .set_undisclosed("vc.degree.name")
+        .unwrap()
+        .set_undisclosed("vc.degree.ciao[0].u1")
+        .unwrap()
+        .set_undisclosed("vc.name")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap();
+
+    let report = presented_jwp.disclosure_report();
+    assert_eq!(
+        serde_json::to_value(&report).unwrap(),
+        serde_json::json!({
+            "disclosed": {
+                "iss": "https://issuer.example",
+                "vc.degree.type": "BachelorDegree",
+                "vc.degree.ciao[1].u2": "value2"
+            },
+            "undisclosed": ["vc.degree.name", "vc.degree.ciao[0].u1", "vc.name"]
+        })
+    );
+}
+
+#[test]
+fn predicate_proves_age_over_21_in_the_header() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    jpt_claims.set_claim(Some("age"), 42, false);
+    jpt_claims.set_claim(Some("age_over_21"), true, false);
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap();
+
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    builder
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .set_undisclosed("name")
+        .unwrap()
+        .set_undisclosed("age")
+        .unwrap();
+    assert!(matches!(
+        builder.set_predicate("age_over_21", Predicate::Eq(serde_json::json!(false))),
+        Err(CustomError::PredicateNotSatisfied(claim)) if claim == "age_over_21"
+    ));
+    let presented_jwp = builder
+        .set_predicate("age_over_21", Predicate::Eq(serde_json::json!(true)))
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap();
+    let compact_presented_jwp = presented_jwp.encode(SerializationType::COMPACT).unwrap();
+    assert_eq!(
+        presented_jwp
+            .encoded_len(SerializationType::COMPACT)
+            .unwrap(),
+        compact_presented_jwp.len()
+    );
+
+    // No payload is included in the presentation, the value is in the header
+    assert_eq!(compact_presented_jwp.split('.').nth(2), Some("~~"));
+
+    let decoded_jwp =
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT).unwrap();
+    decoded_jwp.verify(&public_jwk).unwrap();
+
+    // The proof does not verify against a different constant
+    let mut parts: Vec<String> = compact_presented_jwp.split('.').map(String::from).collect();
+    let mut header: serde_json::Value =
+        serde_json::from_slice(&base64url_decode(&parts[1]).unwrap()).unwrap();
+    header["predicates"]["age_over_21"]["eq"] = serde_json::json!(false);
+    parts[1] = base64url_encode_serializable(&header);
+    let tampered = parts.join(".");
+
+    let result = JwpPresentedDecoder::decode(&tampered, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk);
+    assert!(result.is_err());
+}
+
+#[test]
+fn predicate_compares_numbers_by_value() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("level"), 1, false);
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap();
+
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    builder.set_presentation_protected_header(presentation_header("nonce"));
+    assert!(Predicate::Eq(serde_json::json!(1.0)).holds(&serde_json::json!(1)));
+    assert!(!Predicate::Eq(serde_json::json!(1.5)).holds(&serde_json::json!(1)));
+    let presented_jwp = builder
+        .set_predicate("level", Predicate::Eq(serde_json::json!(1.0)))
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap();
+
+    // The header carries the value as signed, so the proof verifies
+    assert_eq!(
+        presented_jwp
+            .get_presentation_protected_header()
+            .predicates()
+            .unwrap()
+            .get("level"),
+        Some(&Predicate::Eq(serde_json::json!(1)))
+    );
+    let compact_presented_jwp = presented_jwp.encode(SerializationType::COMPACT).unwrap();
+    JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk)
+        .unwrap();
+}
+
+#[test]
+fn build_checked_summary_counts() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    builder.set_presentation_protected_header(presentation_header("nonce"));
+    assert!(matches!(
+        builder.build_checked(&public_jwk, true),
+        Err(CustomError::OverDisclosure)
+    ));
+    let (_, summary) = builder.build_checked(&public_jwk, false).unwrap();
+    assert_eq!(
+        summary,
+        DisclosureSummary {
+            disclosed: 3,
+            undisclosed: 0,
+            predicates: 0
+        }
+    );
+
+    builder.set_undisclosed("vc.age").unwrap();
+    let (presented_jwp, summary) = builder.build_checked(&public_jwk, true).unwrap();
+    assert_eq!(
+        summary,
+        DisclosureSummary {
+            disclosed: 2,
+            undisclosed: 1,
+            predicates: 0
+        }
+    );
+    assert_eq!(summary.total(), 3);
+    assert_eq!(
+        presented_jwp.disclosure_report().undisclosed,
+        vec!["vc.age"]
+    );
+}
+
+#[test]
+fn verify_with_undisclosed_payload_filled_in() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let compact_presented_jwp = present(&issue(&bbs_jwk), &public_jwk, "nonce");
+
+    let mut parts: Vec<String> = compact_presented_jwp.split('.').map(String::from).collect();
+    let mut payloads: Vec<String> = parts[2].split('~').map(String::from).collect();
+    let undisclosed = payloads.iter().position(|p| p.is_empty()).unwrap();
+    payloads[undisclosed] = base64url_encode_serializable(42);
+    parts[2] = payloads.join("~");
+    let corrupted = parts.join(".");
+
+    let result = JwpPresentedDecoder::decode(&corrupted, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk);
+
+    assert!(matches!(result, Err(CustomError::InvalidPresentedJwp)));
+}
+
+#[test]
+fn rotate_nonce_before_build() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    assert!(matches!(
+        builder.with_nonce("fresh-nonce"),
+        Err(CustomError::IncompleteJwpBuild(_))
+    ));
+
+    let presented_jwp = builder
+        .set_presentation_protected_header(presentation_header("stale-nonce"))
+        .with_nonce("fresh-nonce")
+        .unwrap()
+        .with_aud("https://other-recipient.example.com")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap();
+
+    let header = presented_jwp.get_presentation_protected_header();
+    assert_eq!(header.nonce().map(String::as_str), Some("fresh-nonce"));
+    assert_eq!(
+        header.aud().map(String::as_str),
+        Some("https://other-recipient.example.com")
+    );
+}
+
+#[test]
+fn verify_then_present() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let compact_issued_jwp = issue(&bbs_jwk).encode(SerializationType::COMPACT).unwrap();
+    let decoder =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+
+    let other_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    assert!(decoder
+        .verify_into_presentation_builder(&other_jwk.to_public().unwrap())
+        .is_err());
+
+    let compact_presented_jwp = decoder
+        .verify_into_presentation_builder(&public_jwk)
+        .unwrap()
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .set_undisclosed("vc.age")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    let presented_jwp =
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+            .unwrap()
+            .verify(&public_jwk)
+            .unwrap();
+    assert_eq!(
+        presented_jwp.disclosure_report().undisclosed,
+        vec!["vc.age"]
+    );
+}
+
+#[test]
+fn build_with_mismatched_presentation_algorithm() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let result = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(PresentationProtectedHeader::new(
+            PresentationProofAlgorithm::MAC_H256,
+        ))
+        .build(&public_jwk);
+
+    match result {
+        Err(CustomError::AlgorithmMismatch {
+            issuer,
+            presentation,
+        }) => {
+            assert_eq!(issuer, "BBS");
+            assert_eq!(presentation, "MAC-H256");
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn hide_subtree_by_prefix() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(
+        Some("vc"),
+        serde_json::json!({
+            "degree": {
+                "type": "BachelorDegree",
+                "ciao": [{"u1": "value1"}, {"u2": "value2"}]
+            },
+            "degree_year": 2020,
+            "name": "John Doe"
+        }),
+        true,
+    );
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap();
+
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    builder.set_presentation_protected_header(presentation_header("nonce"));
+    assert!(matches!(
+        builder.set_undisclosed_subtree("vc.deg"),
+        Err(CustomError::SelectiveDisclosureError)
+    ));
+    assert_eq!(builder.set_undisclosed_subtree("vc.degree").unwrap(), 3);
+
+    let report = builder.build(&public_jwk).unwrap().disclosure_report();
+    assert_eq!(
+        report.undisclosed,
+        vec![
+            "vc.degree.type",
+            "vc.degree.ciao[0].u1",
+            "vc.degree.ciao[1].u2"
+        ]
+    );
+    assert_eq!(
+        report.disclosed.keys().collect::<Vec<_>>(),
+        vec!["vc.degree_year", "vc.name"]
+    );
+}
+
+#[test]
+fn verify_unsupported_algorithm() {
+    let public_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256)
+        .unwrap()
+        .to_public()
+        .unwrap();
+    let compact_presented_jwp = format!(
+        "{}.{}.{}.{}",
+        base64url_encode(br#"{"typ":"JPT","alg":"MAC-H256","claims":["name"]}"#),
+        base64url_encode(br#"{"alg":"MAC-H256"}"#),
+        base64url_encode(br#""John Doe""#),
+        base64url_encode([0u8; 32])
+    );
+
+    let decoded_presented_jwp =
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT).unwrap();
+    assert!(matches!(
+        decoded_presented_jwp.verify(&public_jwk),
+        Err(CustomError::UnsupportedAlgorithm(alg)) if alg == "MAC-H256"
+    ));
+}
+
+#[test]
+fn semantically_eq_ignores_proof() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let verify = |compact_presented_jwp: &str| {
+        JwpPresentedDecoder::decode(compact_presented_jwp, SerializationType::COMPACT)
+            .unwrap()
+            .verify(&public_jwk)
+            .unwrap()
+    };
+    let first = verify(&present(&issued_jwp, &public_jwk, "nonce-1"));
+    let second = verify(&present(&issued_jwp, &public_jwk, "nonce-1"));
+    assert_ne!(first, second);
+    assert!(first.semantically_eq(&second));
+
+    let other_nonce = verify(&present(&issued_jwp, &public_jwk, "nonce-2"));
+    assert!(!first.semantically_eq(&other_nonce));
+}
+
+#[test]
+fn encode_to_writer_matches_encode() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let presented_jwp = JwpPresentedBuilder::new(&issue(&bbs_jwk))
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .set_undisclosed("vc.age")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap();
+
+    let mut writer: Vec<u8> = Vec::new();
+    presented_jwp
+        .encode_to(&mut writer, SerializationType::COMPACT)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(writer).unwrap(),
+        presented_jwp.encode(SerializationType::COMPACT).unwrap()
+    );
+}
+
+#[test]
+fn issuer_kid_cannot_be_withheld() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let mut header = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+    header.set_kid(Some("issuer-key-1".to_owned()));
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    let issued_jwp = JwpIssuedBuilder::new(header, jpt_claims)
+        .unwrap()
+        .build(&bbs_jwk)
+        .unwrap();
+    let compact_presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .build(&public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    // The issuer header is the header input of the proof: without its `kid` the proof does not verify
+    let mut parts: Vec<String> = compact_presented_jwp.split('.').map(String::from).collect();
+    let mut issuer_header: serde_json::Value =
+        serde_json::from_slice(&base64url_decode(&parts[0]).unwrap()).unwrap();
+    issuer_header.as_object_mut().unwrap().remove("kid");
+    parts[0] = base64url_encode_serializable(&issuer_header);
+
+    let result = JwpPresentedDecoder::decode(&parts.join("."), SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk);
+    assert!(matches!(result, Err(CustomError::InvalidPresentedProof)));
+}
+
+#[test]
+fn verify_with_wrong_key() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let compact_presented_jwp = present(&issue(&bbs_jwk), &bbs_jwk.to_public().unwrap(), "nonce");
+    let other_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256)
+        .unwrap()
+        .to_public()
+        .unwrap();
+
+    let result = JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&other_jwk);
+
+    assert!(matches!(result, Err(CustomError::InvalidPresentedProof)));
+}
+
+#[test]
+fn verify_with_flipped_payload_byte() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let compact_presented_jwp = present(&issue(&bbs_jwk), &public_jwk, "nonce");
+
+    let mut parts: Vec<String> = compact_presented_jwp.split('.').map(String::from).collect();
+    let mut payloads: Vec<String> = parts[2].split('~').map(String::from).collect();
+    let disclosed = payloads.iter().position(|p| !p.is_empty()).unwrap();
+    let mut payload = base64url_decode(&payloads[disclosed]).unwrap();
+    // Flip a byte inside the JSON string, so the payload is still well formed
+    payload[1] ^= 0x01;
+    payloads[disclosed] = base64url_encode(payload);
+    parts[2] = payloads.join("~");
+    let tampered = parts.join(".");
+
+    let result = JwpPresentedDecoder::decode(&tampered, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk);
+
+    assert!(matches!(result, Err(CustomError::InvalidPresentedProof)));
+}
+
+#[test]
+fn presentation_header_from_issued() {
+    let mut issuer_header = IssuerProtectedHeader::new(ProofAlgorithm::BBS_SHAKE256);
+    issuer_header.set_kid(Some("issuer-key-1".to_owned()));
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    let issued_jwp = JwpIssuedBuilder::new(issuer_header, jpt_claims)
+        .unwrap()
+        .build_with_proof(vec![0; ProofAlgorithm::BBS_SHAKE256.proof_len().unwrap()])
+        .unwrap();
+
+    let header = PresentationProtectedHeader::from_issued(&issued_jwp);
+
+    assert_eq!(header.alg(), PresentationProofAlgorithm::BBS_SHAKE256_PROOF);
+    assert_eq!(header.kid().map(String::as_str), Some("issuer-key-1"));
+    assert_eq!(header.aud(), None);
+    assert_eq!(header.nonce(), None);
+}
+
+#[test]
+fn redisclosable_claims_of_partial_presentation() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let compact_presented_jwp = present(&issue(&bbs_jwk), &public_jwk, "nonce");
+
+    let presented_jwp =
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+            .unwrap()
+            .verify(&public_jwk)
+            .unwrap();
+
+    assert_eq!(presented_jwp.redisclosable_claims(), vec!["iss", "vc.name"]);
+}
+
+#[test]
+fn combined_presentation_of_two_issuers() {
+    let first_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let first_public_jwk = first_jwk.to_public().unwrap();
+    let second_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Shake256).unwrap();
+    let second_public_jwk = second_jwk.to_public().unwrap();
+
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://university.example".to_owned());
+    jpt_claims.set_claim(Some("degree"), "MSc", false);
+    jpt_claims.set_claim(Some("grade"), 110, false);
+    let second_issued_jwp = JwpIssuedBuilder::new(
+        IssuerProtectedHeader::new(ProofAlgorithm::BBS_SHAKE256),
+        jpt_claims,
+    )
+    .unwrap()
+    .build(&second_jwk)
+    .unwrap();
+
+    let mut builder = JwpPresentedBuilder::combine(&[issue(&first_jwk), second_issued_jwp]);
+    builder.with_nonce("nonce").unwrap();
+    builder
+        .get_mut(0)
+        .unwrap()
+        .set_undisclosed("vc.age")
+        .unwrap();
+    builder
+        .get_mut(1)
+        .unwrap()
+        .set_undisclosed("grade")
+        .unwrap();
+    let compact = builder
+        .build(&[&first_public_jwk, &second_public_jwk])
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    let decoder =
+        JwpCombinedPresentedDecoder::decode(&compact, SerializationType::COMPACT).unwrap();
+    let combined = decoder
+        .verify(&[&first_public_jwk, &second_public_jwk])
+        .unwrap();
+    let presentations = combined.get_presentations();
+    assert_eq!(presentations.len(), 2);
+    assert_eq!(presentations[0].redisclosable_claims(), ["iss", "vc.name"]);
+    assert_eq!(presentations[1].redisclosable_claims(), ["iss", "degree"]);
+
+    assert!(decoder
+        .verify(&[&second_public_jwk, &first_public_jwk])
+        .is_err());
+    assert!(matches!(
+        decoder.verify(&[&first_public_jwk]),
+        Err(CustomError::KeyCountMismatch {
+            expected: 2,
+            got: 1
+        })
+    ));
+
+    // A presentation answering another challenge cannot be mixed in
+    let other = present(&issue(&first_jwk), &first_public_jwk, "other-nonce");
+    let (_, second) = compact.split_once(',').unwrap();
+    let mixed = JwpCombinedPresentedDecoder::decode(
+        &format!("{other},{second}"),
+        SerializationType::COMPACT,
+    )
+    .unwrap();
+    assert!(matches!(
+        mixed.verify(&[&first_public_jwk, &second_public_jwk]),
+        Err(CustomError::InvalidPresentedJwp)
+    ));
+}
+
+#[test]
+fn verify_with_distinct_issuer_and_holder_keys() {
+    let issuer_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issuer_public_jwk = issuer_jwk.to_public().unwrap();
+    let holder_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let holder_public_jwk = holder_jwk.to_public().unwrap();
+
+    let compact_presented_jwp = JwpPresentedBuilder::new(&issue(&issuer_jwk))
+        .set_presentation_protected_header(presentation_header("nonce"))
+        .set_holder_key(holder_jwk)
+        .build(&issuer_public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    let decoded_jwp =
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT).unwrap();
+
+    decoded_jwp
+        .verify_with_holder_key(&issuer_public_jwk, &holder_public_jwk)
+        .unwrap();
+    // The holder key does not verify the presentation proof, nor the issuer key the holder binding
+    assert!(matches!(
+        decoded_jwp.verify_with_holder_key(&holder_public_jwk, &holder_public_jwk),
+        Err(CustomError::InvalidPresentedProof)
+    ));
+    assert!(matches!(
+        decoded_jwp.verify_with_holder_key(&issuer_public_jwk, &issuer_public_jwk),
+        Err(CustomError::HolderBindingError)
+    ));
+}
+
+#[test]
+fn holder_binding_to_issuer_bound_key() {
+    let issuer_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issuer_public_jwk = issuer_jwk.to_public().unwrap();
+    let holder_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let other_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+
+    let mut issuer_header = IssuerProtectedHeader::new(ProofAlgorithm::BBS);
+    issuer_header.set_presentation_key(holder_jwk.to_public());
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    let issued_jwp = JwpIssuedBuilder::new(issuer_header, jpt_claims)
+        .unwrap()
+        .build(&issuer_jwk)
+        .unwrap();
+
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    builder.set_presentation_protected_header(presentation_header("nonce"));
+    assert!(matches!(
+        builder.build(&issuer_public_jwk),
+        Err(CustomError::HolderBindingError)
+    ));
+    builder.set_holder_key(other_jwk.clone());
+    assert!(matches!(
+        builder.build(&issuer_public_jwk),
+        Err(CustomError::HolderBindingError)
+    ));
+    let compact_presented_jwp = builder
+        .set_holder_key(holder_jwk)
+        .build(&issuer_public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&issuer_public_jwk)
+        .unwrap();
+
+    // Whoever replays the presentation can sign its header with another key, but not with the bound one
+    let mut parts: Vec<String> = compact_presented_jwp
+        .split('.')
+        .map(str::to_owned)
+        .collect();
+    let mut header: PresentationProtectedHeader =
+        serde_json::from_slice(&base64url_decode(&parts[1]).unwrap()).unwrap();
+    for presentation_key in [other_jwk.to_public(), None] {
+        header.set_presentation_key(presentation_key);
+        let signature = BBSplusAlgorithm::sign_holder_binding(
+            ProofAlgorithm::BBS,
+            &other_jwk,
+            &header.holder_signing_input().unwrap(),
+        )
+        .unwrap();
+        header.set_holder_signature(Some(base64url_encode(&signature)));
+        parts[1] = base64url_encode(header.canonical_bytes().unwrap());
+
+        let result = JwpPresentedDecoder::decode(&parts.join("."), SerializationType::COMPACT)
+            .unwrap()
+            .verify(&issuer_public_jwk);
+        assert!(matches!(result, Err(CustomError::HolderBindingError)));
+    }
+}
+
+#[test]
+fn holder_signature_with_signing_key() {
+    let issuer_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let issuer_public_jwk = issuer_jwk.to_public().unwrap();
+    let issued_jwp = issue(&issuer_jwk);
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    builder.set_presentation_protected_header(presentation_header("nonce"));
+
+    // A holder key is used to sign, not to generate proofs
+    let mut holder_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    holder_jwk.set_key_ops(vec![KeyOps::ProofGeneration]);
+    assert!(matches!(
+        builder
+            .set_holder_key(holder_jwk.clone())
+            .build(&issuer_public_jwk),
+        Err(CustomError::KeyOpsNotPermitted(KeyOps::Sign))
+    ));
+    let public_holder_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256)
+        .unwrap()
+        .to_public()
+        .unwrap();
+    assert!(matches!(
+        builder
+            .set_holder_key(public_holder_jwk)
+            .build(&issuer_public_jwk),
+        Err(CustomError::HolderSignatureError(_))
+    ));
+
+    holder_jwk.set_key_ops(vec![KeyOps::Sign]);
+    let compact_presented_jwp = builder
+        .set_holder_key(holder_jwk.clone())
+        .build(&issuer_public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+    JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .verify_with_holder_key(&issuer_public_jwk, &holder_jwk.to_public().unwrap())
+        .unwrap();
+}
+
+#[test]
+fn claims_withheld_by_selective_disclosure_example() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_iss("https://issuer.example".to_owned());
+    jpt_claims.set_claim(
+        Some("vc"),
+        serde_json::json!({
+            "degree": {
+                "type": "BachelorDegree",
+                "name": "Bachelor of Science and Arts",
+                "ciao": [{"u1": "value1"}, {"u2": "value2"}]
+            },
+            "name": "John Doe"
+        }),
+        true,
+    );
+    let issued_jwp =
+        JwpIssuedBuilder::new(IssuerProtectedHeader::new(ProofAlgorithm::BBS), jpt_claims)
+            .unwrap()
+            .build(&bbs_jwk)
+            .unwrap();
+
+    let presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(PresentationProtectedHeader::from_issued(&issued_jwp))
+        .set_undisclosed("vc.degree.name")
+        .unwrap()
+        .set_undisclosed("vc.degree.ciao[0].u1")
+        .unwrap()
+        .set_undisclosed("vc.name")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap();
+
+    assert_eq!(
+        claims_withheld(&issued_jwp, &presented_jwp).unwrap(),
+        ["vc.degree.name", "vc.degree.ciao[0].u1", "vc.name"]
+    );
+
+    let other_presented_jwp = issue(&bbs_jwk)
+        .present_all(presentation_header("nonce"), &public_jwk)
+        .unwrap();
+    assert!(matches!(
+        claims_withheld(&issued_jwp, &other_presented_jwp),
+        Err(CustomError::UnrelatedPresentation)
+    ));
+}
+
+#[test]
+fn disclose_only_listed_claims() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let issued_jwp = issue(&bbs_jwk);
+
+    let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+    builder.set_presentation_protected_header(presentation_header("nonce"));
+    assert!(matches!(
+        builder.disclose_only(&["vc.name", "vc.email"]),
+        Err(CustomError::SelectiveDisclosureError)
+    ));
+    assert_eq!(builder.disclosure_summary().undisclosed, 0);
+
+    let presented_jwp = builder
+        .disclose_only(&["vc.name"])
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap();
+    assert_eq!(presented_jwp.redisclosable_claims(), ["vc.name"]);
+    assert_eq!(
+        presented_jwp.get_payloads().get_undisclosed_indexes().len(),
+        issued_jwp.disclosable_paths().len() - 1
+    );
+
+    JwpPresentedDecoder::decode(
+        &presented_jwp.encode(SerializationType::COMPACT).unwrap(),
+        SerializationType::COMPACT,
+    )
+    .unwrap()
+    .verify(&public_jwk)
+    .unwrap();
+}
+
+#[test]
+fn validate_structure_without_key() {
+    let bbs_jwk = Jwk::generate(KeyPairSubtype::BLS12381G2Sha256).unwrap();
+    let public_jwk = bbs_jwk.to_public().unwrap();
+    let compact_presented_jwp = present(&issue(&bbs_jwk), &public_jwk, "nonce");
+    JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT)
+        .unwrap()
+        .validate_structure()
+        .unwrap();
+
+    // Drop the last bytes of the proof: the token still decodes, but cannot be a valid presentation
+    let (rest, proof) = compact_presented_jwp.rsplit_once('.').unwrap();
+    let mut proof = base64url_decode(proof).unwrap();
+    proof.truncate(proof.len() - 8);
+    let truncated = format!("{rest}.{}", base64url_encode(proof));
+    let decoded_jwp = JwpPresentedDecoder::decode(&truncated, SerializationType::COMPACT).unwrap();
+    assert!(matches!(
+        decoded_jwp.validate_structure(),
+        Err(CustomError::InvalidProofLength { .. })
+    ));
+
+    // An issuer header that cannot be presented with the presentation algorithm
+    let mut parts: Vec<String> = compact_presented_jwp.split('.').map(String::from).collect();
+    parts[1] = base64url_encode_serializable(PresentationProtectedHeader::new(
+        PresentationProofAlgorithm::BBS_SHAKE256_PROOF,
+    ));
+    let mismatched = parts.join(".");
+    assert!(matches!(
+        JwpPresentedDecoder::decode(&mismatched, SerializationType::COMPACT)
+            .unwrap()
+            .validate_structure(),
+        Err(CustomError::AlgorithmMismatch { .. })
+    ));
+}
+
+#[cfg(feature = "rng")]
+#[test]
+fn build_with_seeded_rng_is_reproducible() {
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+    for (subtype, alg) in [
+        (
+            KeyPairSubtype::BLS12381G2Sha256,
+            PresentationProofAlgorithm::BBS_PROOF,
+        ),
+        (
+            KeyPairSubtype::BLS12381G2Shake256,
+            PresentationProofAlgorithm::BBS_SHAKE256_PROOF,
+        ),
+    ] {
+        let bbs_jwk = Jwk::generate(subtype).unwrap();
+        let public_jwk = bbs_jwk.to_public().unwrap();
+        let mut jpt_claims = JptClaims::new();
+        jpt_claims.set_iss("https://issuer.example".to_owned());
+        jpt_claims.set_claim(
+            Some("vc"),
+            serde_json::json!({"name": "John Doe", "age": 42}),
+            true,
+        );
+        let issued_jwp =
+            JwpIssuedBuilder::new(IssuerProtectedHeader::new(subtype.proof_alg()), jpt_claims)
+                .unwrap()
+                .build(&bbs_jwk)
+                .unwrap();
+
+        let mut header = PresentationProtectedHeader::new(alg);
+        header.set_nonce(Some("nonce".to_owned()));
+        let mut builder = JwpPresentedBuilder::new(&issued_jwp);
+        builder
+            .set_presentation_protected_header(header)
+            .set_undisclosed("vc.age")
+            .unwrap();
+        let present = |seed: u64| {
+            builder
+                .build_with_rng(&public_jwk, &mut ChaCha20Rng::seed_from_u64(seed))
+                .unwrap()
+                .encode(SerializationType::COMPACT)
+                .unwrap()
+        };
+
+        let presented_jwp = present(7);
+        assert_eq!(presented_jwp, present(7));
+        assert_ne!(presented_jwp, present(8));
+
+        let decoded = JwpPresentedDecoder::decode(&presented_jwp, SerializationType::COMPACT)
+            .unwrap()
+            .verify(&public_jwk)
+            .unwrap();
+        assert_eq!(decoded.get_payloads().get_undisclosed_indexes(), [2]);
+    }
+}
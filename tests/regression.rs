@@ -0,0 +1,83 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Regression tests for the BBS (SHA-256 ciphersuite) algorithms.
+//!
+//! The fixtures were produced by this crate: they catch changes of its own output (e.g. a zkryptium upgrade that
+//! changes the BBS signatures), but do not show conformance to the JPA draft, and are not a substitute for its
+//! examples. The crate has no test vectors from the draft.
+
+#![cfg(feature = "std")]
+
+use jsonprooftoken::{
+    encoding::SerializationType,
+    jpa::algs::ProofAlgorithm,
+    jpt::claims::JptClaims,
+    jwk::key::Jwk,
+    jwp::{
+        header::IssuerProtectedHeader,
+        issued::{JwpIssuedBuilder, JwpIssuedDecoder},
+        presented::JwpPresentedDecoder,
+    },
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Vector {
+    jwk: Jwk,
+    claims: JptClaims,
+    issued: String,
+    presented: String,
+}
+
+fn bbs_sha256() -> Vector {
+    serde_json::from_str(include_str!("fixtures/bbs_sha256.json")).unwrap()
+}
+
+#[test]
+fn bbs_sha256_issued() {
+    let vector = bbs_sha256();
+
+    let issued_jwp = JwpIssuedBuilder::new(
+        IssuerProtectedHeader::new(ProofAlgorithm::BBS),
+        vector.claims,
+    )
+    .unwrap()
+    .build(&vector.jwk)
+    .unwrap();
+    assert_eq!(
+        issued_jwp.encode(SerializationType::COMPACT).unwrap(),
+        vector.issued
+    );
+
+    let decoded_issued_jwp = JwpIssuedDecoder::decode(&vector.issued, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&vector.jwk.to_public().unwrap())
+        .unwrap();
+    assert_eq!(decoded_issued_jwp, issued_jwp);
+}
+
+#[test]
+fn bbs_sha256_presented() {
+    let vector = bbs_sha256();
+
+    let presented_jwp = JwpPresentedDecoder::decode(&vector.presented, SerializationType::COMPACT)
+        .unwrap()
+        .verify(&vector.jwk.to_public().unwrap())
+        .unwrap();
+    assert_eq!(
+        presented_jwp.get_payloads().get_undisclosed_indexes(),
+        vec![2]
+    );
+}
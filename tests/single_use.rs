@@ -0,0 +1,121 @@
+// Copyright 2023 Fondazione LINKS
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+//     http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "std")]
+
+use jsonprooftoken::{
+    encoding::{base64url_decode, SerializationType},
+    errors::CustomError,
+    jpa::{
+        algs::{PresentationProofAlgorithm, ProofAlgorithm},
+        single_use::SingleUseAlgorithm,
+    },
+    jpt::claims::JptClaims,
+    jwk::{key::Jwk, types::KeyPairSubtype},
+    jwp::{
+        header::{IssuerProtectedHeader, PresentationProtectedHeader},
+        issued::{JwpIssued, JwpIssuedBuilder, JwpIssuedDecoder},
+        presented::{JwpPresentedBuilder, JwpPresentedDecoder},
+    },
+};
+
+fn issue(jwk: &Jwk) -> JwpIssued {
+    let mut jpt_claims = JptClaims::new();
+    jpt_claims.set_claim(Some("name"), "John Doe", false);
+    jpt_claims.set_claim(Some("email"), "john@example.com", false);
+    jpt_claims.set_claim(Some("age"), 42, false);
+    jpt_claims.set_claim(Some("country"), "IT", false);
+
+    JwpIssuedBuilder::new(
+        IssuerProtectedHeader::new(ProofAlgorithm::SU_ES256),
+        jpt_claims,
+    )
+    .unwrap()
+    .build(jwk)
+    .unwrap()
+}
+
+#[test]
+fn issue_and_verify_su_es256() {
+    let jwk = Jwk::generate(KeyPairSubtype::P256).unwrap();
+    let compact_issued_jwp = issue(&jwk).encode(SerializationType::COMPACT).unwrap();
+
+    let decoded_jwp =
+        JwpIssuedDecoder::decode(&compact_issued_jwp, SerializationType::COMPACT).unwrap();
+    assert_eq!(
+        decoded_jwp.get_proof().len(),
+        SingleUseAlgorithm::issuer_proof_len(4)
+    );
+    decoded_jwp.verify(&jwk.to_public().unwrap()).unwrap();
+
+    let other_jwk = Jwk::generate(KeyPairSubtype::P256).unwrap();
+    assert!(matches!(
+        decoded_jwp.verify(&other_jwk.to_public().unwrap()),
+        Err(CustomError::InvalidIssuedProof)
+    ));
+}
+
+#[test]
+fn present_two_of_four_payloads_su_es256() {
+    let jwk = Jwk::generate(KeyPairSubtype::P256).unwrap();
+    let public_jwk = jwk.to_public().unwrap();
+    let issued_jwp = issue(&jwk);
+
+    let mut header = PresentationProtectedHeader::new(PresentationProofAlgorithm::SU_ES256);
+    header.set_nonce(Some("nonce".to_owned()));
+    let compact_presented_jwp = JwpPresentedBuilder::new(&issued_jwp)
+        .set_presentation_protected_header(header)
+        .set_undisclosed("email")
+        .unwrap()
+        .set_undisclosed("country")
+        .unwrap()
+        .build(&public_jwk)
+        .unwrap()
+        .encode(SerializationType::COMPACT)
+        .unwrap();
+
+    let decoded_jwp =
+        JwpPresentedDecoder::decode(&compact_presented_jwp, SerializationType::COMPACT).unwrap();
+    decoded_jwp.verify(&public_jwk).unwrap();
+
+    // The proof keeps the four ephemeral keys, but only the signatures of `name` and `age`
+    let proof = base64url_decode(compact_presented_jwp.split('.').nth(3).unwrap()).unwrap();
+    assert_eq!(
+        proof.len(),
+        SingleUseAlgorithm::presentation_proof_len(4, 2)
+    );
+    let issued_proof = issued_jwp.get_proof();
+    let payload_signature = |index: usize| {
+        let start =
+            SingleUseAlgorithm::issuer_proof_len(index) + SingleUseAlgorithm::EPHEMERAL_KEY_LEN;
+        &issued_proof[start..start + SingleUseAlgorithm::SIGNATURE_LEN]
+    };
+    let signatures_start = SingleUseAlgorithm::presentation_proof_len(4, 0);
+    assert_eq!(
+        proof[signatures_start..],
+        [payload_signature(0), payload_signature(2)].concat()
+    );
+
+    // Disclosing a hidden payload without its signature breaks the proof
+    let mut parts: Vec<String> = compact_presented_jwp.split('.').map(String::from).collect();
+    let compact_issued_jwp = issued_jwp.encode(SerializationType::COMPACT).unwrap();
+    parts[2] = compact_issued_jwp.split('.').nth(1).unwrap().to_owned();
+    let result = JwpPresentedDecoder::decode(&parts.join("."), SerializationType::COMPACT)
+        .unwrap()
+        .verify(&public_jwk);
+    assert!(matches!(
+        result,
+        Err(CustomError::InvalidProofLength { .. })
+    ));
+}
@@ -0,0 +1,38 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+
+use jsonprooftoken::wasm::{
+    js_decode_verify_issued, js_decode_verify_presented, js_issue, js_present,
+};
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const JWK: &str = r#"{
+    "kty": "EC",
+    "crv": "BLS12381G2",
+    "x": "A8jNBDG2TREwU6V73pqJxIJC5IglUL7CXvRQQKMNHK14YiOX2nlIkEUz31aTBmdMBzJxBGC0Tp8XF9cIjX2kmLN0Bz8GsZSOzLIITMN7jG7KWRXFx_nW_z_BOdpDexkt",
+    "y": "DQxxijjGCzuLCT2NsQtJW3HLBEXLxVwuikuL8m7Zgz0GpY-7fjcrnvBgOcSxE9KNDcRyHxZ2yvYqybG78k81_pv1hWAH4IHBit1OCfZxb94ODvnP5sgz90GtdgobINkb",
+    "d": "KaonXCy2Hk11qZXIdZDzZXHQkLNRo-iR6viuck08KF8"
+}"#;
+
+const PUBLIC_JWK: &str = r#"{
+    "kty": "EC",
+    "crv": "BLS12381G2",
+    "x": "A8jNBDG2TREwU6V73pqJxIJC5IglUL7CXvRQQKMNHK14YiOX2nlIkEUz31aTBmdMBzJxBGC0Tp8XF9cIjX2kmLN0Bz8GsZSOzLIITMN7jG7KWRXFx_nW_z_BOdpDexkt",
+    "y": "DQxxijjGCzuLCT2NsQtJW3HLBEXLxVwuikuL8m7Zgz0GpY-7fjcrnvBgOcSxE9KNDcRyHxZ2yvYqybG78k81_pv1hWAH4IHBit1OCfZxb94ODvnP5sgz90GtdgobINkb"
+}"#;
+
+#[wasm_bindgen_test]
+fn issue_present_verify_round_trip() {
+    let header = r#"{"typ": "JPT", "alg": "BBS"}"#;
+    let claims = r#"{"iss": "https://issuer.example", "name": "Alice", "age": 42}"#;
+    let issued = js_issue(header, claims, JWK).unwrap();
+
+    assert!(js_decode_verify_issued(&issued, PUBLIC_JWK).is_ok());
+
+    let presentation_header =
+        r#"{"alg": "BBS-PROOF", "aud": "https://verifier.example", "nonce": "wrmBRkKtXjQ"}"#;
+    let presented = js_present(&issued, presentation_header, r#"["age"]"#, PUBLIC_JWK).unwrap();
+
+    assert!(js_decode_verify_presented(&presented, PUBLIC_JWK).is_ok());
+}